@@ -0,0 +1,335 @@
+//! Web/API 认证：登录会话 Cookie 与 API 令牌
+//!
+//! `/api/*` 受保护的路由按所需权限（[`Scope`]）挂上 [`require`] 中间件：
+//! 带 `Authorization: Bearer <token>` 的请求按 `[[web.tokens]]` 校验令牌及其权限，
+//! 否则校验登录会话：`[web]` 密码登录的会话拥有全部权限，用户登录的会话按其角色
+//! （见 [`crate::users`]），未设置 Web 密码且没有用户时直接放行。
+//! 未认证返回 `401`，权限不足返回 `403`，响应体仍为 `{"success": false, "message": ...}`。
+//! 认证通过后按身份检查请求预算（见 [`crate::ratelimit`]）。
+
+use anyhow::Result;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use subtle::ConstantTimeEq;
+use tracing::{debug, warn};
+
+use crate::config::{ApiTokenConfig, Scope};
+use crate::session::Session;
+use crate::users::User;
+use crate::{AppState, SharedState};
+
+/// 登录会话令牌所在的 Cookie（见 [`crate::session`]）
+pub const AUTH_COOKIE_NAME: &str = "baidupcs_auth";
+
+/// 发起请求的身份，由 [`require`] 放入请求扩展
+#[derive(Debug, Clone)]
+pub enum Principal {
+    /// 未设置 Web 密码且没有用户
+    Anonymous,
+    /// `[web]` 密码登录的会话（管理员）
+    Session(Session),
+    /// 用户登录的会话
+    User { session: Session, user: User },
+    /// API 令牌
+    Token { name: String, scopes: Vec<Scope> },
+}
+
+impl Principal {
+    /// 是否拥有 `scope` 权限
+    pub fn allows(&self, scope: Scope) -> bool {
+        match self {
+            Principal::Anonymous | Principal::Session(_) => true,
+            Principal::User { user, .. } => user.role.allows(scope),
+            Principal::Token { scopes, .. } => scopes.iter().any(|s| s.covers(scope)),
+        }
+    }
+
+    /// 登录的用户；管理员密码、API 令牌和匿名访问时为 `None`（不受目录、账号和额度限制）
+    pub fn user(&self) -> Option<&User> {
+        match self {
+            Principal::User { user, .. } => Some(user),
+            _ => None,
+        }
+    }
+
+    /// 身份标识，用于按身份计数请求预算和记录任务归属；匿名访问时为 `None`（只按 IP 限流）
+    pub fn identity(&self) -> Option<String> {
+        match self {
+            Principal::Anonymous => None,
+            Principal::Session(_) => Some("admin".to_string()),
+            Principal::User { user, .. } => Some(format!("user:{}", user.name)),
+            Principal::Token { name, .. } => Some(format!("token:{}", name)),
+        }
+    }
+
+    /// 发起请求的登录会话
+    pub fn session(&self) -> Option<&Session> {
+        match self {
+            Principal::Session(session) | Principal::User { session, .. } => Some(session),
+            _ => None,
+        }
+    }
+}
+
+/// 认证失败：`401` 未认证 / `403` 权限不足
+#[derive(Debug)]
+pub struct AuthError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl AuthError {
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::UNAUTHORIZED,
+            message: message.into(),
+        }
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::FORBIDDEN,
+            message: message.into(),
+        }
+    }
+
+    /// 令牌或用户缺少 `scope` 权限
+    pub fn missing_scope(principal: &Principal, scope: Scope) -> Self {
+        let who = match principal {
+            Principal::Token { name, .. } => format!("API 令牌 {}", name),
+            Principal::User { user, .. } => format!("用户 {}", user.name),
+            _ => "当前登录".to_string(),
+        };
+        Self::forbidden(format!("{} 没有 {} 权限", who, scope.as_str()))
+    }
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let mut resp = (
+            self.status,
+            Json(serde_json::json!({ "success": false, "message": self.message })),
+        )
+            .into_response();
+        if self.status == StatusCode::UNAUTHORIZED {
+            resp.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static("Bearer"),
+            );
+        }
+        resp
+    }
+}
+
+/// 中间件：要求 `scope` 权限，通过后将 [`Principal`] 放入请求扩展
+pub async fn require(
+    State((shared, scope)): State<(SharedState, Scope)>,
+    cookies: CookieJar,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, AuthError> {
+    let state = shared.load();
+    let principal = match bearer_token(req.headers()) {
+        Some(token) => {
+            let config = find_token(&state.config.web.tokens, token)
+                .ok_or_else(|| AuthError::unauthorized("API 令牌无效"))?;
+            let route = format!("{} {}", req.method(), req.uri().path());
+            state.tokens.record_use(&config.name, &route);
+            debug!("🔑 API 令牌 {}: {}", config.name, route);
+            Principal::Token {
+                name: config.name.clone(),
+                scopes: config.scopes.clone(),
+            }
+        }
+        None if !state.login_required() => Principal::Anonymous,
+        None => {
+            let session = current_session(&state, &cookies)
+                .ok_or_else(|| AuthError::unauthorized("未登录，请先登录"))?;
+            match &session.user {
+                // 每次请求重新读取用户，删除用户或修改角色立即生效
+                Some(name) => Principal::User {
+                    user: state
+                        .users
+                        .get(name)
+                        .ok_or_else(|| AuthError::unauthorized("用户不存在，请重新登录"))?,
+                    session,
+                },
+                None => Principal::Session(session),
+            }
+        }
+    };
+    if !principal.allows(scope) {
+        return Err(AuthError::missing_scope(&principal, scope));
+    }
+    if let Some(key) = principal.identity() {
+        if let Err(limited) =
+            state
+                .limiter
+                .check(&state.config.web.rate_limit, &key, req.uri().path())
+        {
+            return Ok(limited.into_response());
+        }
+    }
+
+    req.extensions_mut().insert(principal);
+    Ok(next.run(req).await)
+}
+
+/// `Authorization: Bearer <token>` 中的令牌，认证方案不区分大小写（RFC 7235）
+///
+/// CSRF 检查也据此判断请求是否使用令牌而不是登录 Cookie。
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.trim_start().split_once(' ')?;
+    scheme.eq_ignore_ascii_case("Bearer").then(|| token.trim())
+}
+
+/// 当前请求的登录会话（刷新最后活动时间）
+pub fn current_session(state: &AppState, cookies: &CookieJar) -> Option<Session> {
+    let token = cookies.get(AUTH_COOKIE_NAME)?;
+    state.sessions.touch(token.value(), &state.config.web)
+}
+
+/// 以常量时间比较查找令牌（逐个比较全部令牌，不提前返回）
+fn find_token<'a>(tokens: &'a [ApiTokenConfig], token: &str) -> Option<&'a ApiTokenConfig> {
+    tokens.iter().fold(None, |found, candidate| {
+        let matched = bool::from(candidate.token.as_bytes().ct_eq(token.as_bytes()));
+        found.or(matched.then_some(candidate))
+    })
+}
+
+/// 一个令牌的使用记录
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// 最近一次访问的接口，如 `POST /api/transfer`
+    pub last_route: Option<String>,
+    pub use_count: u64,
+}
+
+/// API 令牌的使用记录（按令牌名称），保存在数据目录中，重启后保留
+#[derive(Debug, Default)]
+pub struct TokenStore {
+    usage: Mutex<HashMap<String, TokenUsage>>,
+    file: Option<PathBuf>,
+}
+
+impl TokenStore {
+    /// 从文件加载使用记录，文件不存在或损坏时从空记录开始
+    pub fn load(file: PathBuf) -> Self {
+        let usage = match std::fs::read_to_string(&file) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!(
+                    "⚠️ 读取 API 令牌使用记录失败，忽略: {} ({})",
+                    file.display(),
+                    e
+                );
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        Self {
+            usage: Mutex::new(usage),
+            file: Some(file),
+        }
+    }
+
+    pub fn record_use(&self, name: &str, route: &str) {
+        let mut usage = self.usage.lock().unwrap();
+        let entry = usage.entry(name.to_string()).or_default();
+        entry.last_used_at = Some(Utc::now());
+        entry.last_route = Some(route.to_string());
+        entry.use_count += 1;
+    }
+
+    pub fn usage(&self, name: &str) -> TokenUsage {
+        self.usage
+            .lock()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// 写入使用记录文件（没有任何记录时不创建文件）
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        let content = {
+            let usage = self.usage.lock().unwrap();
+            if usage.is_empty() {
+                return Ok(());
+            }
+            serde_json::to_string_pretty(&*usage)?
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_scopes() {
+        let tokens = vec![
+            ApiTokenConfig {
+                name: "ci".to_string(),
+                token: "0123456789abcdef".to_string(),
+                scopes: vec![Scope::Transfer],
+            },
+            ApiTokenConfig {
+                name: "ops".to_string(),
+                token: "fedcba9876543210".to_string(),
+                scopes: vec![Scope::Admin],
+            },
+        ];
+        assert!(find_token(&tokens, "0123456789abcde").is_none());
+        let ci = find_token(&tokens, "0123456789abcdef").unwrap();
+        assert_eq!(ci.name, "ci");
+
+        let principal = Principal::Token {
+            name: ci.name.clone(),
+            scopes: ci.scopes.clone(),
+        };
+        assert!(principal.allows(Scope::Transfer));
+        assert!(principal.allows(Scope::Preview));
+        assert!(!principal.allows(Scope::Fs));
+        assert!(!principal.allows(Scope::Admin));
+
+        let ops = find_token(&tokens, "fedcba9876543210").unwrap();
+        assert!(ops.scopes[0].covers(Scope::Fs));
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        let headers = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::AUTHORIZATION, value.parse().unwrap());
+            headers
+        };
+        assert_eq!(bearer_token(&headers("Bearer abc")), Some("abc"));
+        assert_eq!(bearer_token(&headers("bearer abc")), Some("abc"));
+        assert_eq!(bearer_token(&headers("BEARER  abc ")), Some("abc"));
+        assert_eq!(bearer_token(&headers("Basic YWJj")), None);
+        assert_eq!(bearer_token(&headers("Bearerabc")), None);
+        assert_eq!(bearer_token(&HeaderMap::new()), None);
+    }
+}
@@ -0,0 +1,90 @@
+//! 账号信息与 Cookie 有效性检查
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use super::error::BaiduError;
+use super::quota::get_quota;
+use super::types::QuotaInfo;
+use crate::state::Account;
+
+/// 当前登录账号信息
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountInfo {
+    pub username: String,
+    pub uk: u64,
+    /// 0: 普通用户, 1: 会员, 2: 超级会员
+    pub vip_type: u8,
+    pub vip_level: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quota: Option<QuotaInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UinfoResponse {
+    errno: i32,
+    #[serde(default)]
+    baidu_name: String,
+    #[serde(default)]
+    netdisk_name: String,
+    #[serde(default)]
+    uk: u64,
+    #[serde(default)]
+    vip_type: u8,
+}
+
+/// 查询当前 Cookie 对应的账号（用户名、uk、会员等级、容量）
+///
+/// Cookie 失效时返回 [`BaiduError::CookieInvalid`]。
+pub async fn whoami(account: &Account) -> Result<AccountInfo> {
+    let url = account.api.pan_url("/rest/2.0/xpan/nas?method=uinfo");
+
+    let text = account
+        .api
+        .get(&url)
+        .header("Referer", account.api.pan_url("/disk/home"))
+        .send()
+        .await?
+        .text()
+        .await?;
+    debug!("uinfo 响应: {}", text);
+
+    let res: UinfoResponse = serde_json::from_str(&text)
+        .map_err(|e| anyhow!("解析 uinfo 响应失败: {}, body={}", e, text))?;
+
+    match res.errno {
+        0 => {}
+        -6 | -4 => return Err(BaiduError::CookieInvalid { errno: res.errno }.into()),
+        errno => return Err(anyhow!("查询账号信息失败: errno={}", errno)),
+    }
+
+    // 容量查询失败不影响账号信息
+    let quota = match get_quota(account).await {
+        Ok(q) => Some(q),
+        Err(e) => {
+            warn!("⚠️ 查询网盘容量失败: {}", e);
+            None
+        }
+    };
+
+    Ok(AccountInfo {
+        username: if res.baidu_name.is_empty() {
+            res.netdisk_name
+        } else {
+            res.baidu_name
+        },
+        uk: res.uk,
+        vip_type: res.vip_type,
+        vip_level: vip_level_name(res.vip_type),
+        quota,
+    })
+}
+
+fn vip_level_name(vip_type: u8) -> &'static str {
+    match vip_type {
+        1 => "会员",
+        2 => "超级会员",
+        _ => "普通用户",
+    }
+}
@@ -0,0 +1,40 @@
+//! 错误类型
+//!
+//! 需要调用方区分处理的错误使用具体类型，其余仍走 anyhow。
+//! 调用方可通过 `err.downcast_ref::<BaiduError>()` 判断。
+
+use thiserror::Error;
+
+use super::quota::format_size;
+
+#[derive(Debug, Error)]
+pub enum BaiduError {
+    /// 网盘剩余空间不足以容纳本次转存
+    #[error(
+        "网盘空间不足: 需要 {}，剩余 {}",
+        format_size(*required),
+        format_size(*free)
+    )]
+    QuotaExceeded { required: u64, free: u64 },
+
+    /// BDUSS/STOKEN 已失效或未登录
+    #[error("Cookie 已失效或未登录 (errno={errno})，请重新获取 BDUSS 和 STOKEN（可运行 login 子命令扫码登录）")]
+    CookieInvalid { errno: i32 },
+
+    /// 账号在部分批次已写入网盘后变为不可用（Cookie 失效、空间不足），已写入的文件留在该账号中，
+    /// 不再换账号重试整个计划
+    #[error(
+        "账号 {account} 已转存 {chunks}/{total} 批（{files} 个文件）后不可用，未换账号重试: {message}"
+    )]
+    PartialTransfer {
+        account: String,
+        chunks: usize,
+        total: usize,
+        files: usize,
+        message: String,
+    },
+
+    /// 经由代理连接失败（代理不可达、认证失败或代理无法连到百度），与百度返回的错误区分
+    #[error("代理连接失败 ({proxy}): {message}")]
+    Proxy { proxy: String, message: String },
+}
@@ -0,0 +1,518 @@
+//! 自己网盘的文件管理：列目录、创建目录、重命名、移动、复制、删除
+//!
+//! 列目录走 `api/list`，写操作走 `api/filemanager`（同步模式）。
+//! Cookie 失效（errno=-6）时返回 [`BaiduError::CookieInvalid`]，调用方据此把账号标记为不可用。
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use super::error::BaiduError;
+use crate::state::Account;
+
+/// `api/list` 单页条数上限
+const PAGE_SIZE: usize = 1000;
+
+/// 网盘中的文件或目录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileEntry {
+    pub fs_id: u64,
+    pub path: String,
+    pub server_filename: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default, deserialize_with = "bool_from_int")]
+    pub isdir: bool,
+    #[serde(default)]
+    pub server_mtime: u64,
+}
+
+/// 目录树节点
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeNode {
+    #[serde(flatten)]
+    pub entry: FileEntry,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TreeNode>,
+}
+
+/// 递归列目录的上限，`None` 表示不限制
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ListLimits {
+    /// 最多展开的层数，1 表示只列出目录本身的内容
+    pub max_depth: Option<usize>,
+    /// 最多返回的条目数（也限制了列目录请求的次数）
+    pub max_entries: Option<usize>,
+}
+
+/// 递归列目录的结果，`truncated` 表示达到上限、还有内容没有列出
+#[derive(Debug, Clone, Serialize)]
+pub struct Listing<T> {
+    pub entries: Vec<T>,
+    pub truncated: bool,
+}
+
+fn bool_from_int<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(u8::deserialize(deserializer)? != 0)
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiListResponse {
+    errno: i32,
+    #[serde(default)]
+    list: Vec<FileEntry>,
+}
+
+/// 列出目录内容（自动翻页）
+pub async fn list_dir(account: &Account, dir: &str) -> Result<Vec<FileEntry>> {
+    list_pages(account, dir, None).await
+}
+
+/// 列出目录内容，取到 `max` 条后不再请求下一页（返回的条目可能多于 `max`）
+async fn list_pages(account: &Account, dir: &str, max: Option<usize>) -> Result<Vec<FileEntry>> {
+    let mut entries = Vec::new();
+    let mut page = 1;
+
+    loop {
+        let url = account.api.pan_url(&format!(
+            "/api/list?dir={}&page={}&num={}&order=name&desc=0&web=1&channel=chunlei&clienttype=0",
+            urlencoding::encode(dir),
+            page,
+            PAGE_SIZE
+        ));
+
+        let resp = account
+            .api
+            .get(&url)
+            .header("Referer", account.api.pan_url("/disk/home"))
+            .send()
+            .await?;
+
+        let text = resp.text().await?;
+        debug!(
+            "list 响应 (dir={}, page={}): {}",
+            dir,
+            page,
+            text.chars().take(200).collect::<String>()
+        );
+
+        let res: ApiListResponse = serde_json::from_str(&text)
+            .map_err(|e| anyhow!("解析 list 响应失败: {}, body={}", e, text))?;
+
+        if res.errno == -6 {
+            return Err(BaiduError::CookieInvalid { errno: res.errno }.into());
+        }
+        if res.errno != 0 {
+            let hint = match res.errno {
+                -9 => "目录不存在",
+                _ => "列目录失败",
+            };
+            return Err(anyhow!("{}: {} (errno={})", hint, dir, res.errno));
+        }
+
+        let count = res.list.len();
+        entries.extend(res.list);
+        if count < PAGE_SIZE || max.is_some_and(|max| entries.len() >= max) {
+            break;
+        }
+        page += 1;
+    }
+
+    Ok(entries)
+}
+
+/// 递归列出目录下的所有文件和目录，达到 `limits` 时停止
+pub async fn list_recursive(
+    account: &Account,
+    dir: &str,
+    limits: ListLimits,
+) -> Result<Listing<FileEntry>> {
+    let mut listing = Listing {
+        entries: Vec::new(),
+        truncated: false,
+    };
+    let mut pending = vec![(dir.to_string(), 1)];
+
+    while let Some((current, depth)) = pending.pop() {
+        let remaining = limits.max_entries.map(|max| max - listing.entries.len());
+        if remaining == Some(0) {
+            listing.truncated = true;
+            break;
+        }
+        for entry in list_pages(account, &current, remaining).await? {
+            if limits
+                .max_entries
+                .is_some_and(|max| listing.entries.len() >= max)
+            {
+                listing.truncated = true;
+                return Ok(listing);
+            }
+            if entry.isdir {
+                if limits.max_depth.is_none_or(|max| depth < max) {
+                    pending.push((entry.path.clone(), depth + 1));
+                } else {
+                    listing.truncated = true;
+                }
+            }
+            listing.entries.push(entry);
+        }
+    }
+
+    Ok(listing)
+}
+
+/// 构建目录树，达到 `limits` 时停止展开
+pub async fn tree(account: &Account, dir: &str, limits: ListLimits) -> Result<Listing<TreeNode>> {
+    let mut count = 0;
+    let mut truncated = false;
+    let entries = tree_level(account, dir, 1, limits, &mut count, &mut truncated).await?;
+    Ok(Listing { entries, truncated })
+}
+
+/// 目录树的一层，`depth` 从 1 开始，`count` 为已列出的条目数
+async fn tree_level(
+    account: &Account,
+    dir: &str,
+    depth: usize,
+    limits: ListLimits,
+    count: &mut usize,
+    truncated: &mut bool,
+) -> Result<Vec<TreeNode>> {
+    let mut nodes = Vec::new();
+    let remaining = limits.max_entries.map(|max| max - *count);
+    if remaining == Some(0) {
+        *truncated = true;
+        return Ok(nodes);
+    }
+    for entry in list_pages(account, dir, remaining).await? {
+        if limits.max_entries.is_some_and(|max| *count >= max) {
+            *truncated = true;
+            break;
+        }
+        *count += 1;
+        let children = if !entry.isdir {
+            Vec::new()
+        } else if limits.max_depth.is_none_or(|max| depth < max) {
+            let path = entry.path.clone();
+            Box::pin(tree_level(
+                account,
+                &path,
+                depth + 1,
+                limits,
+                count,
+                truncated,
+            ))
+            .await?
+        } else {
+            *truncated = true;
+            Vec::new()
+        };
+        nodes.push(TreeNode { entry, children });
+    }
+    Ok(nodes)
+}
+
+/// 判断目录是否存在
+pub async fn exists(account: &Account, path: &str) -> Result<bool> {
+    let url = account.api.pan_url(&format!(
+        "/api/list?dir={}&num=1&order=name&desc=0",
+        urlencoding::encode(path)
+    ));
+    let text = account.api.get(&url).send().await?.text().await?;
+
+    #[derive(Deserialize)]
+    struct Probe {
+        errno: i32,
+    }
+    let probe: Probe = serde_json::from_str(&text)
+        .map_err(|e| anyhow!("解析 list 响应失败: {}, body={}", e, text))?;
+
+    match probe.errno {
+        0 => Ok(true),
+        -9 => Ok(false),
+        -6 => Err(BaiduError::CookieInvalid { errno: -6 }.into()),
+        errno => Err(anyhow!("检查路径失败: {} (errno={})", path, errno)),
+    }
+}
+
+/// 查询路径对应的文件或目录（列出父目录后按名称查找），不存在时返回 `None`
+///
+/// [`exists`] 只说明路径存在，区分文件和目录需要看条目的 `isdir`。
+pub async fn stat(account: &Account, path: &str) -> Result<Option<FileEntry>> {
+    let (parent, name) = split_path(path);
+    if name.is_empty() {
+        // 根目录
+        return Ok(Some(FileEntry {
+            fs_id: 0,
+            path: "/".to_string(),
+            server_filename: String::new(),
+            size: 0,
+            isdir: true,
+            server_mtime: 0,
+        }));
+    }
+    if !exists(account, &parent).await? {
+        return Ok(None);
+    }
+    Ok(list_dir(account, &parent)
+        .await?
+        .into_iter()
+        .find(|e| e.server_filename == name))
+}
+
+/// 获取自己网盘操作所需的 bdstoken
+pub async fn get_bdstoken(account: &Account) -> Result<String> {
+    let url = account.api.pan_url(
+        "/api/gettemplatevariable?clienttype=0&app_id=250528&web=1&fields=[%22bdstoken%22]",
+    );
+
+    let text = account
+        .api
+        .get(&url)
+        .header("Referer", account.api.pan_url("/disk/home"))
+        .send()
+        .await?
+        .text()
+        .await?;
+    debug!("gettemplatevariable 响应: {}", text);
+
+    #[derive(Deserialize)]
+    struct TemplateResult {
+        #[serde(default)]
+        bdstoken: String,
+    }
+    #[derive(Deserialize)]
+    struct TemplateResponse {
+        errno: i32,
+        result: Option<TemplateResult>,
+    }
+
+    let res: TemplateResponse = serde_json::from_str(&text)
+        .map_err(|e| anyhow!("解析 bdstoken 响应失败: {}, body={}", e, text))?;
+
+    match res.result {
+        Some(r) if res.errno == 0 && !r.bdstoken.is_empty() => Ok(r.bdstoken),
+        _ if res.errno == -6 => Err(BaiduError::CookieInvalid { errno: res.errno }.into()),
+        _ => Err(anyhow!(
+            "获取 bdstoken 失败 (errno={})，Cookie 可能已失效",
+            res.errno
+        )),
+    }
+}
+
+/// 创建目录（已存在时报错）
+pub async fn mkdir(account: &Account, path: &str, bdstoken: &str) -> Result<()> {
+    info!("📁 创建目录: {}", path);
+    let url = account.api.pan_url(&format!(
+        "/api/create?a=commit&web=1&channel=chunlei&clienttype=0&bdstoken={}",
+        bdstoken
+    ));
+    let form = [("path", path), ("isdir", "1"), ("block_list", "[]")];
+
+    let text = account
+        .api
+        .post(&url)
+        .header("Referer", account.api.pan_url("/disk/home"))
+        .form(&form)
+        .send()
+        .await?
+        .text()
+        .await?;
+    debug!("create 响应: {}", text);
+
+    #[derive(Deserialize)]
+    struct CreateResponse {
+        errno: i32,
+    }
+    let res: CreateResponse = serde_json::from_str(&text)
+        .map_err(|e| anyhow!("解析 create 响应失败: {}, body={}", e, text))?;
+
+    match res.errno {
+        0 => Ok(()),
+        -8 => Err(anyhow!("目录已存在: {}", path)),
+        errno => Err(anyhow!("创建目录失败: {} (errno={})", path, errno)),
+    }
+}
+
+/// 逐级创建目录（类似 `mkdir -p`），已存在的层级会被跳过
+pub async fn mkdir_p(account: &Account, path: &str) -> Result<()> {
+    let bdstoken = get_bdstoken(account).await?;
+    let mut current = String::new();
+
+    for part in path.split('/').filter(|p| !p.is_empty()) {
+        current.push('/');
+        current.push_str(part);
+        if exists(account, &current).await? {
+            continue;
+        }
+        mkdir(account, &current, &bdstoken).await?;
+    }
+
+    Ok(())
+}
+
+/// 检查新文件名：不能为空、不能包含 `/`，也不能是 `.` 或 `..`，防止借改名移动到其他目录
+pub fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+        return Err(anyhow!("无效的文件名: {:?}", name));
+    }
+    Ok(())
+}
+
+/// 重命名文件或目录（只改名，不移动）
+pub async fn rename(account: &Account, path: &str, newname: &str) -> Result<()> {
+    check_name(newname)?;
+    let filelist = serde_json::json!([{ "path": path, "newname": newname }]);
+    filemanager(account, "rename", &filelist).await
+}
+
+/// 移动文件或目录到 `dest` 目录下
+pub async fn move_to(account: &Account, paths: &[String], dest: &str) -> Result<()> {
+    filemanager(account, "move", &dest_filelist(paths, dest)).await
+}
+
+/// 复制文件或目录到 `dest` 目录下
+pub async fn copy_to(account: &Account, paths: &[String], dest: &str) -> Result<()> {
+    filemanager(account, "copy", &dest_filelist(paths, dest)).await
+}
+
+/// 移动单个文件或目录到 `dest` 目录下并改名为 `newname`
+pub async fn move_as(account: &Account, path: &str, dest: &str, newname: &str) -> Result<()> {
+    check_name(newname)?;
+    let filelist = serde_json::json!([{ "path": path, "dest": dest, "newname": newname }]);
+    filemanager(account, "move", &filelist).await
+}
+
+/// 复制单个文件或目录到 `dest` 目录下并命名为 `newname`
+pub async fn copy_as(account: &Account, path: &str, dest: &str, newname: &str) -> Result<()> {
+    check_name(newname)?;
+    let filelist = serde_json::json!([{ "path": path, "dest": dest, "newname": newname }]);
+    filemanager(account, "copy", &filelist).await
+}
+
+/// 删除文件或目录（进入回收站）
+pub async fn delete(account: &Account, paths: &[String]) -> Result<()> {
+    filemanager(account, "delete", &serde_json::json!(paths)).await
+}
+
+fn dest_filelist(paths: &[String], dest: &str) -> serde_json::Value {
+    paths
+        .iter()
+        .map(|p| {
+            let name = p.rsplit('/').next().unwrap_or(p);
+            serde_json::json!({ "path": p, "dest": dest, "newname": name })
+        })
+        .collect()
+}
+
+/// 调用 `api/filemanager`
+async fn filemanager(account: &Account, opera: &str, filelist: &serde_json::Value) -> Result<()> {
+    let bdstoken = get_bdstoken(account).await?;
+    let url = account.api.pan_url(&format!(
+        "/api/filemanager?opera={}&async=0&onnest=fail&web=1&channel=chunlei&clienttype=0&bdstoken={}",
+        opera, bdstoken
+    ));
+    let filelist = filelist.to_string();
+    info!("🗂️ filemanager {}: {}", opera, filelist);
+
+    let text = account
+        .api
+        .ajax_post(&url)
+        .header("Referer", account.api.pan_url("/disk/home"))
+        .form(&[("filelist", filelist.as_str()), ("ondup", "fail")])
+        .send()
+        .await?
+        .text()
+        .await?;
+    debug!("filemanager 响应: {}", text);
+
+    #[derive(Deserialize)]
+    struct ItemResult {
+        errno: i32,
+        #[serde(default)]
+        path: String,
+    }
+    #[derive(Deserialize)]
+    struct FileManagerResponse {
+        errno: i32,
+        #[serde(default)]
+        info: Vec<ItemResult>,
+    }
+
+    let res: FileManagerResponse = serde_json::from_str(&text)
+        .map_err(|e| anyhow!("解析 filemanager 响应失败: {}, body={}", e, text))?;
+
+    if res.errno == 0 {
+        return Ok(());
+    }
+    if res.errno == -6 {
+        return Err(BaiduError::CookieInvalid { errno: res.errno }.into());
+    }
+
+    let failed: Vec<String> = res
+        .info
+        .iter()
+        .filter(|i| i.errno != 0)
+        .map(|i| format!("{} (errno={})", i.path, i.errno))
+        .collect();
+    warn!(
+        "❌ filemanager {} 失败: errno={}, {:?}",
+        opera, res.errno, failed
+    );
+
+    let hint = match res.errno {
+        -9 => "文件不存在",
+        -8 | 12 => "部分文件操作失败（目标可能已存在）",
+        111 => "有其他文件操作正在进行，请稍后再试",
+        _ => "文件操作失败",
+    };
+    Err(anyhow!(
+        "{}: errno={} {}",
+        hint,
+        res.errno,
+        failed.join(", ")
+    ))
+}
+
+/// 拆分路径为 (父目录, 文件名)
+pub fn split_path(path: &str) -> (String, String) {
+    let trimmed = path.trim_end_matches('/');
+    match trimmed.rfind('/') {
+        Some(0) => ("/".to_string(), trimmed[1..].to_string()),
+        Some(i) => (trimmed[..i].to_string(), trimmed[i + 1..].to_string()),
+        None => ("/".to_string(), trimmed.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_path() {
+        assert_eq!(split_path("/a/b/c"), ("/a/b".to_string(), "c".to_string()));
+        assert_eq!(split_path("/a/"), ("/".to_string(), "a".to_string()));
+        assert_eq!(split_path("a"), ("/".to_string(), "a".to_string()));
+    }
+
+    #[test]
+    fn test_check_name() {
+        assert!(check_name("新建文件夹").is_ok());
+        assert!(check_name("a..b.txt").is_ok());
+        for name in ["", ".", "..", "a/b", "../etc"] {
+            assert!(check_name(name).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_dest_filelist() {
+        let list = dest_filelist(&["/a/b.txt".to_string()], "/dst");
+        assert_eq!(
+            list,
+            serde_json::json!([{ "path": "/a/b.txt", "dest": "/dst", "newname": "b.txt" }])
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! 扫码登录
+//!
+//! 获取 passport 二维码 → 百度网盘 App 扫码并确认 → 用确认后得到的临时凭证换取 BDUSS，
+//! 再经 passport 授权跳转到网盘拿到 STOKEN。整个过程无需从浏览器开发者工具复制 Cookie。
+
+use anyhow::{anyhow, Result};
+use qrcode::render::{svg, unicode};
+use qrcode::QrCode;
+use reqwest::cookie::{CookieStore, Jar};
+use reqwest::Url;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{debug, info};
+
+use super::api::{self, BaiduApi};
+use crate::config::BaiduConfig;
+
+/// 二维码中编码的扫码地址（由手机 App 打开，与接口地址无关）
+const WAPPASS_QRLOGIN: &str = "https://wappass.baidu.com/wp/?qrlogin";
+
+/// 一次扫码登录的二维码
+#[derive(Debug, Clone)]
+pub struct LoginQrCode {
+    /// 二维码标识，轮询状态时使用
+    pub sign: String,
+    /// 二维码内容
+    pub content: String,
+}
+
+impl LoginQrCode {
+    fn qr(&self) -> Result<QrCode> {
+        QrCode::new(self.content.as_bytes()).map_err(|e| anyhow!("生成二维码失败: {}", e))
+    }
+
+    /// 以终端字符块渲染
+    pub fn to_terminal(&self) -> Result<String> {
+        Ok(self
+            .qr()?
+            .render::<unicode::Dense1x2>()
+            .dark_color(unicode::Dense1x2::Light)
+            .light_color(unicode::Dense1x2::Dark)
+            .quiet_zone(true)
+            .build())
+    }
+
+    /// 以 SVG 渲染（Web 界面使用）
+    pub fn to_svg(&self) -> Result<String> {
+        Ok(self
+            .qr()?
+            .render::<svg::Color>()
+            .min_dimensions(200, 200)
+            .build())
+    }
+}
+
+/// 扫码状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QrStatus {
+    /// 等待扫码
+    Waiting,
+    /// 已扫码，等待在手机上确认
+    Scanned,
+    /// 已确认，携带换取 BDUSS 的临时凭证
+    Confirmed { token: String },
+    /// 二维码已过期或已取消
+    Expired,
+}
+
+impl QrStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QrStatus::Waiting => "waiting",
+            QrStatus::Scanned => "scanned",
+            QrStatus::Confirmed { .. } => "confirmed",
+            QrStatus::Expired => "expired",
+        }
+    }
+}
+
+/// 登录得到的 Cookie
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub bduss: String,
+    pub stoken: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct QrCodeResponse {
+    #[serde(default)]
+    errno: i32,
+    #[serde(default)]
+    sign: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnicastResponse {
+    errno: i32,
+    #[serde(default)]
+    channel_v: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChannelValue {
+    status: i32,
+    #[serde(default)]
+    v: String,
+}
+
+/// 扫码登录客户端（独立的 Cookie jar，不影响已有账号）
+pub struct QrLogin {
+    api: BaiduApi,
+    jar: Arc<Jar>,
+}
+
+impl QrLogin {
+    pub fn new(baidu: &BaiduConfig) -> Result<Self> {
+        let jar = Arc::new(Jar::default());
+        let client = api::client_builder(baidu)?
+            .cookie_provider(jar.clone())
+            .timeout(std::time::Duration::from_secs(
+                baidu.http_timeout_secs.max(40),
+            ))
+            .build()?;
+        Ok(Self {
+            api: BaiduApi::new(client, baidu.endpoints.clone())
+                .with_proxy(&baidu.proxy)
+                .with_profile(baidu.request_profile(None)?),
+            jar,
+        })
+    }
+
+    /// 获取登录二维码
+    pub async fn start(&self) -> Result<LoginQrCode> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let url = self.api.passport_url(&format!(
+            "/v2/api/getqrcode?lp=pc&qrloginfrom=pc&apiver=v3&tpl=netdisk&tt={}&_={}",
+            now, now
+        ));
+        let text = self.api.get(&url).send().await?.text().await?;
+        debug!("getqrcode 响应: {}", text);
+
+        let res: QrCodeResponse = serde_json::from_str(strip_jsonp(&text))
+            .map_err(|e| anyhow!("解析二维码响应失败: {}, body={}", e, text))?;
+        if res.errno != 0 || res.sign.is_empty() {
+            return Err(anyhow!("获取登录二维码失败: errno={}", res.errno));
+        }
+
+        Ok(LoginQrCode {
+            content: format!(
+                "{}&t={}&error=0&sign={}&cmd=login&lp=pc&tpl=netdisk&adapter=3&qrloginfrom=pc",
+                WAPPASS_QRLOGIN,
+                now / 1000,
+                res.sign
+            ),
+            sign: res.sign,
+        })
+    }
+
+    /// 查询扫码状态（百度侧为长轮询，无变化时会挂起一段时间再返回）
+    pub async fn poll(&self, sign: &str) -> Result<QrStatus> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let url = self.api.passport_url(&format!(
+            "/channel/unicast?channel_id={}&tpl=netdisk&apiver=v3&tt={}&_={}",
+            urlencoding::encode(sign),
+            now,
+            now
+        ));
+        let text = self.api.get(&url).send().await?.text().await?;
+        debug!("unicast 响应: {}", text);
+
+        let res: UnicastResponse = serde_json::from_str(strip_jsonp(&text))
+            .map_err(|e| anyhow!("解析扫码状态失败: {}, body={}", e, text))?;
+        match res.errno {
+            0 => {}
+            // 长轮询超时，状态无变化
+            1 => return Ok(QrStatus::Waiting),
+            _ => return Ok(QrStatus::Expired),
+        }
+
+        let value: ChannelValue = serde_json::from_str(&res.channel_v)
+            .map_err(|e| anyhow!("解析扫码状态失败: {}, channel_v={}", e, res.channel_v))?;
+        Ok(match value.status {
+            0 if !value.v.is_empty() => QrStatus::Confirmed { token: value.v },
+            1 => QrStatus::Scanned,
+            2 => QrStatus::Expired,
+            _ => QrStatus::Waiting,
+        })
+    }
+
+    /// 用确认后的临时凭证换取 BDUSS 和 STOKEN
+    pub async fn finish(&self, token: &str) -> Result<Credentials> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let home = self.api.pan_url("/disk/home");
+
+        // 1. 换取 BDUSS（passport 通过 Set-Cookie 下发）
+        let url = self.api.passport_url(&format!(
+            "/v3/login/main/qrbdusslogin?v={}&bduss={}&u={}&loginVersion=v4&qrcode=1&tpl=netdisk&apiver=v3&tt={}",
+            now,
+            urlencoding::encode(token),
+            urlencoding::encode(&home),
+            now
+        ));
+        self.api.get(&url).send().await?;
+
+        // 2. passport 授权后跳转回网盘，网盘下发 STOKEN
+        let url = self.api.passport_url(&format!(
+            "/v3/login/api/auth/?return_type=5&tpl=netdisk&u={}",
+            urlencoding::encode(&home)
+        ));
+        self.api.get(&url).send().await?;
+
+        let pan = home.parse::<Url>()?;
+        let passport = self.api.passport_url("/").parse::<Url>()?;
+        let bduss = self
+            .cookie(&passport, "BDUSS")
+            .or_else(|| self.cookie(&pan, "BDUSS"))
+            .ok_or_else(|| anyhow!("扫码登录失败：未获取到 BDUSS"))?;
+        let stoken = self
+            .cookie(&pan, "STOKEN")
+            .ok_or_else(|| anyhow!("扫码登录失败：未获取到网盘 STOKEN"))?;
+
+        info!("✅ 扫码登录成功，已获取 BDUSS 和 STOKEN");
+        Ok(Credentials { bduss, stoken })
+    }
+
+    fn cookie(&self, url: &Url, name: &str) -> Option<String> {
+        let header = self.jar.cookies(url)?;
+        header.to_str().ok()?.split("; ").find_map(|pair| {
+            let (k, v) = pair.split_once('=')?;
+            (k == name && !v.is_empty()).then(|| v.to_string())
+        })
+    }
+}
+
+/// 去掉 JSONP 包裹（`callback({...})`）
+fn strip_jsonp(text: &str) -> &str {
+    match (text.find('{'), text.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &text[start..=end],
+        _ => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_jsonp() {
+        assert_eq!(strip_jsonp(r#"cb({"errno":0})"#), r#"{"errno":0}"#);
+        assert_eq!(strip_jsonp(r#"{"errno":1}"#), r#"{"errno":1}"#);
+    }
+
+    #[test]
+    fn test_qr_renders() {
+        let qr = LoginQrCode {
+            sign: "abc".to_string(),
+            content: format!("{}&sign=abc", WAPPASS_QRLOGIN),
+        };
+        assert!(qr.to_svg().unwrap().contains("<svg"));
+        assert!(qr.to_terminal().unwrap().contains('█'));
+    }
+}
@@ -1,11 +1,26 @@
 //! 百度网盘 PCS 模块
 
+pub mod account;
+pub mod api;
+pub mod error;
+pub mod fs;
+pub mod login;
 pub mod parser;
+pub mod pipeline;
+pub mod quota;
 pub mod share;
 pub mod transfer;
 pub mod types;
 
+pub use account::{whoami, AccountInfo};
+pub use api::{BaiduApi, Endpoints};
+pub use error::BaiduError;
+pub use login::{Credentials, LoginQrCode, QrLogin, QrStatus};
 pub use parser::extract_surl;
+pub use pipeline::{
+    run_transfer, Phase, Progress, TransferEvent, TransferOptions, TransferOutcome, TransferQuota,
+};
+pub use quota::{check_quota, get_quota};
 pub use share::get_share_info;
-pub use transfer::transfer_files;
-pub use types::ShareFileInfo;
+pub use transfer::{execute_plan, plan_transfer, transfer_files};
+pub use types::{QuotaInfo, ShareFileInfo, TransferPlan};
@@ -0,0 +1,217 @@
+//! 分享链接 → 网盘的完整转存流程
+//!
+//! 获取分享信息 → 生成转存计划 → 容量检查 → 转存。dry-run 时在生成计划后返回，
+//! 不创建目录也不调用转存接口。各阶段通过 [`Progress`] 回调报告进度（见 [`crate::jobs`]）。
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::info;
+
+use super::parser::extract_surl;
+use super::quota::{check_quota, format_size};
+use super::share::get_share_info;
+use super::transfer::{execute_plan, plan_transfer};
+use super::types::TransferPlan;
+use crate::state::Account;
+
+/// 转存选项
+#[derive(Debug, Clone, Default)]
+pub struct TransferOptions {
+    /// 只生成转存计划，不写入网盘
+    pub dry_run: bool,
+    /// 保存目录，为空时使用账号的 `save_path`
+    pub save_path: Option<String>,
+    /// 转存额度（Web 用户的每日额度），转存前按计划大小预留，超出时不执行转存
+    pub quota: Option<Arc<dyn TransferQuota>>,
+    /// 进度回调
+    pub progress: Option<Progress>,
+}
+
+impl TransferOptions {
+    /// 设置了进度回调时报告事件
+    pub fn emit(&self, event: TransferEvent) {
+        if let Some(progress) = &self.progress {
+            progress.emit(event);
+        }
+    }
+}
+
+/// 转存额度：生成计划后、转存前按计划大小预留，每批转存成功后按实际字节数计入已用额度
+///
+/// 换账号重试时会以新计划的大小再次调用 `reserve`，实现方应替换而不是累加尚未使用的预留。
+/// 转存结束后释放剩余预留由调用方负责（见 [`crate::users::UserQuota`]）。
+pub trait TransferQuota: Send + Sync + std::fmt::Debug {
+    /// 按计划预留额度，超出剩余额度时返回错误
+    fn reserve(&self, plan: &TransferPlan) -> Result<()>;
+    /// 一批文件已写入网盘（`bytes` 为这批文件的大小），即使之后的批次失败也要计入
+    fn transferred(&self, bytes: u64);
+}
+
+/// 转存流程的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// 访问分享页面、验证提取码并列出文件
+    ShareInfo,
+    /// 生成转存计划、检查保存目录
+    Planning,
+    /// 检查网盘剩余空间
+    QuotaCheck,
+    /// 调用转存接口
+    Transferring,
+}
+
+/// 转存进度事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransferEvent {
+    /// 进入新阶段
+    Phase { phase: Phase, account: String },
+    /// 列出了分享中的文件（去重后）
+    FilesDiscovered { count: usize, total_size: u64 },
+    /// 一批文件转存成功（见 [`super::transfer::TRANSFER_CHUNK_SIZE`]），`index` 从 1 开始
+    ChunkTransferred {
+        index: usize,
+        total: usize,
+        files: usize,
+        bytes: u64,
+    },
+    /// 账号不可用（Cookie 失效、空间不足等），账号池会换下一个账号重试
+    AccountFailed { account: String, error: String },
+}
+
+impl Phase {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::ShareInfo => "获取分享信息",
+            Self::Planning => "生成转存计划",
+            Self::QuotaCheck => "检查网盘空间",
+            Self::Transferring => "转存",
+        }
+    }
+}
+
+impl std::fmt::Display for TransferEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Phase { phase, account } => write!(f, "[{}] {}", account, phase.label()),
+            Self::FilesDiscovered { count, total_size } => {
+                write!(f, "共 {} 个文件，{}", count, format_size(*total_size))
+            }
+            Self::ChunkTransferred {
+                index,
+                total,
+                files,
+                bytes,
+            } => write!(
+                f,
+                "第 {}/{} 批转存成功（{} 个文件，{}）",
+                index,
+                total,
+                files,
+                format_size(*bytes)
+            ),
+            Self::AccountFailed { account, error } => {
+                write!(f, "账号 {} 不可用: {}", account, error)
+            }
+        }
+    }
+}
+
+/// 进度回调，在转存流程所在的任务中同步调用
+#[derive(Clone)]
+pub struct Progress(Arc<dyn Fn(TransferEvent) + Send + Sync>);
+
+impl Progress {
+    pub fn new(f: impl Fn(TransferEvent) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub fn emit(&self, event: TransferEvent) {
+        (self.0)(event)
+    }
+}
+
+impl std::fmt::Debug for Progress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Progress(..)")
+    }
+}
+
+/// 转存结果
+#[derive(Debug, Clone)]
+pub struct TransferOutcome {
+    /// 执行转存的账号名称
+    pub account: String,
+    pub plan: TransferPlan,
+    /// 为 true 表示仅生成了计划，未实际转存
+    pub dry_run: bool,
+}
+
+/// 执行完整转存流程
+pub async fn run_transfer(
+    account: &Account,
+    share_url: &str,
+    pwd: &str,
+    options: TransferOptions,
+) -> Result<TransferOutcome> {
+    let surl =
+        extract_surl(share_url).ok_or_else(|| anyhow!("无法从链接中提取 surl: {}", share_url))?;
+
+    let phase = |phase| {
+        options.emit(TransferEvent::Phase {
+            phase,
+            account: account.name.clone(),
+        })
+    };
+
+    phase(Phase::ShareInfo);
+    let info = get_share_info(account, share_url, &surl, pwd).await?;
+    phase(Phase::Planning);
+    let save_path = options.save_path.as_deref().unwrap_or(&account.save_path);
+    let plan = plan_transfer(account, &info, &surl, save_path).await?;
+    options.emit(TransferEvent::FilesDiscovered {
+        count: plan.fs_ids.len(),
+        total_size: plan.total_size,
+    });
+
+    if options.dry_run {
+        info!(
+            "🧪 dry-run: {} 个文件将转存至 {} (ondup={})，未执行任何写入",
+            plan.fs_ids.len(),
+            plan.save_path,
+            plan.ondup
+        );
+        return Ok(TransferOutcome {
+            account: account.name.clone(),
+            plan,
+            dry_run: true,
+        });
+    }
+
+    if let Some(quota) = &options.quota {
+        quota.reserve(&plan)?;
+    }
+    phase(Phase::QuotaCheck);
+    check_quota(account, plan.total_size).await?;
+    phase(Phase::Transferring);
+    execute_plan(account, &plan, |index, total, files, bytes| {
+        if let Some(quota) = &options.quota {
+            quota.transferred(bytes);
+        }
+        options.emit(TransferEvent::ChunkTransferred {
+            index,
+            total,
+            files,
+            bytes,
+        })
+    })
+    .await?;
+
+    Ok(TransferOutcome {
+        account: account.name.clone(),
+        plan,
+        dry_run: false,
+    })
+}
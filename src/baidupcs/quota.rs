@@ -0,0 +1,163 @@
+//! 网盘容量查询与转存前检查
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+
+use super::error::BaiduError;
+use super::types::QuotaInfo;
+use crate::state::Account;
+
+#[derive(Debug, Deserialize)]
+struct QuotaResponse {
+    errno: i32,
+    #[serde(default)]
+    total: u64,
+    #[serde(default)]
+    used: u64,
+    #[serde(default)]
+    free: Option<u64>,
+}
+
+/// 查询当前账号的网盘容量
+pub async fn get_quota(account: &Account) -> Result<QuotaInfo> {
+    let url = account
+        .api
+        .pan_url("/api/quota?checkfree=1&checkexpire=1&web=1&channel=chunlei&clienttype=0");
+
+    let resp = account
+        .api
+        .get(&url)
+        .header("Referer", account.api.pan_url("/"))
+        .send()
+        .await?;
+
+    let text = resp.text().await?;
+    debug!("quota 响应: {}", text);
+
+    let res: QuotaResponse = serde_json::from_str(&text)
+        .map_err(|e| anyhow!("解析 quota 响应失败: {}, body={}", e, text))?;
+
+    match res.errno {
+        0 => {}
+        -6 => return Err(BaiduError::CookieInvalid { errno: res.errno }.into()),
+        errno => return Err(anyhow!("查询网盘容量失败: errno={}", errno)),
+    }
+
+    Ok(QuotaInfo {
+        total: res.total,
+        used: res.used,
+        // 部分情况下接口不返回 free，按 total - used 计算
+        free: res
+            .free
+            .unwrap_or_else(|| res.total.saturating_sub(res.used)),
+    })
+}
+
+/// 转存前检查容量
+///
+/// - 所需空间超过剩余空间：返回 [`BaiduError::QuotaExceeded`]
+/// - 转存后使用率超过 `quota_warn_percent`：仅记录警告
+///
+/// 容量查询本身失败时不阻断转存，只记录警告并返回 `None`；Cookie 失效除外。
+pub async fn check_quota(account: &Account, required: u64) -> Result<Option<QuotaInfo>> {
+    let quota = match get_quota(account).await {
+        Ok(q) => q,
+        Err(e) if e.downcast_ref::<BaiduError>().is_some() => return Err(e),
+        Err(e) => {
+            warn!("⚠️ 无法查询网盘容量，跳过容量检查: {}", e);
+            return Ok(None);
+        }
+    };
+
+    info!(
+        "💾 网盘容量: 已用 {} / 共 {}，剩余 {}；本次预计需要 {}",
+        format_size(quota.used),
+        format_size(quota.total),
+        format_size(quota.free),
+        format_size(required)
+    );
+
+    if evaluate_quota(&quota, required, account.quota_warn_percent)? {
+        warn!("⚠️ 转存后网盘使用率将超过 {}%", account.quota_warn_percent);
+    }
+
+    Ok(Some(quota))
+}
+
+/// 判断容量是否足够；返回 `Ok(true)` 表示足够但超过告警阈值
+fn evaluate_quota(quota: &QuotaInfo, required: u64, warn_percent: u8) -> Result<bool> {
+    if required > quota.free {
+        return Err(BaiduError::QuotaExceeded {
+            required,
+            free: quota.free,
+        }
+        .into());
+    }
+
+    if quota.total == 0 {
+        return Ok(false);
+    }
+
+    let after = quota.used.saturating_add(required) as f64;
+    Ok(after / quota.total as f64 * 100.0 > warn_percent as f64)
+}
+
+/// 将字节数格式化为便于阅读的字符串
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.2} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    fn quota(total: u64, used: u64) -> QuotaInfo {
+        QuotaInfo {
+            total,
+            used,
+            free: total - used,
+        }
+    }
+
+    #[test]
+    fn test_evaluate_quota_enough() {
+        assert!(!evaluate_quota(&quota(100 * GB, 10 * GB), GB, 90).unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_quota_over_threshold() {
+        assert!(evaluate_quota(&quota(100 * GB, 85 * GB), 10 * GB, 90).unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_quota_exceeded() {
+        let err = evaluate_quota(&quota(100 * GB, 95 * GB), 10 * GB, 90).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<BaiduError>(),
+            Some(BaiduError::QuotaExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.50 KB");
+        assert_eq!(format_size(2 * GB), "2.00 GB");
+    }
+}
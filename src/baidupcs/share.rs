@@ -7,8 +7,7 @@ use serde::{Deserialize, Deserializer};
 use tracing::{debug, info, warn};
 
 use super::types::ShareFileInfo;
-use crate::config::Config;
-use crate::AppState;
+use crate::state::Account;
 
 #[derive(Debug, Deserialize)]
 struct ListResponse {
@@ -24,6 +23,13 @@ struct FileItem {
     fs_id: u64,
     #[serde(default)]
     server_filename: String,
+    /// 分享者网盘中的路径，列出目录内容时使用
+    #[serde(default)]
+    path: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    isdir: u8,
 }
 
 /// 自定义反序列化：支持字符串或数字类型的 fsid
@@ -54,7 +60,7 @@ impl FileItem {
 
 /// 获取分享链接信息
 pub async fn get_share_info(
-    state: &AppState,
+    account: &Account,
     _share_url: &str,
     surl: &str,
     pwd: &str,
@@ -63,16 +69,11 @@ pub async fn get_share_info(
 
     // Step 1: 访问分享页面
     let surl_param = surl.strip_prefix('1').unwrap_or(surl);
-    let init_url = format!("https://pan.baidu.com/share/init?surl={}", surl_param);
+    let init_url = account.api.share_init_url(surl_param);
 
     info!("🌐 访问分享页面: {}", init_url);
 
-    let resp = state
-        .client
-        .get(&init_url)
-        .header("User-Agent", Config::browser_ua())
-        .send()
-        .await?;
+    let resp = account.api.get(&init_url).send().await?;
 
     let html = resp.text().await?;
     debug!("📄 页面长度: {} 字节", html.len());
@@ -88,13 +89,15 @@ pub async fn get_share_info(
     // Step 4: 验证提取码
     if !pwd.is_empty() {
         info!("🔐 验证提取码...");
-        verify_password(state, surl_param, pwd, &bdstoken).await?;
+        verify_password(account, surl_param, pwd, &bdstoken).await?;
         info!("✅ 提取码验证成功");
     }
 
     // Step 5: 获取文件列表
     info!("📋 获取文件列表...");
-    let (fs_ids, filenames) = get_file_list(state, &shareid, &uk, surl_param, &bdstoken).await?;
+    let (fs_ids, filenames, sizes, size_known) =
+        get_file_list(account, &shareid, &uk, surl_param, &bdstoken).await?;
+    let total_size = sizes.iter().fold(0u64, |sum, s| sum.saturating_add(*s));
 
     if fs_ids.is_empty() {
         return Err(anyhow!("未找到可转存的文件"));
@@ -111,38 +114,33 @@ pub async fn get_share_info(
         fs_ids,
         bdstoken,
         filenames,
+        sizes,
+        total_size,
+        size_known,
     })
 }
 
 /// 验证提取码
-async fn verify_password(state: &AppState, surl: &str, pwd: &str, bdstoken: &str) -> Result<()> {
+async fn verify_password(account: &Account, surl: &str, pwd: &str, bdstoken: &str) -> Result<()> {
     // 更贴近浏览器/baidupcs-go：verify 的大部分参数在 query string，表单仅提交 pwd/vcode。
     // 少带或带错参数/请求头时，百度有时也会返回 errno=-12（看起来像“提取码错误”）。
     let ts_ms = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
         .as_millis();
-    let url = format!(
-        "https://pan.baidu.com/share/verify?surl={}&t={}&channel=chunlei&web=1&app_id=250528&clienttype=0&bdstoken={}",
-        surl,
-        ts_ms,
-        bdstoken
-    );
+    let url = account.api.pan_url(&format!(
+        "/share/verify?surl={}&t={}&channel=chunlei&web=1&app_id=250528&clienttype=0&bdstoken={}",
+        surl, ts_ms, bdstoken
+    ));
 
     let form = [("pwd", pwd), ("vcode", ""), ("vcode_str", "")];
 
     debug!("🔐 提取码验证: surl={}", surl);
 
-    let resp = state
-        .client
-        .post(url)
-        .header("User-Agent", Config::browser_ua())
-        .header(
-            "Referer",
-            format!("https://pan.baidu.com/share/init?surl={}", surl),
-        )
-        .header("Origin", "https://pan.baidu.com")
-        .header("X-Requested-With", "XMLHttpRequest")
+    let resp = account
+        .api
+        .ajax_post(&url)
+        .header("Referer", account.api.share_init_url(surl))
         .form(&form)
         .send()
         .await?;
@@ -180,36 +178,143 @@ async fn verify_password(state: &AppState, surl: &str, pwd: &str, bdstoken: &str
     Ok(())
 }
 
+/// 列出分享内容时每页的条目数
+const LIST_PAGE_SIZE: usize = 1000;
+
+/// 列出分享根目录时最多请求的页数，超过后返回错误（防止接口一直返回整页时无限翻页）
+const MAX_ROOT_LIST_PAGES: usize = 100;
+
+/// 统计分享中目录大小时最多请求的列表页数，超过后大小按未知处理
+const MAX_DIR_LIST_PAGES: usize = 200;
+
 /// 获取文件列表
 ///
-/// 调用 share/list API 获取分享链接中的所有文件
+/// 调用 share/list API 获取分享链接中的所有文件（自动翻页，最多 [`MAX_ROOT_LIST_PAGES`] 页），
+/// 同时返回每个文件的大小。
+/// share/list 对目录返回 size=0，目录的大小通过递归列出其内容统计；
+/// 请求的页数超过 [`MAX_DIR_LIST_PAGES`] 时停止统计，最后一项返回 `false`（大小未知）。
 async fn get_file_list(
-    state: &AppState,
+    account: &Account,
     shareid: &str,
     uk: &str,
     surl: &str,
     bdstoken: &str,
-) -> Result<(Vec<u64>, Vec<String>)> {
-    let url = format!(
-        "https://pan.baidu.com/share/list?shareid={}&uk={}&shorturl={}&root=1&dir=%2F&page=1&num=1000&order=name&desc=1&showempty=0&web=1&channel=chunlei&clienttype=0&bdstoken={}",
-        shareid, uk, surl, bdstoken
-    );
+) -> Result<(Vec<u64>, Vec<String>, Vec<u64>, bool)> {
+    let mut list = Vec::new();
+    for page in 1.. {
+        if page > MAX_ROOT_LIST_PAGES {
+            return Err(anyhow!(
+                "分享中的文件过多：列出 {} 页（{} 个条目）后仍未结束",
+                MAX_ROOT_LIST_PAGES,
+                list.len()
+            ));
+        }
+        let items = list_page(account, shareid, uk, surl, bdstoken, None, page).await?;
+        let full = items.len() >= LIST_PAGE_SIZE;
+        list.extend(items);
+        if !full {
+            break;
+        }
+    }
+
+    let mut fs_ids = Vec::new();
+    let mut filenames = Vec::new();
+    let mut sizes = Vec::new();
+    let mut pages = 0;
+    let mut size_known = true;
+
+    for file in list {
+        let mut size = file.size;
+        if file.isdir == 1 && size_known {
+            match dir_size(account, shareid, uk, surl, bdstoken, &file.path, &mut pages).await? {
+                Some(dir_size) => {
+                    debug!("📁 目录 {} 共 {} 字节", file.server_filename, dir_size);
+                    size = dir_size;
+                }
+                None => {
+                    warn!("⚠️ 分享中的目录过多，目录大小未计入容量估算");
+                    size_known = false;
+                }
+            }
+        }
+        fs_ids.push(file.get_fsid());
+        sizes.push(size);
+        filenames.push(file.server_filename);
+    }
+
+    Ok((fs_ids, filenames, sizes, size_known))
+}
+
+/// 递归统计分享中目录 `dir` 下所有文件的大小，`pages` 为已请求的列表页数
+///
+/// 页数超过 [`MAX_DIR_LIST_PAGES`] 时返回 `None`。
+async fn dir_size(
+    account: &Account,
+    shareid: &str,
+    uk: &str,
+    surl: &str,
+    bdstoken: &str,
+    dir: &str,
+    pages: &mut usize,
+) -> Result<Option<u64>> {
+    let mut total = 0u64;
+    let mut pending = vec![dir.to_string()];
+    while let Some(dir) = pending.pop() {
+        for page in 1.. {
+            if *pages >= MAX_DIR_LIST_PAGES {
+                return Ok(None);
+            }
+            *pages += 1;
+            let list = list_page(account, shareid, uk, surl, bdstoken, Some(&dir), page).await?;
+            let full = list.len() >= LIST_PAGE_SIZE;
+            for file in list {
+                if file.isdir == 1 {
+                    pending.push(file.path);
+                } else {
+                    total = total.saturating_add(file.size);
+                }
+            }
+            if !full {
+                break;
+            }
+        }
+    }
+    Ok(Some(total))
+}
+
+/// 调用 share/list API 列出分享根目录（`dir` 为 `None`）或其中某个目录的一页
+async fn list_page(
+    account: &Account,
+    shareid: &str,
+    uk: &str,
+    surl: &str,
+    bdstoken: &str,
+    dir: Option<&str>,
+    page: usize,
+) -> Result<Vec<FileItem>> {
+    let dir = match dir {
+        None => "root=1&dir=%2F".to_string(),
+        Some(dir) => format!("dir={}", urlencoding::encode(dir)),
+    };
+    let url = account.api.pan_url(&format!(
+        "/share/list?shareid={}&uk={}&shorturl={}&{}&page={}&num={}&order=name&desc=1&showempty=0&web=1&channel=chunlei&clienttype=0&bdstoken={}",
+        shareid, uk, surl, dir, page, LIST_PAGE_SIZE, bdstoken
+    ));
 
     debug!("📡 调用 list API: {}", url);
 
-    let resp = state
-        .client
+    let resp = account
+        .api
         .get(&url)
-        .header("User-Agent", Config::browser_ua())
-        .header(
-            "Referer",
-            format!("https://pan.baidu.com/share/init?surl={}", surl),
-        )
+        .header("Referer", account.api.share_init_url(surl))
         .send()
         .await?;
 
     let text = resp.text().await?;
-    debug!("📨 list 响应: {}", &text[..200.min(text.len())]);
+    debug!(
+        "📨 list 响应: {}",
+        text.chars().take(200).collect::<String>()
+    );
 
     let res: ListResponse =
         serde_json::from_str(&text).map_err(|e| anyhow!("解析响应失败: {}", e))?;
@@ -230,15 +335,7 @@ async fn get_file_list(
         ));
     }
 
-    let mut fs_ids = Vec::new();
-    let mut filenames = Vec::new();
-
-    for file in res.list {
-        fs_ids.push(file.get_fsid());
-        filenames.push(file.server_filename);
-    }
-
-    Ok((fs_ids, filenames))
+    Ok(res.list)
 }
 
 /// 从 HTML 中提取 shareid 和 uk
@@ -6,24 +6,25 @@ use anyhow::{anyhow, Result};
 use serde::Deserialize;
 use tracing::{debug, error, info, warn};
 
-use crate::config::Config;
-use crate::AppState;
+use super::error::BaiduError;
+use super::types::{ShareFileInfo, TransferPlan};
+use crate::state::Account;
 use chrono::Utc;
+use std::collections::HashSet;
 
 /// 在目标网盘上创建目录（如果 API 支持）
-async fn create_remote_dir(state: &AppState, path: &str, bdstoken: &str) -> Result<bool> {
+async fn create_remote_dir(account: &Account, path: &str, bdstoken: &str) -> Result<bool> {
     info!("🔧 尝试创建远程目录: {}", path);
-    let url = format!(
-        "https://pan.baidu.com/rest/2.0/xpan/file?method=create&path={}&isdir=1&bdstoken={}",
+    let url = account.api.pan_url(&format!(
+        "/rest/2.0/xpan/file?method=create&path={}&isdir=1&bdstoken={}",
         urlencoding::encode(path),
         bdstoken
-    );
+    ));
 
-    let resp = state
-        .client
+    let resp = account
+        .api
         .post(&url)
-        .header("User-Agent", Config::browser_ua())
-        .header("Referer", "https://pan.baidu.com/")
+        .header("Referer", account.api.pan_url("/"))
         .header(
             "Content-Type",
             "application/x-www-form-urlencoded; charset=UTF-8",
@@ -58,6 +59,14 @@ async fn create_remote_dir(state: &AppState, path: &str, bdstoken: &str) -> Resu
     }
 }
 
+/// 一次转存请求的结果
+enum Transferred {
+    /// 已写入网盘（包括百度自动创建了副本的情况）
+    Done,
+    /// 目标目录中已有相同文件，且百度没有创建副本（errno=2、newno 为空）
+    DuplicateNoCopy { show_msg: String },
+}
+
 #[derive(Debug, Deserialize)]
 struct TransferResult {
     #[serde(default)]
@@ -71,20 +80,15 @@ struct TransferResult {
 }
 
 /// 验证保存路径是否存在
-pub async fn verify_save_path(state: &AppState, path: &str) -> Result<bool> {
+pub async fn verify_save_path(account: &Account, path: &str) -> Result<bool> {
     info!("🔍 验证保存路径: {}", path);
 
-    let url = format!(
-        "https://pan.baidu.com/api/list?dir={}&num=1&order=name&desc=0",
+    let url = account.api.pan_url(&format!(
+        "/api/list?dir={}&num=1&order=name&desc=0",
         urlencoding::encode(path)
-    );
+    ));
 
-    let resp = state
-        .client
-        .get(&url)
-        .header("User-Agent", Config::browser_ua())
-        .send()
-        .await?;
+    let resp = account.api.get(&url).send().await?;
 
     let text = resp.text().await?;
     debug!("路径验证响应: {}", text);
@@ -108,39 +112,240 @@ pub async fn verify_save_path(state: &AppState, path: &str) -> Result<bool> {
     }
 }
 
+/// 默认重复文件处理策略
+///
+/// ondup参数: newcopy(重命名), overwrite(覆盖), fail(失败)
+pub const DEFAULT_ONDUP: &str = "newcopy";
+
+/// 生成转存计划（只读，不创建目录也不调用转存接口）
+///
+/// 对 fs_id 去重（保留首次出现的顺序），总大小按去重后的文件统计；
+/// 解析保存路径 `save_path` 并检查其是否存在。
+pub async fn plan_transfer(
+    account: &Account,
+    info: &ShareFileInfo,
+    surl: &str,
+    save_path: &str,
+) -> Result<TransferPlan> {
+    let mut seen = HashSet::new();
+    let mut fs_ids = Vec::with_capacity(info.fs_ids.len());
+    let mut filenames = Vec::with_capacity(info.fs_ids.len());
+    let mut sizes = Vec::with_capacity(info.fs_ids.len());
+    for (i, fs_id) in info.fs_ids.iter().enumerate() {
+        if seen.insert(*fs_id) {
+            fs_ids.push(*fs_id);
+            filenames.push(info.filenames.get(i).cloned().unwrap_or_default());
+            sizes.push(info.sizes.get(i).copied().unwrap_or_default());
+        } else {
+            debug!("跳过重复的 fs_id: {}", fs_id);
+        }
+    }
+
+    let total_size = sizes.iter().fold(0u64, |sum, s| sum.saturating_add(*s));
+
+    let save_path = resolve_save_path(save_path);
+    let save_path_exists = verify_save_path(account, &save_path).await?;
+
+    Ok(TransferPlan {
+        shareid: info.shareid.clone(),
+        uk: info.uk.clone(),
+        bdstoken: info.bdstoken.clone(),
+        surl: surl.to_string(),
+        fs_ids,
+        filenames,
+        sizes,
+        total_size,
+        size_known: info.size_known,
+        save_path,
+        save_path_exists,
+        ondup: DEFAULT_ONDUP.to_string(),
+    })
+}
+
+/// 规范化保存路径：确保以 `/` 开头，去掉末尾多余的 `/`
+fn resolve_save_path(path: &str) -> String {
+    let trimmed = path.trim().trim_end_matches('/');
+    if trimmed.starts_with('/') {
+        trimmed.to_string()
+    } else {
+        format!("/{}", trimmed)
+    }
+}
+
+/// 单次转存请求的文件数上限（百度普通用户为 500）
+pub const TRANSFER_CHUNK_SIZE: usize = 500;
+
+/// 按转存计划执行转存
+///
+/// 文件超过 [`TRANSFER_CHUNK_SIZE`] 时分批转存，每批成功后调用
+/// `on_chunk(批次序号（从 1 开始）, 总批数, 本批文件数, 本批字节数)`；某一批失败时停止并返回错误，
+/// 此前成功的批次已写入网盘。
+///
+/// 保存路径只使用计划中的检查结果，不再逐批检查。某一批遇到重复文件而改为转存到副本目录后，
+/// 之后的批次也转存到同一个副本目录（见 [`transfer_with_fallback`]）。
+pub async fn execute_plan(
+    account: &Account,
+    plan: &TransferPlan,
+    mut on_chunk: impl FnMut(usize, usize, usize, u64),
+) -> Result<()> {
+    if !plan.save_path_exists {
+        return Err(anyhow!(
+            "保存路径不存在: {}，请先在百度网盘中创建该文件夹",
+            plan.save_path
+        ));
+    }
+    let total = plan.fs_ids.len().div_ceil(TRANSFER_CHUNK_SIZE);
+    let mut fallback = None;
+    for (i, chunk) in plan.fs_ids.chunks(TRANSFER_CHUNK_SIZE).enumerate() {
+        if total > 1 {
+            info!("📦 第 {}/{} 批", i + 1, total);
+        }
+        transfer_with_fallback(
+            account,
+            &plan.shareid,
+            &plan.uk,
+            chunk,
+            &plan.bdstoken,
+            &plan.surl,
+            &plan.save_path,
+            &plan.ondup,
+            &mut fallback,
+        )
+        .await?;
+        let start = i * TRANSFER_CHUNK_SIZE;
+        let bytes = plan
+            .sizes
+            .iter()
+            .skip(start)
+            .take(chunk.len())
+            .fold(0u64, |sum, s| sum.saturating_add(*s));
+        on_chunk(i + 1, total, chunk.len(), bytes);
+    }
+    Ok(())
+}
+
 /// 百度网盘转存 API
 ///
 /// # 参考 baidupcs-go 实现
 pub async fn transfer_files(
-    state: &AppState,
+    account: &Account,
     shareid: &str,
     uk: &str,
     fs_ids: &[u64],
     bdstoken: &str,
     surl: &str,
 ) -> Result<()> {
-    info!("📦 开始转存 {} 个文件...", fs_ids.len());
-
-    let savepath = &state.config.baidu.save_path; // ← 改成 save_path
-
     // 先验证保存路径
-    if !verify_save_path(state, savepath).await? {
+    if !verify_save_path(account, &account.save_path).await? {
         return Err(anyhow!(
             "保存路径不存在: {}，请先在百度网盘中创建该文件夹",
-            savepath
+            account.save_path
         ));
     }
+    transfer_with_fallback(
+        account,
+        shareid,
+        uk,
+        fs_ids,
+        bdstoken,
+        surl,
+        &account.save_path,
+        DEFAULT_ONDUP,
+        &mut None,
+    )
+    .await
+}
+
+/// 转存一批文件到 `savepath`，目标目录中已有相同文件且百度没有创建副本时改为转存到副本目录
+///
+/// 副本目录是保存路径下带时间戳的子目录（`copy-<时间>`），只创建一次并记录在 `fallback` 中：
+/// 已经有副本目录时直接转存到其中，同一次转存的文件不会分散到多个副本目录。
+#[allow(clippy::too_many_arguments)]
+async fn transfer_with_fallback(
+    account: &Account,
+    shareid: &str,
+    uk: &str,
+    fs_ids: &[u64],
+    bdstoken: &str,
+    surl: &str,
+    savepath: &str,
+    ondup: &str,
+    fallback: &mut Option<String>,
+) -> Result<()> {
+    let target = fallback.as_deref().unwrap_or(savepath);
+    let show_msg =
+        match transfer_to(account, shareid, uk, fs_ids, bdstoken, surl, target, ondup).await? {
+            Transferred::Done => return Ok(()),
+            Transferred::DuplicateNoCopy { show_msg } => show_msg,
+        };
+    if fallback.is_some() {
+        error!("❌ 副本目录 {} 中也已存在相同文件", target);
+        return Err(anyhow!(
+            "转存失败: 副本目录中也已存在相同文件: {}",
+            show_msg
+        ));
+    }
+
+    // 在保存路径下创建带时间戳的子目录并重试一次转存
+    let timestamp = Utc::now().format("%Y%m%d-%H%M%S").to_string();
+    let new_dir = format!("{}/copy-{}", savepath.trim_end_matches('/'), timestamp);
+    info!("🔁 尝试创建子目录并重试转存: {}", new_dir);
+    match create_remote_dir(account, &new_dir, bdstoken).await {
+        Ok(true) => info!("✅ 子目录创建成功，尝试在新目录执行转存..."),
+        Ok(false) => {
+            error!("❌ 子目录创建返回失败，无法重试转存");
+            return Err(anyhow!("文件已存在，且无法创建子目录重试: {}", show_msg));
+        }
+        Err(e) => {
+            error!("❌ 创建子目录失败: {}", e);
+            return Err(anyhow!(
+                "文件已存在，且创建子目录失败: {} ({})",
+                show_msg,
+                e
+            ));
+        }
+    }
+    *fallback = Some(new_dir.clone());
+
+    match transfer_to(
+        account, shareid, uk, fs_ids, bdstoken, surl, &new_dir, ondup,
+    )
+    .await?
+    {
+        Transferred::Done => {
+            info!("✅ 重试转存成功，之后的文件也转存到 {}", new_dir);
+            Ok(())
+        }
+        Transferred::DuplicateNoCopy { show_msg } => {
+            error!("❌ 重试转存仍然失败: {}", show_msg);
+            Err(anyhow!("重试转存失败: {}", show_msg))
+        }
+    }
+}
+
+/// 转存到指定目录（调用方负责检查目录是否存在）
+#[allow(clippy::too_many_arguments)]
+async fn transfer_to(
+    account: &Account,
+    shareid: &str,
+    uk: &str,
+    fs_ids: &[u64],
+    bdstoken: &str,
+    surl: &str,
+    savepath: &str,
+    ondup: &str,
+) -> Result<Transferred> {
+    info!("📦 开始转存 {} 个文件...", fs_ids.len());
 
     // 构建转存 URL
-    // ondup参数: newcopy(重命名), overwrite(覆盖), fail(失败)
-    let url = format!(
-        "https://pan.baidu.com/share/transfer?shareid={}&from={}&ondup=newcopy&channel=chunlei&clienttype=0&web=1&bdstoken={}",
-        shareid, uk, bdstoken
-    );
+    let url = account.api.pan_url(&format!(
+        "/share/transfer?shareid={}&from={}&ondup={}&channel=chunlei&clienttype=0&web=1&bdstoken={}",
+        shareid, uk, ondup, bdstoken
+    ));
 
     let fsidlist = serde_json::to_string(fs_ids)?;
 
-    let params = [("fsidlist", fsidlist.as_str()), ("path", savepath.as_str())];
+    let params = [("fsidlist", fsidlist.as_str()), ("path", savepath)];
 
     // 详细日志
     info!("📋 转存参数:");
@@ -149,39 +354,24 @@ pub async fn transfer_files(
     info!("  └─ from(uk): {}", uk);
     info!("  └─ fsidlist: {}", fsidlist);
     info!("  └─ 保存路径: {}", savepath);
-    info!("  └─ 重复处理: newcopy (自动重命名)");
+    info!("  └─ 重复处理: {}", ondup);
 
     // 移除 surl 前缀 '1'（如果存在）
     let surlparam = surl.strip_prefix('1').unwrap_or(surl);
-    let referer = format!("https://pan.baidu.com/share/init?surl={}", surlparam);
+    let referer = account.api.share_init_url(surlparam);
 
     info!("  └─ Referer: {}", referer);
 
     // 先访问 referer 页面，确保 Cookie 正确
     debug!("🌐 预访问 referer 页面...");
-    let _ = state
-        .client
-        .get(&referer)
-        .header("User-Agent", Config::browser_ua())
-        .send()
-        .await;
+    let _ = account.api.get(&referer).send().await;
 
     // 调用转存 API
     info!("🚀 发送转存请求...");
-    let resp = state
-        .client
-        .post(&url)
-        .header("User-Agent", Config::browser_ua())
+    let resp = account
+        .api
+        .ajax_post(&url)
         .header("Referer", &referer)
-        .header("Host", "pan.baidu.com")
-        .header("Origin", "https://pan.baidu.com")
-        .header(
-            "Content-Type",
-            "application/x-www-form-urlencoded; charset=UTF-8",
-        )
-        .header("Accept", "application/json, text/javascript, */*; q=0.01")
-        .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
-        .header("X-Requested-With", "XMLHttpRequest")
         .form(&params)
         .send()
         .await?;
@@ -205,7 +395,7 @@ pub async fn transfer_files(
         0 => {
             info!("✅ 转存成功! (errno=0)");
             info!("📂 文件已保存至: {}", savepath);
-            Ok(())
+            Ok(Transferred::Done)
         }
         2 => {
             // errno=2 有多种含义，需要详细判断
@@ -226,78 +416,17 @@ pub async fn transfer_files(
                     error!("❗ server 返回已存在但未创建 new copy (newno empty). 这通常表示目标位置已有相同文件或转存未实际写入。");
                     error!("  └─ show_msg: {}", result.show_msg);
                     error!("  └─ request_id: {:?}", result.request_id);
-                    // 尝试按策略 A：在保存路径下创建带时间戳的子目录并重试一次转存
-                    let timestamp = Utc::now().format("%Y%m%d-%H%M%S").to_string();
-                    let new_dir = format!("{}/copy-{}", savepath.trim_end_matches('/'), timestamp);
-                    info!("🔁 尝试创建子目录并重试转存: {}", new_dir);
-                    // 尝试创建子目录并作为该 if 分支的最终表达式返回结果
-                    match create_remote_dir(state, &new_dir, bdstoken).await {
-                        Ok(created) => {
-                            if created {
-                                info!("✅ 子目录创建成功，尝试在新目录执行转存...");
-                                // 重试转存到 new_dir
-                                let retry_params =
-                                    [("fsidlist", fsidlist.as_str()), ("path", new_dir.as_str())];
-                                let retry_resp = state
-                                    .client
-                                    .post(&url)
-                                    .header("User-Agent", Config::browser_ua())
-                                    .header("Referer", &referer)
-                                    .header("Host", "pan.baidu.com")
-                                    .header("Origin", "https://pan.baidu.com")
-                                    .header(
-                                        "Content-Type",
-                                        "application/x-www-form-urlencoded; charset=UTF-8",
-                                    )
-                                    .header(
-                                        "Accept",
-                                        "application/json, text/javascript, */*; q=0.01",
-                                    )
-                                    .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
-                                    .header("X-Requested-With", "XMLHttpRequest")
-                                    .form(&retry_params)
-                                    .send()
-                                    .await?;
-                                let retry_text = retry_resp.text().await?;
-                                info!("📨 重试转存响应: {}", retry_text);
-                                let retry_result: TransferResult =
-                                    serde_json::from_str(&retry_text).map_err(|e| {
-                                        anyhow!("解析重试响应失败: {}, body: {}", e, retry_text)
-                                    })?;
-                                if retry_result.errno == 0 || (retry_result.errno == 12) {
-                                    info!("✅ 重试转存成功 (errno={})", retry_result.errno);
-                                    Ok(())
-                                } else {
-                                    error!(
-                                        "❌ 重试转存仍然失败: errno={}, show_msg={}",
-                                        retry_result.errno, retry_result.show_msg
-                                    );
-                                    Err(anyhow!("重试转存失败: {}", retry_result.show_msg))
-                                }
-                            } else {
-                                error!("❌ 子目录创建返回失败，无法重试转存");
-                                Err(anyhow!(
-                                    "文件已存在，且无法创建子目录重试: {}",
-                                    result.show_msg
-                                ))
-                            }
-                        }
-                        Err(e) => {
-                            error!("❌ 创建子目录失败: {}", e);
-                            Err(anyhow!(
-                                "文件已存在，且创建子目录失败: {} ({})",
-                                result.show_msg,
-                                e
-                            ))
-                        }
-                    }
+                    // 由调用方在保存路径下创建带时间戳的子目录并重试（见 transfer_with_fallback）
+                    Ok(Transferred::DuplicateNoCopy {
+                        show_msg: result.show_msg,
+                    })
                 } else {
                     info!(
                         "📁 文件已存在（已创建副本 newno={}），转存完成",
                         result.newno
                     );
                     info!("💡 提示: {}", result.show_msg);
-                    Ok(())
+                    Ok(Transferred::Done)
                 }
             } else if msg_lower.contains("未登录")
                 || msg_lower.contains("需要登录")
@@ -313,7 +442,8 @@ pub async fn transfer_files(
                 error!("   1. 浏览器登录 pan.baidu.com");
                 error!("   2. F12 打开开发者工具");
                 error!("   3. Application -> Cookies -> BDUSS 和 STOKEN");
-                Err(anyhow!("Cookie失效: {}", result.show_msg))
+                error!("   或运行 baidu-direct-link login 扫码登录");
+                Err(BaiduError::CookieInvalid { errno: 2 }.into())
             } else if msg_lower.contains("路径")
                 || msg_lower.contains("目录")
                 || msg_lower.contains("文件夹")
@@ -344,7 +474,14 @@ pub async fn transfer_files(
         12 => {
             info!("✅ 转存完成 (errno=12)");
             info!("💡 errno=12 通常表示文件已存在或部分成功");
-            Ok(())
+            Ok(Transferred::Done)
+        }
+        -6 | -4 => {
+            error!("🔐 转存失败: Cookie 失效或未登录");
+            Err(BaiduError::CookieInvalid {
+                errno: result.errno,
+            }
+            .into())
         }
         -1 => {
             error!("❌ 转存失败: 文件不存在或已删除");
@@ -384,7 +521,7 @@ pub async fn transfer_files(
 
 /// 批量转存（预留接口）
 pub async fn do_transfer(
-    state: std::sync::Arc<AppState>,
+    account: std::sync::Arc<Account>,
     shareid: String,
     uk: String,
     fsids: Vec<u64>,
@@ -392,6 +529,6 @@ pub async fn do_transfer(
     surl: &str,
     _savepath: &str,
 ) -> Result<Vec<u64>> {
-    transfer_files(state.as_ref(), &shareid, &uk, &fsids, &bdstoken, surl).await?;
+    transfer_files(account.as_ref(), &shareid, &uk, &fsids, &bdstoken, surl).await?;
     Ok(fsids)
 }
@@ -1,5 +1,7 @@
 //! 数据类型
 
+use serde::Serialize;
+
 #[derive(Debug, Clone)]
 pub struct ShareFileInfo {
     pub shareid: String,
@@ -7,4 +9,38 @@ pub struct ShareFileInfo {
     pub fs_ids: Vec<u64>,
     pub bdstoken: String,
     pub filenames: Vec<String>, // 文件名列表
+    pub sizes: Vec<u64>,        // 与 fs_ids 一一对应的大小（目录为其中所有文件之和）
+    pub total_size: u64,        // 分享中所有文件大小之和
+    pub size_known: bool,       // 为 false 时目录过多未统计完，total_size 只是下限
+}
+
+/// 网盘容量（字节）
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct QuotaInfo {
+    pub total: u64,
+    pub used: u64,
+    pub free: u64,
+}
+
+/// 转存计划：哪些 fs_id 以何种重复策略转存到哪个目录
+///
+/// 由 [`plan_transfer`](super::transfer::plan_transfer) 生成，dry-run 时直接返回给调用方。
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferPlan {
+    pub shareid: String,
+    pub uk: String,
+    #[serde(skip)]
+    pub bdstoken: String,
+    pub surl: String,
+    pub fs_ids: Vec<u64>,
+    pub filenames: Vec<String>,
+    /// 与 `fs_ids` 一一对应的大小，用于按批统计实际转存的字节数
+    #[serde(skip)]
+    pub sizes: Vec<u64>,
+    pub total_size: u64,
+    /// 为 false 时分享中的目录过多未统计完，`total_size` 只是下限
+    pub size_known: bool,
+    pub save_path: String,
+    pub save_path_exists: bool,
+    pub ondup: String,
 }
@@ -1,24 +1,381 @@
 //! 配置文件加载
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
+use std::path::{Path, PathBuf};
+use url::Url;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+use crate::baidupcs::api::Endpoints;
+use crate::baidupcs::fs::ListLimits;
+use crate::secrets::{self, Secrets};
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Config {
+    /// 只使用 `[[accounts]]` 时可省略
+    #[serde(default)]
     pub baidu: BaiduConfig,
+    /// 额外的百度账号（`[[accounts]]`），`[baidu]` 中的 Cookie 视为默认账号
+    #[serde(default)]
+    pub accounts: Vec<AccountConfig>,
+    /// 多账号时的选择策略与故障切换
+    #[serde(default)]
+    pub pool: PoolConfig,
     #[serde(default)]
     pub web: WebConfig,
+    /// 后台转存任务（`POST /api/jobs`）
+    #[serde(default)]
+    pub jobs: JobsConfig,
+    /// 本地数据（Cookie 等）存放位置
+    #[serde(default)]
+    pub storage: StorageConfig,
+    /// 加密凭据文件
+    #[serde(default)]
+    pub secrets: SecretsConfig,
+    /// 配置文件路径（由 [`Config::load`] 记录，扫码登录等写回配置时使用）
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct BaiduConfig {
+    /// `[baidu]` 中 Cookie 对应账号的名称
+    #[serde(default = "default_account_name")]
+    pub name: String,
+    #[serde(default)]
     pub cookie_bduss: String,
+    #[serde(default)]
     pub cookie_stoken: String,
+    /// 从浏览器导出文件或 BaiduPCS-Go 配置导入 Cookie（见 [`crate::cookie_import`]）
+    #[serde(default)]
+    pub import_cookies: Option<PathBuf>,
     #[serde(default = "default_save_path")]
     pub save_path: String,
     #[serde(default = "default_http_timeout_secs")]
     pub http_timeout_secs: u64,
+    /// 转存后网盘使用率超过该百分比时告警
+    #[serde(default = "default_quota_warn_percent")]
+    pub quota_warn_percent: u8,
+    /// 百度接口地址（测试/预发环境可指向本地假服务器）
+    #[serde(default)]
+    pub endpoints: Endpoints,
+    /// 出站代理，所有账号共用
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+    /// 默认使用的请求配置（HTTP 身份），内置 `browser` 与 `app`
+    #[serde(default = "default_profile")]
+    pub profile: String,
+    /// 自定义请求配置（`[baidu.profiles.<name>]`），与内置配置同名时覆盖对应字段
+    #[serde(default)]
+    pub profiles: BTreeMap<String, RequestProfile>,
+}
+
+impl Default for BaiduConfig {
+    fn default() -> Self {
+        Self {
+            name: default_account_name(),
+            cookie_bduss: String::new(),
+            cookie_stoken: String::new(),
+            import_cookies: None,
+            save_path: default_save_path(),
+            http_timeout_secs: default_http_timeout_secs(),
+            quota_warn_percent: default_quota_warn_percent(),
+            endpoints: Endpoints::default(),
+            proxy: ProxyConfig::default(),
+            profile: default_profile(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+impl BaiduConfig {
+    /// 按名称解析请求配置（`None` 时使用 `[baidu].profile`），并校验请求头
+    pub fn request_profile(&self, name: Option<&str>) -> Result<RequestProfile> {
+        let name = name.filter(|n| !n.is_empty()).unwrap_or(&self.profile);
+        let profile = match (RequestProfile::builtin(name), self.profiles.get(name)) {
+            (Some(base), Some(custom)) => base.merge(custom),
+            (Some(base), None) => base,
+            (None, Some(custom)) => custom.clone(),
+            (None, None) => {
+                return Err(anyhow!(
+                    "未知的请求配置: {}（内置 browser / app，或在 [baidu.profiles.{}] 中定义）",
+                    name,
+                    name
+                ))
+            }
+        };
+        profile
+            .validate()
+            .map_err(|e| anyhow!("请求配置 {} 无效: {}", name, e))?;
+        Ok(profile)
+    }
+}
+
+/// 请求配置（HTTP 身份）：发往百度的每个请求都带上这些请求头，`cookies` 加入账号的 Cookie jar
+///
+/// 百度更新网页端后只需修改配置，无需发布新版本。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RequestProfile {
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub accept_language: Option<String>,
+    /// 额外请求头
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// 额外 Cookie（如 BAIDUID），优先于 Cookie jar 中保存的值
+    #[serde(default)]
+    pub cookies: BTreeMap<String, String>,
+}
+
+impl RequestProfile {
+    /// 内置配置：`browser`（网页端，默认）与 `app`（安卓客户端）
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "browser" => Some(Self {
+                user_agent: Some(Config::browser_ua().to_string()),
+                accept_language: Some("zh-CN,zh;q=0.9,en;q=0.8".to_string()),
+                ..Default::default()
+            }),
+            "app" => Some(Self {
+                user_agent: Some(Config::app_ua().to_string()),
+                ..Default::default()
+            }),
+            _ => None,
+        }
+    }
+
+    /// 用 `other` 中设置了的字段覆盖，请求头和 Cookie 按名称合并
+    pub fn merge(mut self, other: &Self) -> Self {
+        if other.user_agent.is_some() {
+            self.user_agent = other.user_agent.clone();
+        }
+        if other.accept_language.is_some() {
+            self.accept_language = other.accept_language.clone();
+        }
+        self.headers.extend(other.headers.clone());
+        self.cookies.extend(other.cookies.clone());
+        self
+    }
+
+    /// 请求头名称和值必须合法，否则每个请求都会失败
+    fn validate(&self) -> Result<()> {
+        let values = self
+            .user_agent
+            .iter()
+            .chain(self.accept_language.iter())
+            .chain(self.headers.values())
+            .chain(self.cookies.values());
+        for value in values {
+            reqwest::header::HeaderValue::from_str(value)
+                .map_err(|_| anyhow!("包含非法字符的值: {:?}", value))?;
+        }
+        for name in self.headers.keys() {
+            reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| anyhow!("非法的请求头名称: {:?}", name))?;
+        }
+        for name in self.cookies.keys() {
+            if name.is_empty() || name.contains([';', '=', ' ', ',']) {
+                return Err(anyhow!("非法的 Cookie 名称: {:?}", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn default_profile() -> String {
+    "browser".to_string()
+}
+
+/// 出站代理（`[baidu.proxy]`）
+///
+/// 未配置 `url` 时依次使用环境变量 `HTTPS_PROXY`、`ALL_PROXY`（及小写形式）。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ProxyConfig {
+    /// `http://`、`https://`、`socks5://` 或 `socks5h://`（由代理解析域名）
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// 不走代理的主机，匹配域名本身及其子域名，`*` 表示全部直连；同 `NO_PROXY`
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+}
+
+impl ProxyConfig {
+    /// 合并认证信息后的代理地址；未配置代理时为 `None`
+    pub fn resolved_url(&self) -> Result<Option<Url>> {
+        let Some(raw) = self.url.as_deref().filter(|u| !u.is_empty()) else {
+            return Ok(None);
+        };
+        let mut url = Url::parse(raw).map_err(|e| anyhow!("代理地址无效: {} ({})", raw, e))?;
+        if !matches!(url.scheme(), "http" | "https" | "socks5" | "socks5h") {
+            return Err(anyhow!(
+                "不支持的代理协议: {}（可选 http / https / socks5 / socks5h）",
+                url.scheme()
+            ));
+        }
+        if url.host_str().is_none() {
+            return Err(anyhow!("代理地址缺少主机: {}", raw));
+        }
+        if let Some(username) = self.username.as_deref().filter(|u| !u.is_empty()) {
+            url.set_username(username)
+                .map_err(|_| anyhow!("无法设置代理用户名"))?;
+            url.set_password(self.password.as_deref())
+                .map_err(|_| anyhow!("无法设置代理密码"))?;
+        }
+        Ok(Some(url))
+    }
+
+    /// 去掉认证信息的代理地址，用于日志和错误信息
+    pub fn display_url(&self) -> Option<String> {
+        let raw = self.url.as_deref().filter(|u| !u.is_empty())?;
+        match Url::parse(raw) {
+            Ok(mut url) => {
+                let _ = url.set_username("");
+                let _ = url.set_password(None);
+                Some(url.to_string().trim_end_matches('/').to_string())
+            }
+            Err(_) => Some(raw.to_string()),
+        }
+    }
+
+    /// 该主机是否直连（命中 `no_proxy`）
+    pub fn bypasses(&self, host: &str) -> bool {
+        self.no_proxy.iter().any(|entry| {
+            let entry = entry.trim().trim_start_matches('.');
+            entry == "*"
+                || host.eq_ignore_ascii_case(entry)
+                || host
+                    .to_ascii_lowercase()
+                    .ends_with(&format!(".{}", entry.to_ascii_lowercase()))
+        })
+    }
+}
+
+/// 一个百度账号
+///
+/// 超时、接口地址、容量告警阈值等与 `[baidu]` 共用。
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AccountConfig {
+    pub name: String,
+    /// 配置了 `import_cookies` 时可省略，从导入文件中读取
+    #[serde(default)]
+    pub cookie_bduss: String,
+    #[serde(default)]
+    pub cookie_stoken: String,
+    /// 从导入文件读取 Cookie；`cookie_bduss`/`cookie_stoken` 非空时以它们为准
+    #[serde(default)]
+    pub import_cookies: Option<PathBuf>,
+    /// 未设置时使用 `[baidu].save_path`
+    #[serde(default)]
+    pub save_path: Option<String>,
+    /// 请求配置名称，未设置时使用 `[baidu].profile`
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+/// 账号选择策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolStrategy {
+    /// 按配置顺序，优先使用靠前的账号
+    #[default]
+    Priority,
+    /// 轮流使用各账号
+    RoundRobin,
+    /// 优先使用剩余空间最多的账号（每次转存前查询容量）
+    MostFree,
+}
+
+impl std::str::FromStr for PoolStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "priority" => Ok(Self::Priority),
+            "round_robin" => Ok(Self::RoundRobin),
+            "most_free" => Ok(Self::MostFree),
+            _ => Err(anyhow!(
+                "未知的账号选择策略: {}（可选 priority / round_robin / most_free）",
+                s
+            )),
+        }
+    }
+}
+
+/// 账号池配置（`[pool]`）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PoolConfig {
+    #[serde(default)]
+    pub strategy: PoolStrategy,
+    /// 账号因 Cookie 失效或空间不足被标记为不可用后，多久再重新尝试（秒）
+    #[serde(default = "default_unhealthy_cooldown_secs")]
+    pub unhealthy_cooldown_secs: u64,
+    /// 同一账号同时进行的转存数（dry-run 不计），默认 1 即逐个执行，0 表示不限制
+    #[serde(default = "default_max_transfers_per_account")]
+    pub max_transfers_per_account: usize,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            strategy: PoolStrategy::default(),
+            unhealthy_cooldown_secs: default_unhealthy_cooldown_secs(),
+            max_transfers_per_account: default_max_transfers_per_account(),
+        }
+    }
+}
+
+fn default_max_transfers_per_account() -> usize {
+    1
+}
+
+/// 后台转存任务配置（`[jobs]`），见 [`crate::jobs`]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct JobsConfig {
+    /// 同时执行的任务数（修改后需重启）
+    #[serde(default = "default_job_workers")]
+    pub workers: usize,
+    /// 排队和执行中的任务上限，超出时拒绝新任务
+    #[serde(default = "default_max_pending_jobs")]
+    pub max_pending: usize,
+    /// 已结束的任务保留多久（秒），之后无法再查询
+    #[serde(default = "default_job_retention_secs")]
+    pub retention_secs: u64,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            workers: default_job_workers(),
+            max_pending: default_max_pending_jobs(),
+            retention_secs: default_job_retention_secs(),
+        }
+    }
+}
+
+fn default_job_workers() -> usize {
+    2
+}
+
+fn default_max_pending_jobs() -> usize {
+    100
+}
+
+fn default_job_retention_secs() -> u64 {
+    3600
+}
+
+fn default_unhealthy_cooldown_secs() -> u64 {
+    600
+}
+
+fn default_account_name() -> String {
+    "default".to_string()
 }
 
 fn default_save_path() -> String {
@@ -29,45 +386,425 @@ fn default_http_timeout_secs() -> u64 {
     30
 }
 
+fn default_quota_warn_percent() -> u8 {
+    90
+}
+
+/// 本地数据配置（`[storage]`）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StorageConfig {
+    /// 数据目录，Cookie 保存在其下的 `cookies/<账号名>.json`
+    #[serde(default = "default_data_dir")]
+    pub data_dir: PathBuf,
+    /// 是否持久化 Cookie jar（重启后保留百度后续下发的 Cookie）
+    #[serde(default = "default_true")]
+    pub persist_cookies: bool,
+    /// Web 服务定期保存 Cookie 的间隔（秒）
+    #[serde(default = "default_cookie_save_interval_secs")]
+    pub cookie_save_interval_secs: u64,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: default_data_dir(),
+            persist_cookies: true,
+            cookie_save_interval_secs: default_cookie_save_interval_secs(),
+        }
+    }
+}
+
+impl StorageConfig {
+    /// 账号 Cookie 文件路径；未启用持久化时为 `None`
+    pub fn cookie_file(&self, account: &str) -> Option<PathBuf> {
+        let file = format!("{}.json", account.replace(['/', '\\'], "_"));
+        self.persist_cookies
+            .then(|| self.data_dir.join("cookies").join(file))
+    }
+
+    /// API 令牌使用记录文件
+    pub fn token_usage_file(&self) -> PathBuf {
+        self.data_dir.join("api_tokens.json")
+    }
+
+    /// Web 用户文件（见 [`crate::users`]）
+    pub fn users_file(&self) -> PathBuf {
+        self.data_dir.join("users.json")
+    }
+}
+
+fn default_data_dir() -> PathBuf {
+    PathBuf::from("data")
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_cookie_save_interval_secs() -> u64 {
+    300
+}
+
+/// 加密凭据配置（`[secrets]`），见 [`crate::secrets`]
+///
+/// 口令通过环境变量 `SECRETS_PASSPHRASE` 或 `SECRETS_PASSPHRASE_FILE` 提供。
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SecretsConfig {
+    /// 加密凭据文件路径，其中的值覆盖配置文件中的明文
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct WebConfig {
+    /// 明文密码（兼容旧配置，启动时告警），建议改用 `password_hash`
     #[serde(default)]
     pub password: String,
+    /// `hash-password` 子命令生成的 argon2 哈希，设置后忽略 `password`
+    #[serde(default)]
+    pub password_hash: String,
+    /// 检查配置文件变化的间隔（秒），0 表示只在收到 SIGHUP 时重载
+    #[serde(default = "default_reload_interval_secs")]
+    pub reload_interval_secs: u64,
+    /// 监听地址，IPv6 写作 `::` 或 `[::1]`
+    #[serde(default = "default_bind")]
+    pub bind: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// 改为监听 Unix domain socket（供同机反向代理使用），设置后不再监听 TCP 端口
+    #[serde(default)]
+    pub unix_socket: Option<PathBuf>,
+    /// HTTPS（`[web.tls]`），启用后登录 Cookie 带 `Secure` 标志
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    /// 登录会话有效期（秒），到期后需重新登录
+    #[serde(default = "default_session_ttl_secs")]
+    pub session_ttl_secs: u64,
+    /// 登录会话空闲超时（秒），0 表示不限制
+    #[serde(default = "default_session_idle_secs")]
+    pub session_idle_secs: u64,
+    /// 供脚本使用的 API 令牌（`[[web.tokens]]`），通过 `Authorization: Bearer` 访问 `/api/*`
+    #[serde(default)]
+    pub tokens: Vec<ApiTokenConfig>,
+    /// 限流与登录失败锁定（`[web.rate_limit]`）
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// 文件管理递归列目录（`/api/fs/list?recursive=true`、`/api/fs/tree`）最多展开的层数
+    #[serde(default = "default_fs_max_depth")]
+    pub fs_max_depth: usize,
+    /// 文件管理递归列目录最多返回的条目数，超过时结果带 `truncated: true`
+    #[serde(default = "default_fs_max_entries")]
+    pub fs_max_entries: usize,
+}
+
+impl Default for WebConfig {
+    fn default() -> Self {
+        Self {
+            password: String::new(),
+            password_hash: String::new(),
+            reload_interval_secs: default_reload_interval_secs(),
+            bind: default_bind(),
+            port: default_port(),
+            unix_socket: None,
+            tls: None,
+            session_ttl_secs: default_session_ttl_secs(),
+            session_idle_secs: default_session_idle_secs(),
+            tokens: Vec::new(),
+            rate_limit: RateLimitConfig::default(),
+            fs_max_depth: default_fs_max_depth(),
+            fs_max_entries: default_fs_max_entries(),
+        }
+    }
+}
+
+impl WebConfig {
+    /// 是否设置了登录密码（明文或哈希）
+    pub fn password_required(&self) -> bool {
+        !self.password.is_empty() || !self.password_hash.is_empty()
+    }
+
+    /// TCP 监听地址（`host:port`，IPv6 带方括号）
+    pub fn listen_addr(&self) -> String {
+        let host = self.bind.trim_start_matches('[').trim_end_matches(']');
+        if host.contains(':') {
+            format!("[{}]:{}", host, self.port)
+        } else {
+            format!("{}:{}", host, self.port)
+        }
+    }
+
+    /// 是否以 HTTPS 提供服务
+    pub fn tls_enabled(&self) -> bool {
+        self.tls.is_some()
+    }
+
+    /// 文件管理递归列目录的上限，`depth` 为请求指定的深度（不能超过配置的上限）
+    pub fn fs_limits(&self, depth: Option<usize>) -> ListLimits {
+        ListLimits {
+            max_depth: Some(depth.map_or(self.fs_max_depth, |d| d.min(self.fs_max_depth))),
+            max_entries: Some(self.fs_max_entries),
+        }
+    }
+}
+
+/// API 令牌的权限范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// 转存（包含预览）
+    Transfer,
+    /// 预览转存计划、查询账号和容量
+    Preview,
+    /// 网盘文件管理
+    Fs,
+    /// 全部操作，包括会话、令牌管理和扫码登录
+    Admin,
+}
+
+impl Scope {
+    /// 持有该权限时是否可以执行需要 `required` 权限的操作
+    pub fn covers(self, required: Scope) -> bool {
+        self == required
+            || self == Scope::Admin
+            || (self == Scope::Transfer && required == Scope::Preview)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Transfer => "transfer",
+            Scope::Preview => "preview",
+            Scope::Fs => "fs",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for Scope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "transfer" => Ok(Self::Transfer),
+            "preview" => Ok(Self::Preview),
+            "fs" => Ok(Self::Fs),
+            "admin" => Ok(Self::Admin),
+            _ => Err(anyhow!(
+                "未知的权限范围: {}（可选 transfer / preview / fs / admin）",
+                s
+            )),
+        }
+    }
+}
+
+/// 一个 API 令牌（`[[web.tokens]]`）
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ApiTokenConfig {
+    /// 令牌名称，用于日志和使用记录
+    pub name: String,
+    /// 令牌值（至少 16 个字符，例如用 `openssl rand -hex 32` 生成）
+    pub token: String,
+    pub scopes: Vec<Scope>,
+}
+
+/// 限流配置（`[web.rate_limit]`），见 [`crate::ratelimit`]
+///
+/// 请求预算对每个客户端 IP 和每个登录身份（用户、管理员、API 令牌）分别计数。
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RateLimitConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 可信反向代理（IP 或 CIDR），只采信来自这些地址的 `X-Forwarded-For`
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
+    /// 连续登录失败多少次后锁定
+    #[serde(default = "default_login_max_failures")]
+    pub login_max_failures: u32,
+    /// 首次锁定时长（秒），之后每多失败一次翻倍
+    #[serde(default = "default_login_lockout_secs")]
+    pub login_lockout_secs: u64,
+    /// 锁定时长上限（秒）
+    #[serde(default = "default_login_lockout_max_secs")]
+    pub login_lockout_max_secs: u64,
+    /// 按路径前缀的请求预算，取最长匹配的前缀；配置后替换全部默认值
+    #[serde(default = "default_route_budgets")]
+    pub routes: BTreeMap<String, RouteBudget>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            trusted_proxies: Vec::new(),
+            login_max_failures: default_login_max_failures(),
+            login_lockout_secs: default_login_lockout_secs(),
+            login_lockout_max_secs: default_login_lockout_max_secs(),
+            routes: default_route_budgets(),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// 解析后的可信代理网段，单个 IP 视为 /32（IPv6 为 /128）
+    pub fn proxies(&self) -> Result<Vec<ipnet::IpNet>> {
+        self.trusted_proxies
+            .iter()
+            .map(|p| {
+                p.parse::<ipnet::IpNet>()
+                    .or_else(|_| p.parse::<std::net::IpAddr>().map(ipnet::IpNet::from))
+                    .map_err(|_| anyhow!("无效的可信代理地址: {}", p))
+            })
+            .collect()
+    }
+
+    /// `path` 适用的请求预算（最长前缀匹配），返回匹配的前缀和预算
+    pub fn budget(&self, path: &str) -> Option<(&str, RouteBudget)> {
+        self.routes
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(prefix, budget)| (prefix.as_str(), *budget))
+    }
+}
+
+/// 一条路由的请求预算：每 `per_secs` 秒最多 `requests` 次（令牌桶，允许该数量的突发）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RouteBudget {
+    pub requests: u32,
+    pub per_secs: u64,
+}
+
+fn default_login_max_failures() -> u32 {
+    5
+}
+
+fn default_login_lockout_secs() -> u64 {
+    30
+}
+
+fn default_login_lockout_max_secs() -> u64 {
+    3600
+}
+
+fn default_route_budgets() -> BTreeMap<String, RouteBudget> {
+    [
+        ("/api/", 300, 60),
+        ("/api/login", 10, 60),
+        ("/api/transfer", 10, 60),
+    ]
+    .into_iter()
+    .map(|(prefix, requests, per_secs)| (prefix.to_string(), RouteBudget { requests, per_secs }))
+    .collect()
+}
+
+/// TLS 证书配置，文件变化时自动重新加载（见 [`crate::server`]）
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TlsConfig {
+    /// PEM 格式证书链
+    pub cert: PathBuf,
+    /// PEM 格式私钥（PKCS#8 / PKCS#1 / SEC1）
+    pub key: PathBuf,
+}
+
+fn default_reload_interval_secs() -> u64 {
+    5
+}
+
+fn default_bind() -> String {
+    "0.0.0.0".to_string()
+}
+
+fn default_port() -> u16 {
+    5200
+}
+
+fn default_session_ttl_secs() -> u64 {
+    30 * 24 * 3600
+}
+
+fn default_session_idle_secs() -> u64 {
+    7 * 24 * 3600
 }
 
+fn default_fs_max_depth() -> usize {
+    10
+}
+
+fn default_fs_max_entries() -> usize {
+    10_000
+}
+
+/// 可通过环境变量覆盖的配置项：（环境变量，配置键）
+///
+/// `BDUSS`、`STOKEN`、`WEB_PASSWORD`、`WEB_PASSWORD_HASH` 还支持 `*_FILE` 形式。
+pub const ENV_OVERRIDES: &[(&str, &str)] = &[
+    ("BDUSS", "baidu.cookie_bduss"),
+    ("STOKEN", "baidu.cookie_stoken"),
+    ("IMPORT_COOKIES", "baidu.import_cookies"),
+    ("SAVE_PATH", "baidu.save_path"),
+    ("HTTP_TIMEOUT_SECS", "baidu.http_timeout_secs"),
+    ("QUOTA_WARN_PERCENT", "baidu.quota_warn_percent"),
+    ("BAIDU_PAN_URL", "baidu.endpoints.pan"),
+    ("BAIDU_PASSPORT_URL", "baidu.endpoints.passport"),
+    ("BAIDU_PCS_URL", "baidu.endpoints.pcs"),
+    ("BAIDU_PROFILE", "baidu.profile"),
+    ("POOL_STRATEGY", "pool.strategy"),
+    ("DATA_DIR", "storage.data_dir"),
+    ("SECRETS_FILE", "secrets.file"),
+    ("WEB_PASSWORD", "web.password"),
+    ("WEB_PASSWORD_HASH", "web.password_hash"),
+    ("WEB_BIND", "web.bind"),
+    ("PORT", "web.port"),
+    ("WEB_UNIX_SOCKET", "web.unix_socket"),
+    ("WEB_TLS_CERT", "web.tls.cert"),
+    ("WEB_TLS_KEY", "web.tls.key"),
+    ("WEB_TRUSTED_PROXIES", "web.rate_limit.trusted_proxies"),
+];
+
 impl Config {
-    /// 从文件加载配置，支持环境变量覆盖
-    /// 优先级：环境变量 > 配置文件
+    /// 从文件加载配置，支持加密凭据文件和环境变量覆盖
+    /// 优先级：环境变量（含 `*_FILE`）> 加密凭据文件 > 配置文件
     pub fn load(path: &str) -> Result<Self> {
         let mut config = if std::path::Path::new(path).exists() {
             let content = fs::read_to_string(path)?;
             toml::from_str(&content)?
         } else {
             // 如果文件不存在，从环境变量创建默认配置
-            Config {
-                baidu: BaiduConfig {
-                    cookie_bduss: String::new(),
-                    cookie_stoken: String::new(),
-                    save_path: default_save_path(),
-                    http_timeout_secs: default_http_timeout_secs(),
-                },
-                web: WebConfig {
-                    password: String::new(),
-                },
-            }
+            Config::default()
         };
+        config.path = Some(PathBuf::from(path));
 
-        // 环境变量覆盖（优先级更高）
-        if let Ok(bduss) = std::env::var("BDUSS") {
+        // 加密凭据文件
+        if let Ok(file) = std::env::var("SECRETS_FILE") {
+            if !file.is_empty() {
+                config.secrets.file = Some(PathBuf::from(file));
+            }
+        }
+        if let Some(file) = &config.secrets.file {
+            let passphrase = secrets::passphrase_from_env()?.ok_or_else(|| {
+                anyhow!(
+                    "已配置加密凭据文件 {}，请通过 SECRETS_PASSPHRASE 或 SECRETS_PASSPHRASE_FILE 提供口令",
+                    file.display()
+                )
+            })?;
+            Secrets::load(file, &passphrase)?.apply(&mut config);
+        }
+
+        // 环境变量覆盖（优先级更高），BDUSS/STOKEN/WEB_PASSWORD 支持 `*_FILE`
+        if let Some(bduss) = secrets::env_or_file("BDUSS")? {
             if !bduss.is_empty() {
                 config.baidu.cookie_bduss = bduss;
             }
         }
-        if let Ok(stoken) = std::env::var("STOKEN") {
+        if let Some(stoken) = secrets::env_or_file("STOKEN")? {
             if !stoken.is_empty() {
                 config.baidu.cookie_stoken = stoken;
             }
         }
+        if let Ok(file) = std::env::var("IMPORT_COOKIES") {
+            if !file.is_empty() {
+                config.baidu.import_cookies = Some(PathBuf::from(file));
+            }
+        }
         if let Ok(save_path) = std::env::var("SAVE_PATH") {
             if !save_path.is_empty() {
                 config.baidu.save_path = save_path;
@@ -78,19 +815,277 @@ impl Config {
                 config.baidu.http_timeout_secs = secs;
             }
         }
-        if let Ok(password) = std::env::var("WEB_PASSWORD") {
+        if let Ok(percent) = std::env::var("QUOTA_WARN_PERCENT") {
+            if let Ok(percent) = percent.parse::<u8>() {
+                config.baidu.quota_warn_percent = percent;
+            }
+        }
+        for (key, target) in [
+            ("BAIDU_PAN_URL", &mut config.baidu.endpoints.pan),
+            ("BAIDU_PASSPORT_URL", &mut config.baidu.endpoints.passport),
+            ("BAIDU_PCS_URL", &mut config.baidu.endpoints.pcs),
+        ] {
+            if let Ok(url) = std::env::var(key) {
+                if !url.is_empty() {
+                    *target = url.trim_end_matches('/').to_string();
+                }
+            }
+        }
+        if let Ok(profile) = std::env::var("BAIDU_PROFILE") {
+            if !profile.is_empty() {
+                config.baidu.profile = profile;
+            }
+        }
+        if let Ok(strategy) = std::env::var("POOL_STRATEGY") {
+            if !strategy.is_empty() {
+                config.pool.strategy = strategy.parse()?;
+            }
+        }
+        if let Ok(data_dir) = std::env::var("DATA_DIR") {
+            if !data_dir.is_empty() {
+                config.storage.data_dir = PathBuf::from(data_dir);
+            }
+        }
+        // 代理：配置文件优先，未配置时使用通用的代理环境变量
+        if config
+            .baidu
+            .proxy
+            .url
+            .as_deref()
+            .unwrap_or_default()
+            .is_empty()
+        {
+            config.baidu.proxy.url = ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+                .iter()
+                .find_map(|key| std::env::var(key).ok().filter(|v| !v.is_empty()));
+        }
+        if config.baidu.proxy.no_proxy.is_empty() {
+            if let Some(list) = ["NO_PROXY", "no_proxy"]
+                .iter()
+                .find_map(|key| std::env::var(key).ok().filter(|v| !v.is_empty()))
+            {
+                config.baidu.proxy.no_proxy = list
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string)
+                    .collect();
+            }
+        }
+        if let Some(password) = secrets::env_or_file("WEB_PASSWORD")? {
             config.web.password = password;
         }
+        if let Some(hash) = secrets::env_or_file("WEB_PASSWORD_HASH")? {
+            config.web.password_hash = hash;
+        }
+        if !config.web.password_hash.is_empty() {
+            crate::password::validate_hash(&config.web.password_hash)?;
+        }
+        config.validate_tokens()?;
+        if let Ok(proxies) = std::env::var("WEB_TRUSTED_PROXIES") {
+            // 逗号分隔，如 `127.0.0.1,10.0.0.0/8`
+            config.web.rate_limit.trusted_proxies = proxies
+                .split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        config.validate_rate_limit()?;
+        if config.jobs.workers == 0 {
+            return Err(anyhow!("[jobs] workers 至少为 1"));
+        }
+        if let Ok(bind) = std::env::var("WEB_BIND") {
+            if !bind.is_empty() {
+                config.web.bind = bind;
+            }
+        }
+        if let Ok(port) = std::env::var("PORT") {
+            if let Ok(port) = port.parse::<u16>() {
+                config.web.port = port;
+            }
+        }
+        if let Ok(socket) = std::env::var("WEB_UNIX_SOCKET") {
+            if !socket.is_empty() {
+                config.web.unix_socket = Some(PathBuf::from(socket));
+            }
+        }
+        match (std::env::var("WEB_TLS_CERT"), std::env::var("WEB_TLS_KEY")) {
+            (Ok(cert), Ok(key)) if !cert.is_empty() && !key.is_empty() => {
+                config.web.tls = Some(TlsConfig {
+                    cert: PathBuf::from(cert),
+                    key: PathBuf::from(key),
+                });
+            }
+            (Ok(_), Err(_)) | (Err(_), Ok(_)) => {
+                return Err(anyhow!("WEB_TLS_CERT 与 WEB_TLS_KEY 需要同时设置"));
+            }
+            _ => {}
+        }
+        if config.web.unix_socket.is_some() && config.web.tls.is_some() {
+            return Err(anyhow!(
+                "[web] unix_socket 与 [web.tls] 不能同时设置：监听 Unix socket 时由反向代理提供 HTTPS"
+            ));
+        }
 
         Ok(config)
     }
 
-    /// 获取应用 User-Agent
+    /// API 令牌名称不能重复，令牌值不能过短，至少授予一个权限
+    fn validate_tokens(&self) -> Result<()> {
+        let mut names = std::collections::HashSet::new();
+        for token in &self.web.tokens {
+            if token.name.is_empty() || !names.insert(token.name.as_str()) {
+                return Err(anyhow!("API 令牌名称为空或重复: {:?}", token.name));
+            }
+            if token.token.len() < 16 {
+                return Err(anyhow!("API 令牌 {} 过短（至少 16 个字符）", token.name));
+            }
+            if token.scopes.is_empty() {
+                return Err(anyhow!("API 令牌 {} 没有授予任何权限", token.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// 可信代理地址必须能解析，请求预算不能为 0
+    fn validate_rate_limit(&self) -> Result<()> {
+        let rate_limit = &self.web.rate_limit;
+        rate_limit.proxies()?;
+        for (prefix, budget) in &rate_limit.routes {
+            if budget.requests == 0 || budget.per_secs == 0 {
+                return Err(anyhow!(
+                    "请求预算 {} 的 requests 和 per_secs 不能为 0",
+                    prefix
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 所有账号：`[baidu]` 中配置了 Cookie（或导入文件）时作为第一个账号，其后是 `[[accounts]]`
+    pub fn account_configs(&self) -> Result<Vec<AccountConfig>> {
+        let mut accounts = Vec::new();
+        if !self.baidu.cookie_bduss.is_empty()
+            || self.baidu.import_cookies.is_some()
+            || self.accounts.is_empty()
+        {
+            accounts.push(AccountConfig {
+                name: self.baidu.name.clone(),
+                cookie_bduss: self.baidu.cookie_bduss.clone(),
+                cookie_stoken: self.baidu.cookie_stoken.clone(),
+                import_cookies: self.baidu.import_cookies.clone(),
+                save_path: Some(self.baidu.save_path.clone()),
+                profile: None,
+            });
+        }
+        accounts.extend(self.accounts.iter().cloned());
+
+        let mut names = std::collections::HashSet::new();
+        for account in &accounts {
+            if !names.insert(account.name.as_str()) {
+                return Err(anyhow!("账号名称重复: {}", account.name));
+            }
+        }
+        Ok(accounts)
+    }
+
+    /// 将账号 Cookie 写回配置文件（保留原有注释和格式，文件不存在时创建），返回写入的文件
+    ///
+    /// `account` 为空或等于 `[baidu].name` 时写入 `[baidu]`，否则写入同名的 `[[accounts]]`，
+    /// 不存在则追加一个。配置了加密凭据文件时改为写入该文件，不在配置中留下明文。
+    pub fn save_credentials(
+        path: &Path,
+        account: Option<&str>,
+        bduss: &str,
+        stoken: &str,
+    ) -> Result<PathBuf> {
+        let content = if path.exists() {
+            fs::read_to_string(path)?
+        } else {
+            String::new()
+        };
+        let mut doc = content
+            .parse::<toml_edit::DocumentMut>()
+            .map_err(|e| anyhow!("解析配置文件失败: {}", e))?;
+
+        let baidu_name = doc
+            .get("baidu")
+            .and_then(|b| b.get("name"))
+            .and_then(|n| n.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(default_account_name);
+
+        let account = account.filter(|a| !a.is_empty() && *a != baidu_name);
+
+        let secrets_file = std::env::var("SECRETS_FILE")
+            .ok()
+            .filter(|f| !f.is_empty())
+            .or_else(|| {
+                doc.get("secrets")
+                    .and_then(|s| s.get("file"))
+                    .and_then(|f| f.as_str())
+                    .map(str::to_string)
+            });
+        if let Some(file) = secrets_file.map(PathBuf::from) {
+            let passphrase = secrets::passphrase_from_env()?
+                .ok_or_else(|| anyhow!("写入加密凭据文件需要通过 SECRETS_PASSPHRASE 提供口令"))?;
+            let mut secrets = if file.exists() {
+                Secrets::load(&file, &passphrase)?
+            } else {
+                Secrets::default()
+            };
+            secrets.set_credentials(account, bduss, stoken);
+            secrets.save(&file, &passphrase)?;
+            return Ok(file);
+        }
+
+        match account {
+            None => {
+                let baidu = doc
+                    .entry("baidu")
+                    .or_insert(toml_edit::table())
+                    .as_table_mut()
+                    .ok_or_else(|| anyhow!("配置文件中的 [baidu] 不是表"))?;
+                baidu["cookie_bduss"] = toml_edit::value(bduss);
+                baidu["cookie_stoken"] = toml_edit::value(stoken);
+            }
+            Some(name) => {
+                let accounts = doc
+                    .entry("accounts")
+                    .or_insert(toml_edit::Item::ArrayOfTables(Default::default()))
+                    .as_array_of_tables_mut()
+                    .ok_or_else(|| anyhow!("配置文件中的 accounts 不是 [[accounts]] 数组"))?;
+                let existing = accounts
+                    .iter_mut()
+                    .find(|t| t.get("name").and_then(|n| n.as_str()) == Some(name));
+                match existing {
+                    Some(table) => {
+                        table["cookie_bduss"] = toml_edit::value(bduss);
+                        table["cookie_stoken"] = toml_edit::value(stoken);
+                    }
+                    None => {
+                        let mut table = toml_edit::Table::new();
+                        table["name"] = toml_edit::value(name);
+                        table["cookie_bduss"] = toml_edit::value(bduss);
+                        table["cookie_stoken"] = toml_edit::value(stoken);
+                        accounts.push(table);
+                    }
+                }
+            }
+        }
+
+        // 配置文件中保存着 BDUSS/STOKEN，与加密凭据文件一样原子写入并只允许自己读写
+        secrets::write_private(path, doc.to_string().as_bytes())?;
+        Ok(path.to_path_buf())
+    }
+
+    /// 内置 `app` 请求配置的 User-Agent
     pub fn app_ua() -> &'static str {
         "netdisk;2.2.51.6;netdisk;10.0.63;PC;android-android"
     }
 
-    /// 获取浏览器 User-Agent
+    /// 内置 `browser` 请求配置的 User-Agent
     pub fn browser_ua() -> &'static str {
         "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36"
     }
@@ -0,0 +1,340 @@
+//! 从浏览器导出文件或其他工具导入 Cookie
+//!
+//! 支持三种格式（自动识别）：
+//! - Netscape `cookies.txt`（curl、wget 及多数浏览器扩展的“导出为 txt”）
+//! - Cookie-Editor / EditThisCookie 等扩展导出的 JSON 数组
+//! - BaiduPCS-Go 的 `pcs_config.json`
+//!
+//! 只保留 `baidu.com` 及其子域名下的 Cookie。
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+/// 导入文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    Netscape,
+    ExtensionJson,
+    BaiduPcsGo,
+}
+
+impl std::fmt::Display for ImportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ImportFormat::Netscape => "Netscape cookies.txt",
+            ImportFormat::ExtensionJson => "浏览器扩展 JSON",
+            ImportFormat::BaiduPcsGo => "BaiduPCS-Go 配置",
+        })
+    }
+}
+
+/// 一条导入的 Cookie
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedCookie {
+    /// 不带前导 `.` 的域名
+    pub domain: String,
+    /// 是否同时对子域名生效
+    pub include_subdomains: bool,
+    pub path: String,
+    pub name: String,
+    pub value: String,
+    /// 过期时间（Unix 秒），会话 Cookie 为 `None`
+    pub expires: Option<i64>,
+}
+
+impl ImportedCookie {
+    /// 转换为 Set-Cookie 字符串及其来源 URL；已过期时返回 `None`
+    pub fn to_set_cookie(&self, now: i64) -> Option<(String, String)> {
+        let mut cookie = format!("{}={}; Path={}", self.name, self.value, self.path);
+        if self.include_subdomains {
+            cookie.push_str(&format!("; Domain=.{}", self.domain));
+        }
+        if let Some(expires) = self.expires {
+            if expires <= now {
+                return None;
+            }
+            cookie.push_str(&format!("; Max-Age={}", expires - now));
+        }
+        Some((cookie, format!("https://{}{}", self.domain, self.path)))
+    }
+}
+
+/// 导入结果
+#[derive(Debug, Clone)]
+pub struct ImportedCookies {
+    pub format: ImportFormat,
+    pub cookies: Vec<ImportedCookie>,
+}
+
+impl ImportedCookies {
+    /// 按名称查找，优先取网盘域名下的值（passport 与网盘各有一个 STOKEN）
+    pub fn get(&self, name: &str) -> Option<&str> {
+        let mut matches = self
+            .cookies
+            .iter()
+            .filter(|c| c.name == name && !c.value.is_empty());
+        let first = matches.clone().next();
+        matches
+            .find(|c| c.domain == "pan.baidu.com")
+            .or(first)
+            .map(|c| c.value.as_str())
+    }
+
+    pub fn bduss(&self) -> Option<&str> {
+        self.get("BDUSS")
+    }
+
+    pub fn stoken(&self) -> Option<&str> {
+        self.get("STOKEN")
+    }
+
+    /// 导入的 Cookie 名称（去重，保持顺序）
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for cookie in &self.cookies {
+            if !names.contains(&cookie.name.as_str()) {
+                names.push(&cookie.name);
+            }
+        }
+        names
+    }
+}
+
+/// 读取并解析导入文件
+pub fn import_file(path: &Path) -> Result<ImportedCookies> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("读取 Cookie 文件失败: {} ({})", path.display(), e))?;
+    parse(&content).map_err(|e| anyhow!("{}: {}", path.display(), e))
+}
+
+/// 自动识别格式并解析
+pub fn parse(content: &str) -> Result<ImportedCookies> {
+    let trimmed = content.trim_start_matches('\u{feff}').trim_start();
+    let (format, cookies) = if trimmed.starts_with('[') {
+        (ImportFormat::ExtensionJson, parse_extension_json(trimmed)?)
+    } else if trimmed.starts_with('{') {
+        (ImportFormat::BaiduPcsGo, parse_baidupcs_go(trimmed)?)
+    } else {
+        (ImportFormat::Netscape, parse_netscape(trimmed)?)
+    };
+
+    let cookies: Vec<_> = cookies.into_iter().filter(is_baidu).collect();
+    if cookies.is_empty() {
+        return Err(anyhow!("未找到 baidu.com 的 Cookie（格式: {}）", format));
+    }
+    Ok(ImportedCookies { format, cookies })
+}
+
+fn is_baidu(cookie: &ImportedCookie) -> bool {
+    cookie.domain == "baidu.com" || cookie.domain.ends_with(".baidu.com")
+}
+
+/// 统一域名写法，返回（域名, 是否对子域名生效）
+fn normalize_domain(domain: &str, include_subdomains: bool) -> (String, bool) {
+    match domain.strip_prefix('.') {
+        Some(domain) => (domain.to_string(), true),
+        None => (domain.to_string(), include_subdomains),
+    }
+}
+
+/// Netscape 格式：domain  flag  path  secure  expiration  name  value（Tab 分隔）
+fn parse_netscape(content: &str) -> Result<Vec<ImportedCookie>> {
+    let mut cookies = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        // curl 用 `#HttpOnly_` 前缀标记 HttpOnly Cookie，其余 `#` 开头为注释
+        let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 7 {
+            return Err(anyhow!("cookies.txt 第 {} 行格式错误", i + 1));
+        }
+        let (domain, include_subdomains) =
+            normalize_domain(fields[0], fields[1].eq_ignore_ascii_case("TRUE"));
+        let expires = fields[4].trim().parse::<i64>().ok().filter(|&t| t > 0);
+        cookies.push(ImportedCookie {
+            domain,
+            include_subdomains,
+            path: fields[2].to_string(),
+            name: fields[5].to_string(),
+            value: fields[6].trim_end().to_string(),
+            expires,
+        });
+    }
+    Ok(cookies)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExtensionCookie {
+    domain: String,
+    name: String,
+    value: String,
+    #[serde(default = "default_path")]
+    path: String,
+    #[serde(default)]
+    host_only: bool,
+    #[serde(default)]
+    session: bool,
+    #[serde(default)]
+    expiration_date: Option<f64>,
+}
+
+fn default_path() -> String {
+    "/".to_string()
+}
+
+/// Cookie-Editor / EditThisCookie 导出的 JSON 数组
+fn parse_extension_json(content: &str) -> Result<Vec<ImportedCookie>> {
+    let items: Vec<ExtensionCookie> =
+        serde_json::from_str(content).map_err(|e| anyhow!("解析 Cookie JSON 失败: {}", e))?;
+    Ok(items
+        .into_iter()
+        .map(|c| {
+            let (domain, include_subdomains) = normalize_domain(&c.domain, !c.host_only);
+            ImportedCookie {
+                domain,
+                include_subdomains,
+                path: c.path,
+                name: c.name,
+                value: c.value,
+                expires: c.expiration_date.filter(|_| !c.session).map(|t| t as i64),
+            }
+        })
+        .collect())
+}
+
+#[derive(Debug, Deserialize)]
+struct PcsGoConfig {
+    #[serde(default)]
+    baidu_active_uid: u64,
+    #[serde(default)]
+    baidu_user_list: Vec<PcsGoUser>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PcsGoUser {
+    #[serde(default)]
+    uid: u64,
+    #[serde(default)]
+    bduss: String,
+    #[serde(default)]
+    stoken: String,
+    /// 新版本会保存完整的 Cookie 字符串
+    #[serde(default)]
+    cookies: String,
+}
+
+/// BaiduPCS-Go 配置：取当前登录的用户（没有则取第一个）
+fn parse_baidupcs_go(content: &str) -> Result<Vec<ImportedCookie>> {
+    let config: PcsGoConfig =
+        serde_json::from_str(content).map_err(|e| anyhow!("解析 BaiduPCS-Go 配置失败: {}", e))?;
+    let user = config
+        .baidu_user_list
+        .iter()
+        .find(|u| u.uid == config.baidu_active_uid)
+        .or_else(|| config.baidu_user_list.first())
+        .ok_or_else(|| anyhow!("BaiduPCS-Go 配置中没有已登录的用户"))?;
+
+    let cookie = |domain: &str, name: &str, value: &str| ImportedCookie {
+        domain: domain.to_string(),
+        include_subdomains: true,
+        path: "/".to_string(),
+        name: name.to_string(),
+        value: value.to_string(),
+        expires: None,
+    };
+
+    let mut cookies: Vec<ImportedCookie> = user
+        .cookies
+        .split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .map(|(name, value)| cookie("baidu.com", name, value))
+        .collect();
+    if !user.bduss.is_empty() {
+        cookies.retain(|c| c.name != "BDUSS");
+        cookies.push(cookie("baidu.com", "BDUSS", &user.bduss));
+    }
+    if !user.stoken.is_empty() {
+        cookies.retain(|c| c.name != "STOKEN");
+        cookies.push(cookie("pan.baidu.com", "STOKEN", &user.stoken));
+    }
+    Ok(cookies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_netscape() {
+        let content = "# Netscape HTTP Cookie File\n\
+            .baidu.com\tTRUE\t/\tFALSE\t1999999999\tBDUSS\tbduss-value\n\
+            #HttpOnly_pan.baidu.com\tFALSE\t/\tTRUE\t0\tSTOKEN\tpan-stoken\n\
+            passport.baidu.com\tFALSE\t/\tTRUE\t0\tSTOKEN\tpassport-stoken\n\
+            .example.com\tTRUE\t/\tFALSE\t0\tOTHER\tx\n";
+        let imported = parse(content).unwrap();
+
+        assert_eq!(imported.format, ImportFormat::Netscape);
+        assert_eq!(imported.cookies.len(), 3);
+        assert_eq!(imported.bduss(), Some("bduss-value"));
+        // 优先使用网盘域名下的 STOKEN
+        assert_eq!(imported.stoken(), Some("pan-stoken"));
+        assert_eq!(imported.cookies[0].expires, Some(1999999999));
+        assert!(imported.cookies[0].include_subdomains);
+    }
+
+    #[test]
+    fn test_parse_extension_json() {
+        let content = r#"[
+            {"domain": ".baidu.com", "name": "BAIDUID", "value": "id", "path": "/", "hostOnly": false, "session": false, "expirationDate": 1999999999.5},
+            {"domain": "pan.baidu.com", "name": "STOKEN", "value": "st", "path": "/", "hostOnly": true, "session": true}
+        ]"#;
+        let imported = parse(content).unwrap();
+
+        assert_eq!(imported.format, ImportFormat::ExtensionJson);
+        assert_eq!(imported.names(), vec!["BAIDUID", "STOKEN"]);
+        assert_eq!(imported.bduss(), None);
+        assert!(!imported.cookies[1].include_subdomains);
+        assert_eq!(imported.cookies[1].expires, None);
+    }
+
+    #[test]
+    fn test_parse_baidupcs_go() {
+        let content = r#"{
+            "baidu_active_uid": 2,
+            "baidu_user_list": [
+                {"uid": 1, "bduss": "old", "stoken": ""},
+                {"uid": 2, "bduss": "active-bduss", "stoken": "active-stoken", "cookies": "BDUSS=active-bduss; PANPSC=psc"}
+            ]
+        }"#;
+        let imported = parse(content).unwrap();
+
+        assert_eq!(imported.format, ImportFormat::BaiduPcsGo);
+        assert_eq!(imported.bduss(), Some("active-bduss"));
+        assert_eq!(imported.stoken(), Some("active-stoken"));
+        assert_eq!(imported.names(), vec!["PANPSC", "BDUSS", "STOKEN"]);
+    }
+
+    #[test]
+    fn test_to_set_cookie() {
+        let cookie = ImportedCookie {
+            domain: "baidu.com".to_string(),
+            include_subdomains: true,
+            path: "/".to_string(),
+            name: "BAIDUID".to_string(),
+            value: "id".to_string(),
+            expires: Some(200),
+        };
+        let (set_cookie, url) = cookie.to_set_cookie(100).unwrap();
+        assert_eq!(
+            set_cookie,
+            "BAIDUID=id; Path=/; Domain=.baidu.com; Max-Age=100"
+        );
+        assert_eq!(url, "https://baidu.com/");
+        assert!(cookie.to_set_cookie(300).is_none());
+    }
+}
@@ -0,0 +1,197 @@
+//! CSRF 防护：保护依赖登录 Cookie 的修改类接口
+//!
+//! 三层防护：
+//! - 登录 Cookie 带 `SameSite=Strict`，其他站点发起的请求不携带 Cookie；
+//! - 修改类请求（`POST`/`PUT`/`PATCH`/`DELETE`）的 `Origin`（没有时取 `Referer`）必须与本站一致；
+//! - 携带登录 Cookie 的修改类请求还必须在 [`CSRF_HEADER`] 头中带上会话的 CSRF 令牌
+//!   （同步令牌，登录时生成，注入 `index.html` 并在登录响应中返回）。
+//!
+//! 登录接口（`/api/login`）只检查来源。使用 `Authorization: Bearer` 的请求不依赖 Cookie，不做检查。
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, Uri},
+    middleware::Next,
+    response::Response,
+};
+use axum_extra::extract::CookieJar;
+use subtle::ConstantTimeEq;
+use tracing::warn;
+use url::Url;
+
+use crate::auth::{self, AuthError, AUTH_COOKIE_NAME};
+use crate::{ratelimit, SharedState};
+
+/// 携带 CSRF 令牌的请求头
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// 登录接口，不检查 CSRF 令牌
+const LOGIN_PATH: &str = "/api/login";
+
+/// 中间件：检查修改类请求的来源和 CSRF 令牌，失败时返回 `403`
+pub async fn protect(
+    State(shared): State<SharedState>,
+    cookies: CookieJar,
+    req: Request,
+    next: Next,
+) -> Result<Response, AuthError> {
+    if req.method().is_safe() || auth::bearer_token(req.headers()).is_some() {
+        return Ok(next.run(req).await);
+    }
+    let state = shared.load();
+    let forwarded = ratelimit::peer_trusted(ratelimit::peer_ip(&req), &state.trusted_proxies);
+    if !same_origin(req.headers(), req.uri(), forwarded) {
+        warn!(
+            "🛡️ 拒绝跨站请求: {} {}（Origin: {:?}）",
+            req.method(),
+            req.uri().path(),
+            req.headers().get(header::ORIGIN)
+        );
+        return Err(AuthError::forbidden("跨站请求被拒绝"));
+    }
+
+    // 登录页拿不到旧会话的令牌，带着仍有效的旧 Cookie 重新登录时只检查来源
+    if req.uri().path() == LOGIN_PATH {
+        return Ok(next.run(req).await);
+    }
+
+    // 无效或过期的 Cookie 交给认证中间件返回 401
+    let session = cookies
+        .get(AUTH_COOKIE_NAME)
+        .and_then(|token| state.sessions.get(token.value(), &state.config.web));
+    if let Some(session) = session {
+        let sent = req
+            .headers()
+            .get(CSRF_HEADER)
+            .map(|v| v.as_bytes())
+            .unwrap_or_default();
+        if !bool::from(sent.ct_eq(session.csrf_token.as_bytes())) {
+            warn!("🛡️ CSRF 令牌无效: {} {}", req.method(), req.uri().path());
+            return Err(AuthError::forbidden("CSRF 令牌无效，请刷新页面后重试"));
+        }
+    }
+    Ok(next.run(req).await)
+}
+
+/// `Origin`（没有时取 `Referer`）的主机与请求的 `Host`（或反向代理传来的 `X-Forwarded-Host`）一致
+///
+/// 两者都没有时放行：浏览器发起的跨站修改请求总会带 `Origin`，命令行工具则不带。
+/// `X-Forwarded-Host` 只在直连地址是可信代理时（`trust_forwarded`）采信，与 `X-Forwarded-For` 相同。
+fn same_origin(headers: &HeaderMap, uri: &Uri, trust_forwarded: bool) -> bool {
+    let source = headers
+        .get(header::ORIGIN)
+        .or_else(|| headers.get(header::REFERER))
+        .map(|v| v.to_str().unwrap_or_default());
+    let Some(source) = source else {
+        return true;
+    };
+    // `Origin: null`（沙箱 iframe、file:// 等）无法解析，按跨站处理
+    let Some(origin) = Url::parse(source).ok().and_then(|url| authority(&url)) else {
+        return false;
+    };
+
+    // HTTP/2 请求没有 Host 头，主机在 URI 中
+    let host = headers
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| uri.authority().map(|a| a.as_str()));
+    let forwarded = headers
+        .get("x-forwarded-host")
+        .filter(|_| trust_forwarded)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .map(str::trim);
+    [host, forwarded]
+        .into_iter()
+        .flatten()
+        .any(|h| h.eq_ignore_ascii_case(&origin))
+}
+
+/// `host[:port]`，默认端口省略
+fn authority(url: &Url) -> Option<String> {
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_origin() {
+        let uri: Uri = "/api/transfer".parse().unwrap();
+        let headers = |pairs: &[(&'static str, &'static str)]| {
+            let mut map = HeaderMap::new();
+            for (name, value) in pairs {
+                map.insert(*name, value.parse().unwrap());
+            }
+            map
+        };
+
+        assert!(same_origin(
+            &headers(&[("host", "localhost:5200")]),
+            &uri,
+            true
+        ));
+        assert!(same_origin(
+            &headers(&[
+                ("host", "localhost:5200"),
+                ("origin", "http://localhost:5200")
+            ]),
+            &uri,
+            true
+        ));
+        assert!(same_origin(
+            &headers(&[
+                ("host", "pan.example.com"),
+                ("origin", "https://pan.example.com")
+            ]),
+            &uri,
+            true
+        ));
+        assert!(same_origin(
+            &headers(&[
+                ("host", "127.0.0.1:5200"),
+                ("x-forwarded-host", "pan.example.com"),
+                ("referer", "https://pan.example.com/"),
+            ]),
+            &uri,
+            true
+        ));
+        // 不是经可信代理连接时，伪造的 X-Forwarded-Host 不被采信
+        assert!(!same_origin(
+            &headers(&[
+                ("host", "127.0.0.1:5200"),
+                ("x-forwarded-host", "evil.example"),
+                ("origin", "https://evil.example"),
+            ]),
+            &uri,
+            false
+        ));
+        assert!(!same_origin(
+            &headers(&[
+                ("host", "localhost:5200"),
+                ("origin", "https://evil.example")
+            ]),
+            &uri,
+            true
+        ));
+        assert!(!same_origin(
+            &headers(&[("host", "localhost:5200"), ("origin", "null")]),
+            &uri,
+            true
+        ));
+        // 端口不同视为跨站
+        assert!(!same_origin(
+            &headers(&[
+                ("host", "localhost:5200"),
+                ("origin", "http://localhost:8080")
+            ]),
+            &uri,
+            true
+        ));
+    }
+}
@@ -0,0 +1,456 @@
+//! 配置自检（`doctor` 子命令）
+//!
+//! 一次性检查配置来源、各账号 Cookie、保存路径、Web 密码、监听地址与证书、代理和网络连通性，
+//! 退出码可用作容器启动前的检查：
+//!
+//! | 退出码 | 含义 |
+//! |-------|------|
+//! | 0 | 全部通过（`--strict` 时不能有警告） |
+//! | 1 | 有检查未通过 |
+//! | 2 | 配置无法加载 |
+
+use std::path::Path;
+use std::time::Duration;
+
+use crate::baidupcs::{self, BaiduError};
+use crate::config::{Config, ProxyConfig, TlsConfig, WebConfig, ENV_OVERRIDES};
+use crate::secrets::{self, Secrets};
+use crate::server;
+use crate::state::{Account, AppState};
+use crate::users::UserStore;
+
+/// 检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+    /// 前置检查未通过或不适用
+    Skip,
+}
+
+impl Status {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Status::Ok => "✅",
+            Status::Warn => "⚠️",
+            Status::Fail => "❌",
+            Status::Skip => "⏭️",
+        }
+    }
+}
+
+/// 一项检查
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// 配置项的来源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// 环境变量（含 `*_FILE`）
+    Env(String),
+    /// 加密凭据文件
+    Secrets,
+    /// 配置文件
+    File,
+    Default,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Env(name) => write!(f, "环境变量 {}", name),
+            Source::Secrets => write!(f, "加密凭据文件"),
+            Source::File => write!(f, "配置文件"),
+            Source::Default => write!(f, "默认值"),
+        }
+    }
+}
+
+/// 自检报告
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub config_path: String,
+    /// 配置无法加载时的错误
+    pub load_error: Option<String>,
+    /// 各配置项的来源（配置键，来源）
+    pub sources: Vec<(&'static str, Source)>,
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    pub fn count(&self, status: Status) -> usize {
+        self.checks.iter().filter(|c| c.status == status).count()
+    }
+
+    /// 进程退出码，见模块文档
+    pub fn exit_code(&self, strict: bool) -> i32 {
+        if self.load_error.is_some() {
+            2
+        } else if self.count(Status::Fail) > 0 || (strict && self.count(Status::Warn) > 0) {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// 输出为终端文本
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let exists = Path::new(&self.config_path).exists();
+        out.push_str(&format!(
+            "配置文件: {}{}\n",
+            self.config_path,
+            if exists {
+                ""
+            } else {
+                "（不存在，仅使用环境变量）"
+            }
+        ));
+        if let Some(e) = &self.load_error {
+            out.push_str(&format!("❌ 配置加载失败: {}\n", e));
+            return out;
+        }
+
+        out.push_str("\n配置来源:\n");
+        let width = self.sources.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
+        for (key, source) in &self.sources {
+            out.push_str(&format!("  {:width$}  {}\n", key, source, width = width));
+        }
+
+        out.push_str("\n检查结果:\n");
+        for check in &self.checks {
+            out.push_str(&format!(
+                "  {} {}: {}\n",
+                check.status.icon(),
+                check.name,
+                check.detail
+            ));
+        }
+        out.push_str(&format!(
+            "\n共 {} 项：{} 通过，{} 警告，{} 失败，{} 跳过\n",
+            self.checks.len(),
+            self.count(Status::Ok),
+            self.count(Status::Warn),
+            self.count(Status::Fail),
+            self.count(Status::Skip)
+        ));
+        out
+    }
+}
+
+/// 加载配置并执行全部检查
+pub async fn run(config_path: &str) -> Report {
+    let mut report = Report {
+        config_path: config_path.to_string(),
+        ..Default::default()
+    };
+    let config = match Config::load(config_path) {
+        Ok(config) => config,
+        Err(e) => {
+            report.load_error = Some(format!("{:#}", e));
+            return report;
+        }
+    };
+    report.sources = setting_sources(config_path, &config);
+
+    let checks = &mut report.checks;
+    checks.push(web_password_check(&config));
+    checks.push(listen_check(&config.web).await);
+    if let Some(tls) = &config.web.tls {
+        checks.push(tls_check(tls));
+    }
+
+    // Cookie 格式等问题会导致账号无法构建
+    let state = match AppState::new(config) {
+        Ok(state) => state,
+        Err(e) => {
+            checks.push(Check::new("账号", Status::Fail, format!("{:#}", e)));
+            return report;
+        }
+    };
+
+    checks.push(proxy_check(&state.config.baidu.proxy).await);
+    let reachable = network_check(&state.default_account()).await;
+    let online = reachable.status == Status::Ok;
+    checks.push(reachable);
+
+    for account in &state.accounts {
+        checks.extend(account_checks(account, online).await);
+    }
+    state.save_cookies();
+    report
+}
+
+/// 判断各配置项来自环境变量、加密凭据文件、配置文件还是默认值
+pub fn setting_sources(config_path: &str, config: &Config) -> Vec<(&'static str, Source)> {
+    let file: Option<toml::Value> = std::fs::read_to_string(config_path)
+        .ok()
+        .and_then(|content| toml::from_str(&content).ok());
+    let secrets = config.secrets.file.as_ref().and_then(|path| {
+        let passphrase = secrets::passphrase_from_env().ok()??;
+        Secrets::load(path, &passphrase).ok()
+    });
+
+    ENV_OVERRIDES
+        .iter()
+        .map(|&(env, key)| {
+            let set = |name: &str| std::env::var(name).is_ok_and(|v| !v.is_empty());
+            let file_env = format!("{}_FILE", env);
+            let from_secrets = secrets.as_ref().is_some_and(|s| match key {
+                "baidu.cookie_bduss" => s.cookie_bduss.is_some(),
+                "baidu.cookie_stoken" => s.cookie_stoken.is_some(),
+                "web.password" => s.web_password.is_some(),
+                _ => false,
+            });
+            let in_file = file.as_ref().is_some_and(|root| {
+                key.split('.')
+                    .try_fold(root, |value, part| value.get(part))
+                    .is_some()
+            });
+
+            let source = if set(env) {
+                Source::Env(env.to_string())
+            } else if set(&file_env) {
+                Source::Env(file_env)
+            } else if from_secrets {
+                Source::Secrets
+            } else if in_file {
+                Source::File
+            } else {
+                Source::Default
+            };
+            (key, source)
+        })
+        .collect()
+}
+
+fn web_password_check(config: &Config) -> Check {
+    let web = &config.web;
+    let users = match UserStore::load(config.storage.users_file()) {
+        Ok(users) => users.list(),
+        Err(e) => return Check::new("Web 密码", Status::Fail, format!("{:#}", e)),
+    };
+    if !web.password_required() && !users.is_empty() {
+        Check::new(
+            "Web 密码",
+            Status::Ok,
+            format!("未设置，使用 {} 个 Web 用户登录", users.len()),
+        )
+    } else if !web.password_hash.is_empty() {
+        Check::new("Web 密码", Status::Ok, "已设置（argon2 哈希）")
+    } else if !web.password.is_empty() {
+        Check::new(
+            "Web 密码",
+            Status::Warn,
+            "使用明文密码，建议运行 hash-password 子命令生成 password_hash",
+        )
+    } else {
+        Check::new(
+            "Web 密码",
+            Status::Warn,
+            "未设置，任何能访问端口的人都可以使用 Web 界面",
+        )
+    }
+}
+
+/// 检查 Web 服务能否监听（服务已在运行时会失败）
+async fn listen_check(web: &WebConfig) -> Check {
+    if let Some(path) = &web.unix_socket {
+        let dir = path
+            .parent()
+            .filter(|d| !d.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        return if !dir.is_dir() {
+            Check::new(
+                "监听地址",
+                Status::Fail,
+                format!("{} 的目录不存在", path.display()),
+            )
+        } else if path.exists() {
+            Check::new(
+                "监听地址",
+                Status::Warn,
+                format!("{} 已存在，启动时将被替换", path.display()),
+            )
+        } else {
+            Check::new("监听地址", Status::Ok, format!("unix:{}", path.display()))
+        };
+    }
+
+    let addr = web.listen_addr();
+    match tokio::net::TcpListener::bind(&addr).await {
+        Ok(_) => Check::new("监听地址", Status::Ok, format!("{} 可用", addr)),
+        Err(e) => Check::new(
+            "监听地址",
+            Status::Fail,
+            format!("{} 无法监听: {}", addr, e),
+        ),
+    }
+}
+
+/// 检查 TLS 证书与私钥能否加载
+fn tls_check(tls: &TlsConfig) -> Check {
+    match server::server_config(tls) {
+        Ok(_) => Check::new(
+            "TLS 证书",
+            Status::Ok,
+            format!("{} 可用", tls.cert.display()),
+        ),
+        Err(e) => Check::new("TLS 证书", Status::Fail, format!("{:#}", e)),
+    }
+}
+
+/// 检查代理能否连接（只建立 TCP 连接，是否能连到百度由网络连通检查确认）
+async fn proxy_check(proxy: &ProxyConfig) -> Check {
+    let url = match proxy.resolved_url() {
+        Ok(Some(url)) => url,
+        Ok(None) => return Check::new("代理", Status::Ok, "未配置，直连百度"),
+        Err(e) => return Check::new("代理", Status::Fail, e.to_string()),
+    };
+    let display = proxy.display_url().unwrap_or_default();
+    let host = url.host_str().unwrap_or_default().to_string();
+    let port = url.port_or_known_default().unwrap_or(match url.scheme() {
+        "socks5" | "socks5h" => 1080,
+        _ => 8080,
+    });
+
+    let connect = tokio::net::TcpStream::connect((host.as_str(), port));
+    match tokio::time::timeout(Duration::from_secs(5), connect).await {
+        Ok(Ok(_)) => Check::new("代理", Status::Ok, format!("{} 可连接", display)),
+        Ok(Err(e)) => Check::new("代理", Status::Fail, format!("{} 无法连接: {}", display, e)),
+        Err(_) => Check::new("代理", Status::Fail, format!("{} 连接超时", display)),
+    }
+}
+
+/// 检查能否连接百度网盘（只要有 HTTP 响应即视为可达）
+async fn network_check(account: &Account) -> Check {
+    let url = account.api.pan_url("/");
+    let result = account
+        .api
+        .get(&url)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await;
+    match result {
+        Ok(resp) => Check::new(
+            "网络连通",
+            Status::Ok,
+            format!("{} 可访问（HTTP {}）", url, resp.status().as_u16()),
+        ),
+        Err(e) => Check::new("网络连通", Status::Fail, format!("无法访问 {}: {}", url, e)),
+    }
+}
+
+/// 单个账号：Cookie 是否有效、保存路径是否存在且可写
+async fn account_checks(account: &Account, online: bool) -> Vec<Check> {
+    let name = |what: &str| format!("账号 {} {}", account.name, what);
+    if !online {
+        return vec![Check::new(name("Cookie"), Status::Skip, "网络不可达")];
+    }
+
+    let info = match baidupcs::whoami(account).await {
+        Ok(info) => info,
+        Err(e) => {
+            let status = if matches!(
+                e.downcast_ref::<BaiduError>(),
+                Some(BaiduError::CookieInvalid { .. })
+            ) {
+                Status::Fail
+            } else {
+                Status::Warn
+            };
+            return vec![
+                Check::new(name("Cookie"), status, e.to_string()),
+                Check::new(name("保存路径"), Status::Skip, "Cookie 未通过检查"),
+            ];
+        }
+    };
+    let mut checks = vec![Check::new(
+        name("Cookie"),
+        Status::Ok,
+        format!("有效：{}（{}）", info.username, info.vip_level),
+    )];
+
+    let path = &account.save_path;
+    match baidupcs::fs::exists(account, path).await {
+        Ok(true) => {}
+        Ok(false) => {
+            checks.push(Check::new(
+                name("保存路径"),
+                Status::Fail,
+                format!("{} 不存在，请先在网盘中创建", path),
+            ));
+            return checks;
+        }
+        Err(e) => {
+            checks.push(Check::new(name("保存路径"), Status::Warn, e.to_string()));
+            return checks;
+        }
+    }
+
+    // 不实际写入：能取到文件操作所需的 bdstoken 且仍有剩余空间即视为可写
+    let writable = match baidupcs::fs::get_bdstoken(account).await {
+        Err(e) => Check::new(
+            name("保存路径"),
+            Status::Fail,
+            format!("{} 存在，但无法获取写入凭证: {}", path, e),
+        ),
+        Ok(_) => match &info.quota {
+            Some(quota) if quota.free == 0 => Check::new(
+                name("保存路径"),
+                Status::Fail,
+                format!("{} 存在，但网盘已无剩余空间", path),
+            ),
+            Some(quota) => Check::new(
+                name("保存路径"),
+                Status::Ok,
+                format!(
+                    "{} 存在且可写，剩余 {}",
+                    path,
+                    baidupcs::quota::format_size(quota.free)
+                ),
+            ),
+            None => Check::new(name("保存路径"), Status::Ok, format!("{} 存在且可写", path)),
+        },
+    };
+    checks.push(writable);
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code() {
+        let mut report = Report::default();
+        report
+            .checks
+            .push(Check::new("Web 密码", Status::Warn, "未设置"));
+        assert_eq!(report.exit_code(false), 0);
+        assert_eq!(report.exit_code(true), 1);
+
+        report
+            .checks
+            .push(Check::new("Cookie", Status::Fail, "失效"));
+        assert_eq!(report.exit_code(false), 1);
+
+        report.load_error = Some("TOML 语法错误".to_string());
+        assert_eq!(report.exit_code(false), 2);
+    }
+}
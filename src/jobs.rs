@@ -0,0 +1,391 @@
+//! 后台转存任务
+//!
+//! `POST /api/jobs` 校验请求后立即返回任务 ID，转存流程在后台执行，`GET /api/jobs/:id`
+//! 查询阶段、进度、结果或错误，不再受浏览器和反向代理的请求超时限制；
+//! `GET /api/jobs/:id/events` 以 Server-Sent Events 实时推送进度事件（见 [`JobQueue::subscribe`]）。
+//! 同时执行的任务数由 `[jobs] workers` 限制，其余任务排队；同一账号上的转存由账号池
+//! 逐个执行（见 [`crate::pool`]）。任务只保存在内存中，重启服务后丢失。
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, Semaphore};
+use tracing::{error, info};
+
+use crate::baidupcs::{Phase, Progress, TransferEvent, TransferPlan};
+use crate::config::JobsConfig;
+
+/// 任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// 等待空闲的 worker
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl JobStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Queued => "排队中",
+            Self::Running => "执行中",
+            Self::Succeeded => "已完成",
+            Self::Failed => "失败",
+        }
+    }
+
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed)
+    }
+}
+
+/// 任务进度
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JobProgress {
+    /// 按阶段估算的完成百分比
+    pub percent: u8,
+    /// 分享中的文件数（列出文件后才有）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub files: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_size: Option<u64>,
+    /// 已转存成功的文件数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transferred: Option<usize>,
+}
+
+/// 任务成功时的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct JobResult {
+    pub message: String,
+    pub account: String,
+    pub file_count: usize,
+    pub save_path: String,
+    /// 转存计划（dry-run 时返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<TransferPlan>,
+}
+
+/// 一个转存任务
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: String,
+    /// 提交者（见 [`crate::auth::Principal::identity`]），匿名提交时为空
+    #[serde(skip)]
+    pub owner: Option<String>,
+    pub share_url: String,
+    pub dry_run: bool,
+    pub status: JobStatus,
+    /// 转存流程当前所处的阶段
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase: Option<Phase>,
+    /// 正在使用的账号
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    pub progress: JobProgress,
+    /// 到目前为止的进度事件
+    pub events: Vec<TransferEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<JobResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl Job {
+    /// 根据转存流程的进度事件更新阶段和进度
+    fn apply(&mut self, event: &TransferEvent) {
+        match event {
+            TransferEvent::Phase { phase, account } => {
+                self.phase = Some(*phase);
+                self.account = Some(account.clone());
+                self.progress.percent = match phase {
+                    Phase::ShareInfo => 10,
+                    Phase::Planning => 30,
+                    Phase::QuotaCheck => 50,
+                    Phase::Transferring => 70,
+                };
+            }
+            TransferEvent::FilesDiscovered { count, total_size } => {
+                self.progress.files = Some(*count);
+                self.progress.total_size = Some(*total_size);
+            }
+            TransferEvent::ChunkTransferred {
+                index,
+                total,
+                files,
+                ..
+            } => {
+                *self.progress.transferred.get_or_insert(0) += files;
+                self.progress.percent = (70 + 29 * index / (*total).max(1)) as u8;
+            }
+            // 换账号重试时从头开始，进度随下一个阶段事件更新
+            TransferEvent::AccountFailed { .. } => self.progress.transferred = None,
+        }
+        self.events.push(event.clone());
+    }
+
+    fn finish(&mut self, result: Result<JobResult, String>) {
+        self.finished_at = Some(Utc::now());
+        match result {
+            Ok(result) => {
+                self.status = JobStatus::Succeeded;
+                self.account = Some(result.account.clone());
+                self.progress.percent = 100;
+                self.result = Some(result);
+            }
+            Err(message) => {
+                self.status = JobStatus::Failed;
+                self.error = Some(message);
+            }
+        }
+    }
+}
+
+/// 推送给任务订阅者的更新
+#[derive(Debug, Clone)]
+pub enum JobUpdate {
+    /// 转存流程的进度事件
+    Event(TransferEvent),
+    /// 开始执行或结束后的任务快照
+    Job(Box<Job>),
+}
+
+struct Entry {
+    job: Job,
+    updates: broadcast::Sender<JobUpdate>,
+}
+
+/// 每个任务的更新缓冲，订阅者落后太多时丢弃最早的更新
+const UPDATE_BUFFER: usize = 64;
+
+/// 任务队列（热重载时沿用）
+pub struct JobQueue {
+    jobs: Mutex<HashMap<String, Entry>>,
+    workers: Arc<Semaphore>,
+}
+
+impl JobQueue {
+    pub fn new(config: &JobsConfig) -> Self {
+        Self {
+            jobs: Mutex::new(HashMap::new()),
+            workers: Arc::new(Semaphore::new(config.workers.max(1))),
+        }
+    }
+
+    /// 提交任务，返回排队中的任务；排队和执行中的任务达到上限时返回错误
+    ///
+    /// `run` 在取得 worker 后执行，收到的 [`Progress`] 用于报告阶段和进度。
+    pub fn submit<F, Fut>(
+        self: &Arc<Self>,
+        config: &JobsConfig,
+        owner: Option<String>,
+        share_url: &str,
+        dry_run: bool,
+        run: F,
+    ) -> Result<Job>
+    where
+        F: FnOnce(Progress) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<JobResult, String>> + Send + 'static,
+    {
+        let job = Job {
+            id: crate::session::random_string(9),
+            owner,
+            share_url: share_url.to_string(),
+            dry_run,
+            status: JobStatus::Queued,
+            phase: None,
+            account: None,
+            progress: JobProgress::default(),
+            events: Vec::new(),
+            result: None,
+            error: None,
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+        };
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            let expire = job.created_at - Duration::seconds(config.retention_secs as i64);
+            jobs.retain(|_, e| e.job.finished_at.is_none_or(|t| t > expire));
+            let pending = jobs
+                .values()
+                .filter(|e| !e.job.status.is_finished())
+                .count();
+            if pending >= config.max_pending {
+                return Err(anyhow!(
+                    "任务队列已满（{} 个任务未完成），请稍后重试",
+                    pending
+                ));
+            }
+            jobs.insert(
+                job.id.clone(),
+                Entry {
+                    job: job.clone(),
+                    updates: broadcast::channel(UPDATE_BUFFER).0,
+                },
+            );
+        }
+        info!("🗂️ 任务 {} 已提交: {}", job.id, job.share_url);
+
+        let (queue, id) = (self.clone(), job.id.clone());
+        tokio::spawn(async move {
+            let _permit = queue.workers.clone().acquire_owned().await;
+            queue.update(&id, |job| {
+                job.status = JobStatus::Running;
+                job.started_at = Some(Utc::now());
+                None
+            });
+            let progress = {
+                let (queue, id) = (queue.clone(), id.clone());
+                Progress::new(move |event| {
+                    queue.update(&id, |job| {
+                        job.apply(&event);
+                        Some(JobUpdate::Event(event))
+                    })
+                })
+            };
+            // 转存流程 panic 时任务标记为失败，不会一直停留在执行中
+            let result = tokio::spawn(run(progress))
+                .await
+                .unwrap_or_else(|e| Err(format!("任务异常终止: {}", e)));
+            match &result {
+                Ok(_) => info!("✅ 任务 {} 完成", id),
+                Err(e) => error!("❌ 任务 {} 失败: {}", id, e),
+            }
+            queue.update(&id, |job| {
+                job.finish(result);
+                None
+            });
+        });
+        Ok(job)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.jobs.lock().unwrap().get(id).map(|e| e.job.clone())
+    }
+
+    /// 任务的当前快照和之后的更新
+    ///
+    /// 快照中已包含之前的进度事件；任务结束后不会再有更新，任务过期被清理时接收端关闭。
+    pub fn subscribe(&self, id: &str) -> Option<(Job, broadcast::Receiver<JobUpdate>)> {
+        let jobs = self.jobs.lock().unwrap();
+        let entry = jobs.get(id)?;
+        Some((entry.job.clone(), entry.updates.subscribe()))
+    }
+
+    /// 修改任务并通知订阅者：`f` 返回的进度事件，没有时发送修改后的任务快照
+    fn update(&self, id: &str, f: impl FnOnce(&mut Job) -> Option<JobUpdate>) {
+        if let Some(entry) = self.jobs.lock().unwrap().get_mut(id) {
+            let update =
+                f(&mut entry.job).unwrap_or_else(|| JobUpdate::Job(Box::new(entry.job.clone())));
+            // 没有订阅者时发送失败，忽略
+            let _ = entry.updates.send(update);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn wait_finished(queue: &JobQueue, id: &str) -> Job {
+        for _ in 0..100 {
+            let job = queue.get(id).unwrap();
+            if job.status.is_finished() {
+                return job;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("任务 {} 未结束", id);
+    }
+
+    #[tokio::test]
+    async fn test_jobs_queue_progress_and_limits() {
+        let config = JobsConfig {
+            workers: 1,
+            max_pending: 2,
+            retention_secs: 3600,
+        };
+        let queue = Arc::new(JobQueue::new(&config));
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+
+        // 第一个任务报告进度后等待，占住唯一的 worker
+        let first = queue
+            .submit(
+                &config,
+                None,
+                "https://pan.baidu.com/s/1a",
+                false,
+                |progress| async move {
+                    progress.emit(TransferEvent::Phase {
+                        phase: Phase::Transferring,
+                        account: "main".to_string(),
+                    });
+                    progress.emit(TransferEvent::FilesDiscovered {
+                        count: 3,
+                        total_size: 1024,
+                    });
+                    let _ = released.await;
+                    Err("分享链接已失效或过期".to_string())
+                },
+            )
+            .unwrap();
+        let second = queue
+            .submit(
+                &config,
+                None,
+                "https://pan.baidu.com/s/1b",
+                true,
+                |_| async {
+                    Ok(JobResult {
+                        message: "预览".to_string(),
+                        account: "main".to_string(),
+                        file_count: 1,
+                        save_path: "/".to_string(),
+                        plan: None,
+                    })
+                },
+            )
+            .unwrap();
+        assert!(queue
+            .submit(
+                &config,
+                None,
+                "https://pan.baidu.com/s/1c",
+                true,
+                |_| async { Err(String::new()) }
+            )
+            .is_err());
+
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let running = queue.get(&first.id).unwrap();
+        assert_eq!(running.status, JobStatus::Running);
+        assert_eq!(running.phase, Some(Phase::Transferring));
+        assert_eq!(running.progress.files, Some(3));
+        assert_eq!(queue.get(&second.id).unwrap().status, JobStatus::Queued);
+
+        // 订阅时快照包含之前的事件，之后收到结束时的快照
+        let (snapshot, mut updates) = queue.subscribe(&first.id).unwrap();
+        assert_eq!(snapshot.events.len(), 2);
+        release.send(()).unwrap();
+        match updates.recv().await.unwrap() {
+            JobUpdate::Job(job) => assert_eq!(job.status, JobStatus::Failed),
+            other => panic!("unexpected update: {:?}", other),
+        }
+        let failed = wait_finished(&queue, &first.id).await;
+        assert_eq!(failed.error.as_deref(), Some("分享链接已失效或过期"));
+        let done = wait_finished(&queue, &second.id).await;
+        assert_eq!(done.status, JobStatus::Succeeded);
+        assert_eq!(done.progress.percent, 100);
+    }
+}
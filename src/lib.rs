@@ -1,12 +1,25 @@
 //! 百度网盘转存（精简版）
 
+pub mod auth;
 pub mod baidupcs;
 pub mod config;
+pub mod cookie_import;
+pub mod csrf;
+pub mod doctor;
+pub mod jobs;
+pub mod password;
+pub mod pool;
+pub mod ratelimit;
+pub mod reload;
+pub mod secrets;
+pub mod server;
+pub mod session;
 pub mod state;
+pub mod users;
 pub mod web;
 
 pub use config::Config;
-pub use state::AppState;
+pub use state::{AppState, SharedState};
 
 /// 库版本
 pub const VERSION: &str = env!("CARGO_PKG_VERSION"); // ✅ 修复：移除了反斜杠
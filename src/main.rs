@@ -2,7 +2,27 @@ use anyhow::{anyhow, Result};
 use std::sync::Arc;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use baidu_direct_link::{baidupcs, config::Config, AppState};
+use baidu_direct_link::{baidupcs, baidupcs::fs, config::Config, state::Account, AppState};
+
+const USAGE: &str = "用法:
+  baidu-direct-link [--account <name>] [--dry-run] <share_url> [pwd] [config_path]
+  baidu-direct-link ls [-r] [dir]          列出目录（-r 递归）
+  baidu-direct-link tree [dir] [depth]     以树形显示目录
+  baidu-direct-link mkdir <path>           逐级创建目录
+  baidu-direct-link mv <src>... <dest>     移动/重命名
+  baidu-direct-link cp <src>... <dest>     复制
+  baidu-direct-link rm <path>...           删除（进入回收站）
+  baidu-direct-link whoami                 显示当前账号、会员等级和容量
+  baidu-direct-link login                  扫码登录，将 BDUSS/STOKEN 写入配置文件
+  baidu-direct-link import-cookies <file>  从 cookies.txt / 扩展 JSON / BaiduPCS-Go 配置导入 Cookie
+  baidu-direct-link doctor [--strict]      检查配置、Cookie、保存路径、监听地址和网络（退出码 0/1/2）
+  baidu-direct-link secrets encrypt <plain.toml> <out>  将明文凭据加密为凭据文件
+  baidu-direct-link secrets decrypt <file>              解密并输出凭据文件内容
+  baidu-direct-link hash-password          输入 Web 密码，输出可填入 [web] password_hash 的哈希
+
+所有命令都可用 --account <name>（或环境变量 BAIDU_ACCOUNT）选择账号；转存未指定账号时按账号池策略
+选择并自动切换，其他命令默认使用第一个账号
+子命令的配置文件路径通过环境变量 CONFIG_PATH 指定（默认 config.toml）";
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -17,15 +37,114 @@ async fn main() -> Result<()> {
 
     tracing::info!("🚀 百度网盘转存工具启动中...");
 
-    // 用法：baidu-direct-link <share_url> [pwd] [config_path]
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    let account = take_option(&mut args, "--account")?
+        .or_else(|| std::env::var("BAIDU_ACCOUNT").ok())
+        .filter(|a| !a.is_empty());
+    let account = account.as_deref();
+
+    match args.get(1).map(|s| s.as_str()) {
+        Some("ls" | "tree" | "mkdir" | "mv" | "cp" | "rm") => {
+            run_fs_command(&args[1..], account).await
+        }
+        Some("whoami") => run_whoami(account).await,
+        Some("login") => run_login(account).await,
+        Some("import-cookies") => run_import_cookies(&args[2..], account),
+        Some("secrets") => run_secrets(&args[2..]),
+        Some("doctor") => run_doctor(&args[2..]).await,
+        Some("hash-password") => run_hash_password(),
+        Some("-h" | "--help") | None => Err(anyhow!("{}", USAGE)),
+        Some(_) => run_transfer(args, account).await,
+    }
+}
+
+/// 从参数中取出 `--name <value>`
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>> {
+    match args.iter().position(|a| a == name) {
+        Some(i) if i + 1 < args.len() => {
+            let value = args.remove(i + 1);
+            args.remove(i);
+            Ok(Some(value))
+        }
+        Some(_) => Err(anyhow!("{} 需要一个参数\n{}", name, USAGE)),
+        None => Ok(None),
+    }
+}
+
+/// 加载配置并初始化应用状态
+fn load_state(config_path: &str) -> Result<AppState> {
+    // 加载配置（支持环境变量）
+    let config = Config::load(config_path)?;
+    if std::path::Path::new(config_path).exists() {
+        tracing::info!("✅ 配置加载完成: {}", config_path);
+    } else {
+        tracing::info!("✅ 配置从环境变量加载");
+    }
+
+    if let Some(proxy) = config.baidu.proxy.display_url() {
+        tracing::info!("🌐 百度请求经由代理: {}", proxy);
+    }
+
+    // 初始化应用状态（仅 Cookie + HTTP client）
+    let state = AppState::new(config)?;
+    tracing::info!("✅ HTTP Client 初始化完成");
+    Ok(state)
+}
+
+/// 加载配置并选择账号
+///
+/// 启动时通过 whoami 检查所选账号的 Cookie，已失效则直接退出。
+async fn load_account(config_path: &str, account: Option<&str>) -> Result<Arc<Account>> {
+    let account = load_state(config_path)?.account(account)?;
+    check_account(&account).await?;
+    Ok(account)
+}
+
+/// 检查账号 Cookie，明确失效时返回错误，其他查询失败仅告警
+async fn check_account(account: &Account) -> Result<()> {
+    match baidupcs::whoami(account).await {
+        Ok(info) => tracing::info!(
+            "👤 当前账号: {} / {} (uk={}, {})",
+            account.name,
+            info.username,
+            info.uk,
+            info.vip_level
+        ),
+        Err(e)
+            if matches!(
+                e.downcast_ref::<baidupcs::BaiduError>(),
+                Some(baidupcs::BaiduError::CookieInvalid { .. })
+            ) =>
+        {
+            return Err(e)
+        }
+        Err(e) => tracing::warn!("⚠️ 无法验证 Cookie，继续执行: {}", e),
+    }
+
+    Ok(())
+}
+
+fn default_config_path() -> String {
+    std::env::var("CONFIG_PATH").unwrap_or_else(|_| "config.toml".to_string())
+}
+
+/// 转存分享链接
+async fn run_transfer(args: Vec<String>, account: Option<&str>) -> Result<()> {
+    // 用法：baidu-direct-link [--dry-run] <share_url> [pwd] [config_path]
+    let mut dry_run = false;
+    let args: Vec<String> = args
+        .into_iter()
+        .filter(|a| {
+            if a == "--dry-run" {
+                dry_run = true;
+                false
+            } else {
+                true
+            }
+        })
+        .collect();
     if args.len() < 2 {
-        return Err(anyhow!(
-            "用法: {} <share_url> [pwd] [config_path]",
-            args.first()
-                .map(|s| s.as_str())
-                .unwrap_or("baidu-direct-link")
-        ));
+        return Err(anyhow!("{}", USAGE));
     }
 
     let share_url = args[1].clone();
@@ -35,40 +154,361 @@ async fn main() -> Result<()> {
         .or_else(|| args.get(3).cloned())
         .unwrap_or_else(|| "config.toml".to_string());
 
-    // 加载配置（支持环境变量）
-    let config = Config::load(&config_path)?;
-    if std::path::Path::new(&config_path).exists() {
-        tracing::info!("✅ 配置加载完成: {}", config_path);
+    let state = load_state(&config_path)?;
+    let options = baidupcs::TransferOptions {
+        dry_run,
+        // 与 Web 任务相同的进度事件
+        progress: Some(baidupcs::Progress::new(|event| {
+            tracing::info!("📍 {}", event)
+        })),
+        ..Default::default()
+    };
+
+    // 获取分享信息 → 生成计划 → 容量检查 → 转存
+    // 指定账号（或只有一个账号）时先检查 Cookie，失效直接退出；否则由账号池自动切换
+    let outcome = if account.is_some() || state.accounts.len() == 1 {
+        let account = state.account(account)?;
+        check_account(&account).await?;
+        state
+            .pool
+            .run_transfer_with(&[account], &share_url, &pwd, options)
+            .await?
     } else {
-        tracing::info!("✅ 配置从环境变量加载");
+        state.pool.run_transfer(&share_url, &pwd, options).await?
+    };
+    state.save_cookies();
+    let account = state.account(Some(&outcome.account))?;
+
+    if outcome.dry_run {
+        print_plan(&outcome.plan);
+        return Ok(());
     }
 
-    // 初始化应用状态（仅 Cookie + HTTP client）
-    let state = Arc::new(AppState::new(config)?);
-    tracing::info!("✅ HTTP Client 初始化完成");
+    tracing::info!(
+        "✅ 转存请求已完成，{} 个文件，账号: {}，保存路径: {}",
+        outcome.plan.fs_ids.len(),
+        account.name,
+        outcome.plan.save_path
+    );
+    if let Ok(quota) = baidupcs::get_quota(&account).await {
+        tracing::info!(
+            "💾 网盘剩余空间: {}",
+            baidupcs::quota::format_size(quota.free)
+        );
+    }
+    Ok(())
+}
 
-    // 提取 surl
-    let surl = baidupcs::extract_surl(&share_url)
-        .ok_or_else(|| anyhow!("无法从链接中提取 surl: {}", share_url))?;
-
-    // 1) 获取分享信息
-    let info = baidupcs::get_share_info(state.as_ref(), &share_url, &surl, &pwd).await?;
-    tracing::info!("📦 获取到 {} 个文件，开始转存...", info.fs_ids.len());
-
-    // 2) 转存
-    baidupcs::transfer_files(
-        state.as_ref(),
-        &info.shareid,
-        &info.uk,
-        &info.fs_ids,
-        &info.bdstoken,
-        &surl,
-    )
-    .await?;
+/// 输出 dry-run 计划
+fn print_plan(plan: &baidupcs::TransferPlan) {
+    println!("dry-run：以下文件将被转存（未执行任何写入）");
+    println!("  目标目录: {}", plan.save_path);
+    if !plan.save_path_exists {
+        println!("  ⚠️ 目标目录不存在，实际转存将失败");
+    }
+    println!("  重复处理: {}", plan.ondup);
+    println!(
+        "  预计大小: {}",
+        baidupcs::quota::format_size(plan.total_size)
+    );
+    for (fs_id, name) in plan.fs_ids.iter().zip(&plan.filenames) {
+        println!("  - {} {}", fs_id, name);
+    }
+}
 
-    tracing::info!(
-        "✅ 转存请求已完成，保存路径: {}",
-        state.config.baidu.save_path
+/// 显示当前账号信息
+async fn run_whoami(account: Option<&str>) -> Result<()> {
+    let account = load_account(&default_config_path(), account).await?;
+    let info = baidupcs::whoami(&account).await?;
+    account.save_cookies()?;
+
+    println!("账号: {}", account.name);
+    println!("用户名: {}", info.username);
+    println!("uk: {}", info.uk);
+    println!("会员: {}", info.vip_level);
+    if let Some(quota) = info.quota {
+        println!(
+            "容量: 已用 {} / 共 {}，剩余 {}",
+            baidupcs::quota::format_size(quota.used),
+            baidupcs::quota::format_size(quota.total),
+            baidupcs::quota::format_size(quota.free)
+        );
+    }
+    Ok(())
+}
+
+/// 扫码登录并将 Cookie 写入配置文件
+///
+/// 指定 `--account` 时写入同名账号（不存在则新增），否则写入 `[baidu]`。
+async fn run_login(account: Option<&str>) -> Result<()> {
+    let config_path = default_config_path();
+    // 此时可能还没有任何 Cookie，只读取配置，不初始化账号
+    let config = Config::load(&config_path)?;
+    let login = baidupcs::QrLogin::new(&config.baidu)?;
+
+    let qr = login.start().await?;
+    println!("{}", qr.to_terminal()?);
+    println!("请使用百度网盘 App 扫描二维码并确认登录");
+
+    let mut scanned = false;
+    let token = loop {
+        match login.poll(&qr.sign).await? {
+            baidupcs::QrStatus::Waiting => {}
+            baidupcs::QrStatus::Scanned => {
+                if !scanned {
+                    println!("📱 已扫码，请在手机上确认");
+                    scanned = true;
+                }
+            }
+            baidupcs::QrStatus::Confirmed { token } => break token,
+            baidupcs::QrStatus::Expired => return Err(anyhow!("二维码已过期或已取消，请重试")),
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    };
+
+    let credentials = login.finish(&token).await?;
+    let saved = Config::save_credentials(
+        std::path::Path::new(&config_path),
+        account,
+        &credentials.bduss,
+        &credentials.stoken,
+    )?;
+    println!(
+        "✅ 登录成功，Cookie 已写入 {}（账号: {}）",
+        saved.display(),
+        account.unwrap_or(&config.baidu.name)
     );
+    if std::env::var("BDUSS").is_ok_and(|v| !v.is_empty()) && account.is_none() {
+        println!("⚠️ 环境变量 BDUSS/STOKEN 优先于配置文件，请移除后再使用新的 Cookie");
+    }
     Ok(())
 }
+
+/// 导入 Cookie：BDUSS/STOKEN 写入配置文件，其余 Cookie 加入账号的持久化 Cookie jar
+fn run_import_cookies(args: &[String], account: Option<&str>) -> Result<()> {
+    let file = args.first().ok_or_else(|| anyhow!("{}", USAGE))?;
+    let imported = baidu_direct_link::cookie_import::import_file(std::path::Path::new(file))?;
+
+    println!("格式: {}", imported.format);
+    println!(
+        "找到 {} 个 Cookie: {}",
+        imported.cookies.len(),
+        imported.names().join(", ")
+    );
+    let mark = |v: Option<&str>| if v.is_some() { "✅" } else { "❌ 未找到" };
+    println!("  BDUSS  {}", mark(imported.bduss()));
+    println!("  STOKEN {}", mark(imported.stoken()));
+
+    let (Some(bduss), Some(stoken)) = (imported.bduss(), imported.stoken()) else {
+        return Err(anyhow!(
+            "导入文件中缺少 BDUSS 或网盘 STOKEN，请在登录网盘后重新导出"
+        ));
+    };
+
+    let config_path = default_config_path();
+    let saved =
+        Config::save_credentials(std::path::Path::new(&config_path), account, bduss, stoken)?;
+    println!("✅ BDUSS/STOKEN 已写入 {}", saved.display());
+
+    let state = load_state(&config_path)?;
+    let account = state.account(account)?;
+    let count = account.import_cookies(&imported);
+    if state.config.storage.persist_cookies {
+        account.save_cookies()?;
+        println!(
+            "✅ 另有 {} 个 Cookie 已保存到账号 {} 的 Cookie jar",
+            count, account.name
+        );
+    } else {
+        println!("⚠️ 未启用 Cookie 持久化，其余 Cookie 不会保留；可在配置中设置 import_cookies 每次启动时导入");
+    }
+    Ok(())
+}
+
+/// 配置自检，退出码：0 通过，1 有检查未通过，2 配置无法加载
+async fn run_doctor(args: &[String]) -> Result<()> {
+    let strict = args.iter().any(|a| a == "--strict");
+    let report = baidu_direct_link::doctor::run(&default_config_path()).await;
+    print!("{}", report.render());
+    std::process::exit(report.exit_code(strict));
+}
+
+/// 生成 Web 密码哈希：交互输入两次（非终端时从标准输入读取一行）
+fn run_hash_password() -> Result<()> {
+    use std::io::IsTerminal;
+
+    let password = if std::io::stdin().is_terminal() {
+        let first = rpassword::prompt_password("Web 密码: ")?;
+        let second = rpassword::prompt_password("再次输入: ")?;
+        if first != second {
+            return Err(anyhow!("两次输入的密码不一致"));
+        }
+        first
+    } else {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        line.trim_end_matches(['\r', '\n']).to_string()
+    };
+    if password.is_empty() {
+        return Err(anyhow!("密码不能为空"));
+    }
+
+    println!("{}", baidu_direct_link::password::hash(&password)?);
+    eprintln!("将上面的哈希填入配置文件 [web] password_hash（或环境变量 WEB_PASSWORD_HASH），并删除明文 password");
+    Ok(())
+}
+
+/// 加密凭据文件子命令
+fn run_secrets(args: &[String]) -> Result<()> {
+    use baidu_direct_link::secrets::{self, Secrets};
+
+    match (args.first().map(|s| s.as_str()), args.get(1), args.get(2)) {
+        (Some("encrypt"), Some(input), Some(output)) => {
+            let plaintext = std::fs::read_to_string(input)
+                .map_err(|e| anyhow!("读取 {} 失败: {}", input, e))?;
+            let secrets = Secrets::parse(&plaintext)?;
+            let passphrase = match secrets::passphrase_from_env()? {
+                Some(p) => p,
+                None => {
+                    let first = rpassword::prompt_password("设置口令: ")?;
+                    let second = rpassword::prompt_password("再次输入口令: ")?;
+                    if first != second {
+                        return Err(anyhow!("两次输入的口令不一致"));
+                    }
+                    first
+                }
+            };
+            secrets.save(std::path::Path::new(output), &passphrase)?;
+            println!("✅ 已加密写入 {}", output);
+            println!("💡 在配置中设置 [secrets] file = \"{}\"（或环境变量 SECRETS_FILE），并删除明文文件", output);
+            Ok(())
+        }
+        (Some("decrypt"), Some(input), None) => {
+            let passphrase = match secrets::passphrase_from_env()? {
+                Some(p) => p,
+                None => rpassword::prompt_password("口令: ")?,
+            };
+            let data = std::fs::read_to_string(input)
+                .map_err(|e| anyhow!("读取 {} 失败: {}", input, e))?;
+            let plaintext = secrets::decrypt(&data, &passphrase)?;
+            print!("{}", String::from_utf8(plaintext)?);
+            Ok(())
+        }
+        _ => Err(anyhow!("{}", USAGE)),
+    }
+}
+
+/// 网盘文件管理子命令
+async fn run_fs_command(args: &[String], account: Option<&str>) -> Result<()> {
+    let account = load_account(&default_config_path(), account).await?;
+    let result = fs_command(&account, args).await;
+    account.save_cookies()?;
+    result
+}
+
+async fn fs_command(account: &Account, args: &[String]) -> Result<()> {
+    let command = args[0].as_str();
+    let rest = &args[1..];
+
+    match command {
+        "ls" => {
+            let recursive = rest.iter().any(|a| a == "-r");
+            let dir = rest
+                .iter()
+                .find(|a| !a.starts_with('-'))
+                .map(|s| s.as_str())
+                .unwrap_or("/");
+            let entries = if recursive {
+                fs::list_recursive(account, dir, fs::ListLimits::default())
+                    .await?
+                    .entries
+            } else {
+                fs::list_dir(account, dir).await?
+            };
+            for entry in entries {
+                if entry.isdir {
+                    println!("{:>12}  {}/", "<DIR>", entry.path);
+                } else {
+                    println!(
+                        "{:>12}  {}",
+                        baidupcs::quota::format_size(entry.size),
+                        entry.path
+                    );
+                }
+            }
+        }
+        "tree" => {
+            let dir = rest.first().map(|s| s.as_str()).unwrap_or("/");
+            let depth = rest.get(1).and_then(|s| s.parse::<usize>().ok());
+            println!("{}", dir);
+            let limits = fs::ListLimits {
+                max_depth: depth,
+                max_entries: None,
+            };
+            print_tree(&fs::tree(account, dir, limits).await?.entries, "");
+        }
+        "mkdir" => {
+            let path = rest.first().ok_or_else(|| anyhow!("{}", USAGE))?;
+            fs::mkdir_p(account, path).await?;
+            println!("已创建: {}", path);
+        }
+        "mv" | "cp" => {
+            if rest.len() < 2 {
+                return Err(anyhow!("{}", USAGE));
+            }
+            let (dest, srcs) = rest.split_last().expect("长度已检查");
+            let is_move = command == "mv";
+
+            match fs::stat(account, dest).await? {
+                Some(entry) if entry.isdir => {
+                    // 目标是已存在的目录：移入/复制到该目录下
+                    if is_move {
+                        fs::move_to(account, srcs, dest).await?;
+                    } else {
+                        fs::copy_to(account, srcs, dest).await?;
+                    }
+                }
+                Some(_) => return Err(anyhow!("目标已存在且不是目录: {}", dest)),
+                None if srcs.len() == 1 => {
+                    // 目标不存在：视为新路径（同目录下移动即重命名）
+                    let (dest_dir, newname) = fs::split_path(dest);
+                    let (src_dir, _) = fs::split_path(&srcs[0]);
+                    if is_move && src_dir == dest_dir {
+                        fs::rename(account, &srcs[0], &newname).await?;
+                    } else if is_move {
+                        fs::move_as(account, &srcs[0], &dest_dir, &newname).await?;
+                    } else {
+                        fs::copy_as(account, &srcs[0], &dest_dir, &newname).await?;
+                    }
+                }
+                None => return Err(anyhow!("目标目录不存在: {}", dest)),
+            }
+            println!("完成: {} -> {}", srcs.join(", "), dest);
+        }
+        "rm" => {
+            if rest.is_empty() {
+                return Err(anyhow!("{}", USAGE));
+            }
+            fs::delete(account, rest).await?;
+            println!("已删除: {}", rest.join(", "));
+        }
+        _ => unreachable!("未知子命令: {}", command),
+    }
+
+    Ok(())
+}
+
+/// 以树形输出目录
+fn print_tree(nodes: &[fs::TreeNode], prefix: &str) {
+    for (i, node) in nodes.iter().enumerate() {
+        let last = i + 1 == nodes.len();
+        let name = if node.entry.isdir {
+            format!("{}/", node.entry.server_filename)
+        } else {
+            node.entry.server_filename.clone()
+        };
+        println!("{}{}{}", prefix, if last { "└── " } else { "├── " }, name);
+        let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+        print_tree(&node.children, &child_prefix);
+    }
+}
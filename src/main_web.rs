@@ -4,7 +4,7 @@ use anyhow::Result;
 use std::sync::Arc;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use baidu_direct_link::{config::Config, web, AppState};
+use baidu_direct_link::{baidupcs, config::Config, reload, server, web, AppState, SharedState};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -35,23 +35,156 @@ async fn main() -> Result<()> {
     let state = Arc::new(AppState::new(config)?);
     tracing::info!("✅ HTTP Client 初始化完成");
 
-    // 创建路由
-    let app = web::create_router(state);
+    // 检查各账号 Cookie 是否有效（失效时仅告警，Web 界面仍可访问）
+    for account in &state.accounts {
+        match baidupcs::whoami(account).await {
+            Ok(info) => tracing::info!(
+                "👤 账号 {}: {} (uk={}, {})",
+                account.name,
+                info.username,
+                info.uk,
+                info.vip_level
+            ),
+            Err(e) => {
+                tracing::warn!(
+                    "⚠️ 账号 {} 的 Cookie 检查未通过，使用该账号的转存将会失败: {}",
+                    account.name,
+                    e
+                );
+                // Cookie 明确失效时先从账号池中摘除，冷却后再重试
+                if baidu_direct_link::pool::is_account_error(&e) {
+                    state.pool.mark_unhealthy(&account.name, &e.to_string());
+                }
+            }
+        }
+    }
+    let web_config = &state.config.web;
+    if !state.login_required() {
+        tracing::warn!("⚠️ 未设置 Web 密码，任何能访问端口的人都可以使用 Web 界面");
+    } else if web_config.password_required() && web_config.password_hash.is_empty() {
+        tracing::warn!(
+            "⚠️ Web 密码以明文保存在配置中，建议运行 `baidu-direct-link hash-password` 改用 password_hash"
+        );
+    }
+    if let Some(proxy) = state.config.baidu.proxy.display_url() {
+        tracing::info!("🌐 百度请求经由代理: {}", proxy);
+    }
+    if state.accounts.len() > 1 {
+        tracing::info!(
+            "🔀 账号池: {} 个账号，策略 {:?}",
+            state.accounts.len(),
+            state.pool.strategy()
+        );
+    }
 
-    // 获取端口（默认 5200）
-    let port = std::env::var("PORT")
-        .ok()
-        .and_then(|s| s.parse::<u16>().ok())
-        .unwrap_or(5200);
+    // 后续通过 SIGHUP 或配置文件变化热重载
+    let shared = SharedState::new(state.clone());
+    #[cfg(unix)]
+    tokio::spawn(reload::watch_sighup(shared.clone()));
+    if state.config.web.reload_interval_secs > 0 {
+        tracing::info!(
+            "🔄 配置热重载: 每 {} 秒检查配置文件变化，或发送 SIGHUP",
+            state.config.web.reload_interval_secs
+        );
+        tokio::spawn(reload::watch_files(
+            shared.clone(),
+            std::time::Duration::from_secs(state.config.web.reload_interval_secs),
+        ));
+    }
+
+    // 定期保存 Cookie（百度会在请求过程中下发/刷新 Cookie）与 API 令牌使用记录
+    if state.config.storage.persist_cookies {
+        tracing::info!(
+            "🍪 Cookie 持久化: {}",
+            state.config.storage.data_dir.join("cookies").display()
+        );
+    }
+    if !state.users.is_empty() {
+        tracing::info!("👥 Web 用户: {} 个", state.users.list().len());
+    }
+    if !state.config.web.tokens.is_empty() {
+        tracing::info!("🔑 已配置 {} 个 API 令牌", state.config.web.tokens.len());
+    }
+    {
+        let shared = shared.clone();
+        let interval =
+            std::time::Duration::from_secs(state.config.storage.cookie_save_interval_secs.max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let state = shared.load();
+                state.save_cookies();
+                state.save_token_usage();
+            }
+        });
+    }
+
+    // 创建路由
+    let app = web::create_reloadable_router(shared.clone());
 
-    let addr = format!("0.0.0.0:{}", port);
-    tracing::info!("🌐 Web 服务器启动在: http://{}", addr);
-    tracing::info!("📝 请在浏览器中访问: http://localhost:{}", port);
-    tracing::info!("💚 健康检查: http://localhost:{}/health", port);
+    // 监听（[web] bind/port，或 unix_socket；配置了 [web.tls] 时使用 HTTPS）
+    let listener = server::Listener::bind(web_config).await?;
+    tracing::info!("🌐 Web 服务器启动在: {}", listener.url());
+    if web_config.unix_socket.is_none() {
+        let scheme = if web_config.tls_enabled() {
+            "https"
+        } else {
+            "http"
+        };
+        tracing::info!(
+            "📝 请在浏览器中访问: {}://localhost:{}",
+            scheme,
+            web_config.port
+        );
+        tracing::info!(
+            "💚 健康检查: {}://localhost:{}/health",
+            scheme,
+            web_config.port
+        );
+    }
+    if let Some(certs) = listener.tls() {
+        let interval = (web_config.reload_interval_secs > 0)
+            .then(|| std::time::Duration::from_secs(web_config.reload_interval_secs));
+        tokio::spawn(server::watch_tls(certs, interval));
+    }
 
     // 启动服务器
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+    server::serve(listener, app, shutdown_signal()).await?;
+
+    // 退出前保存 Cookie 与 API 令牌使用记录
+    shared.load().save_cookies();
+    shared.load().save_token_usage();
+    tracing::info!("👋 Web 服务器已停止");
 
     Ok(())
 }
+
+/// 等待 Ctrl+C 或 SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!("⚠️ 无法监听 SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    tracing::info!("🛑 收到退出信号，正在停止...");
+}
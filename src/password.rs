@@ -0,0 +1,71 @@
+//! Web 登录密码：argon2 哈希与常量时间校验
+//!
+//! `[web] password_hash` 保存 `hash-password` 子命令生成的 PHC 格式哈希（`$argon2id$...`），
+//! 配置中不再出现明文密码。明文 `[web] password` 仍然可用，但启动时会给出警告。
+
+use anyhow::{anyhow, Result};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chacha20poly1305::aead::OsRng;
+use subtle::ConstantTimeEq;
+
+use crate::config::WebConfig;
+
+/// 生成 argon2id 哈希（随机盐，默认参数）
+pub fn hash(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|h| h.to_string())
+        .map_err(|e| anyhow!("生成密码哈希失败: {}", e))
+}
+
+/// 检查哈希格式（加载配置时调用，避免服务启动后才发现无法登录）
+pub fn validate_hash(hash: &str) -> Result<()> {
+    PasswordHash::new(hash)
+        .map(|_| ())
+        .map_err(|e| anyhow!("[web] password_hash 不是有效的密码哈希: {}", e))
+}
+
+/// 校验登录密码：配置了 `password_hash` 时只校验哈希，否则以常量时间比较明文密码
+///
+/// 未设置任何密码时总是返回 `false`。
+pub fn verify(web: &WebConfig, password: &str) -> bool {
+    if !web.password_hash.is_empty() {
+        return verify_hash(&web.password_hash, password);
+    }
+    !web.password.is_empty() && bool::from(web.password.as_bytes().ct_eq(password.as_bytes()))
+}
+
+/// 校验密码与 PHC 格式哈希是否匹配
+pub fn verify_hash(hash: &str, password: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_and_verify() {
+        let mut web = WebConfig::default();
+        assert!(!verify(&web, ""));
+
+        web.password = "plain".to_string();
+        assert!(verify(&web, "plain"));
+        assert!(!verify(&web, "plain2"));
+
+        web.password_hash = hash("hashed").unwrap();
+        assert!(web.password_hash.starts_with("$argon2id$"));
+        assert!(validate_hash(&web.password_hash).is_ok());
+        // 配置了哈希后明文密码不再生效
+        assert!(verify(&web, "hashed"));
+        assert!(!verify(&web, "plain"));
+
+        assert!(validate_hash("not-a-hash").is_err());
+    }
+}
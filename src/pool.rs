@@ -0,0 +1,392 @@
+//! 账号池
+//!
+//! 按策略（优先级 / 轮询 / 剩余空间最多）挑选账号；某个账号 Cookie 失效或空间不足时
+//! 标记为不可用，转存自动切换到下一个可用账号。不可用的账号在冷却时间过后重新参与选择。
+//! 同一账号上的转存按 `max_transfers_per_account` 排队执行，避免并发写入触发百度风控。
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::{info, warn};
+
+use crate::baidupcs::{
+    self, BaiduError, Progress, TransferEvent, TransferOptions, TransferOutcome,
+};
+use crate::config::{PoolConfig, PoolStrategy};
+use crate::state::Account;
+
+/// 账号健康状态（用于接口展示）
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountStatus {
+    pub name: String,
+    pub healthy: bool,
+    /// 被标记为不可用的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// 距离重新尝试的剩余秒数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_in_secs: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct Health {
+    unhealthy_until: Option<Instant>,
+    reason: Option<String>,
+}
+
+impl Health {
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.unhealthy_until.is_none_or(|until| now >= until)
+    }
+}
+
+/// 每个账号同时进行的转存数限制
+struct TransferLimits {
+    /// 0 表示不限制
+    per_account: usize,
+    semaphores: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl TransferLimits {
+    /// 等待账号空闲，返回的许可在转存结束后释放
+    async fn acquire(&self, name: &str) -> Option<OwnedSemaphorePermit> {
+        if self.per_account == 0 {
+            return None;
+        }
+        let semaphore = self
+            .semaphores
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.per_account)))
+            .clone();
+        if semaphore.available_permits() == 0 {
+            info!("⏳ 账号 {} 上有转存正在进行，排队等待", name);
+        }
+        semaphore.acquire_owned().await.ok()
+    }
+}
+
+pub struct AccountPool {
+    accounts: Vec<Arc<Account>>,
+    strategy: PoolStrategy,
+    cooldown: Duration,
+    health: Mutex<Vec<Health>>,
+    cursor: AtomicUsize,
+    limits: Arc<TransferLimits>,
+}
+
+/// 一次转存尝试中已写入网盘的批次
+#[derive(Debug, Clone, Copy, Default)]
+struct Committed {
+    chunks: usize,
+    total: usize,
+    files: usize,
+}
+
+/// 包装进度回调，记录已写入网盘的批次后再转发给原回调
+fn track_chunks(options: &TransferOptions) -> (TransferOptions, Arc<Mutex<Committed>>) {
+    let committed = Arc::new(Mutex::new(Committed::default()));
+    let inner = options.progress.clone();
+    let tracked = committed.clone();
+    let progress = Progress::new(move |event| {
+        if let TransferEvent::ChunkTransferred { total, files, .. } = &event {
+            let mut c = tracked.lock().unwrap();
+            c.chunks += 1;
+            c.total = *total;
+            c.files += files;
+        }
+        if let Some(inner) = &inner {
+            inner.emit(event);
+        }
+    });
+    let options = TransferOptions {
+        progress: Some(progress),
+        ..options.clone()
+    };
+    (options, committed)
+}
+
+/// 是否是账号本身的问题（换一个账号可能成功）
+pub fn is_account_error(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<BaiduError>(),
+        Some(BaiduError::CookieInvalid { .. } | BaiduError::QuotaExceeded { .. })
+    )
+}
+
+impl AccountPool {
+    pub fn new(accounts: Vec<Arc<Account>>, config: &PoolConfig) -> Self {
+        let health = accounts.iter().map(|_| Health::default()).collect();
+        Self {
+            accounts,
+            strategy: config.strategy,
+            cooldown: Duration::from_secs(config.unhealthy_cooldown_secs),
+            health: Mutex::new(health),
+            cursor: AtomicUsize::new(0),
+            limits: Arc::new(TransferLimits {
+                per_account: config.max_transfers_per_account,
+                semaphores: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// 热重载时沿用旧账号池的排队状态，使重载前后发起的转存仍按账号排队（限制数变化时除外）
+    pub fn keep_limits_from(&mut self, old: &AccountPool) {
+        if self.limits.per_account == old.limits.per_account {
+            self.limits = old.limits.clone();
+        }
+    }
+
+    pub fn strategy(&self) -> PoolStrategy {
+        self.strategy
+    }
+
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.accounts.iter().position(|a| a.name == name)
+    }
+
+    /// 标记账号不可用，冷却时间内不再被选中
+    pub fn mark_unhealthy(&self, name: &str, reason: &str) {
+        if let Some(i) = self.index_of(name) {
+            let mut health = self.health.lock().unwrap();
+            health[i] = Health {
+                unhealthy_until: Some(Instant::now() + self.cooldown),
+                reason: Some(reason.to_string()),
+            };
+        }
+    }
+
+    /// 标记账号可用
+    pub fn mark_healthy(&self, name: &str) {
+        if let Some(i) = self.index_of(name) {
+            self.health.lock().unwrap()[i] = Health::default();
+        }
+    }
+
+    /// 所有账号的健康状态
+    pub fn status(&self) -> Vec<AccountStatus> {
+        let now = Instant::now();
+        let health = self.health.lock().unwrap();
+        self.accounts
+            .iter()
+            .zip(health.iter())
+            .map(|(account, h)| {
+                let healthy = h.is_healthy(now);
+                AccountStatus {
+                    name: account.name.clone(),
+                    healthy,
+                    reason: if healthy { None } else { h.reason.clone() },
+                    retry_in_secs: h
+                        .unhealthy_until
+                        .filter(|_| !healthy)
+                        .map(|until| until.saturating_duration_since(now).as_secs()),
+                }
+            })
+            .collect()
+    }
+
+    /// 当前可用的账号，按策略排序
+    fn healthy_accounts(&self) -> Vec<Arc<Account>> {
+        let now = Instant::now();
+        let health = self.health.lock().unwrap();
+        self.accounts
+            .iter()
+            .zip(health.iter())
+            .filter(|(_, h)| h.is_healthy(now))
+            .map(|(a, _)| a.clone())
+            .collect()
+    }
+
+    /// 按策略排序的候选账号（不含不可用的账号）
+    pub async fn candidates(&self) -> Vec<Arc<Account>> {
+        let mut accounts = self.healthy_accounts();
+        if accounts.len() <= 1 {
+            return accounts;
+        }
+
+        match self.strategy {
+            PoolStrategy::Priority => {}
+            PoolStrategy::RoundRobin => {
+                let start = self.cursor.fetch_add(1, Ordering::Relaxed) % accounts.len();
+                accounts.rotate_left(start);
+            }
+            PoolStrategy::MostFree => {
+                // 容量查询失败的账号排在最后；Cookie 失效的直接标记为不可用
+                let mut ranked = Vec::with_capacity(accounts.len());
+                for account in accounts {
+                    let free = match baidupcs::get_quota(&account).await {
+                        Ok(quota) => Some(quota.free),
+                        Err(e) => {
+                            if is_account_error(&e) {
+                                self.mark_unhealthy(&account.name, &e.to_string());
+                                continue;
+                            }
+                            warn!("⚠️ 账号 {} 容量查询失败: {}", account.name, e);
+                            None
+                        }
+                    };
+                    ranked.push((free, account));
+                }
+                ranked.sort_by_key(|(free, _)| std::cmp::Reverse(*free));
+                accounts = ranked.into_iter().map(|(_, a)| a).collect();
+            }
+        }
+        accounts
+    }
+
+    /// 按策略选择账号执行转存，账号不可用时自动切换到下一个
+    pub async fn run_transfer(
+        &self,
+        share_url: &str,
+        pwd: &str,
+        options: TransferOptions,
+    ) -> Result<TransferOutcome> {
+        let candidates = self.candidates().await;
+        self.run_transfer_with(&candidates, share_url, pwd, options)
+            .await
+    }
+
+    /// 依次在给定账号上尝试转存
+    ///
+    /// 只有 Cookie 失效、空间不足这类账号相关的错误才会切换账号并标记不可用；
+    /// 提取码错误、链接失效等与账号无关的错误直接返回。已有批次写入网盘后才出现的账号错误
+    /// 也不再切换（换账号会从头转存整个计划），返回 [`BaiduError::PartialTransfer`]。
+    /// 实际转存时等待该账号上进行中的转存结束。
+    pub async fn run_transfer_with(
+        &self,
+        accounts: &[Arc<Account>],
+        share_url: &str,
+        pwd: &str,
+        options: TransferOptions,
+    ) -> Result<TransferOutcome> {
+        let mut last_err = None;
+
+        for account in accounts {
+            let _permit = if options.dry_run {
+                None
+            } else {
+                self.limits.acquire(&account.name).await
+            };
+            info!("👤 使用账号: {}", account.name);
+            let (attempt, committed) = track_chunks(&options);
+            match baidupcs::run_transfer(account, share_url, pwd, attempt).await {
+                Ok(outcome) => {
+                    self.mark_healthy(&account.name);
+                    return Ok(outcome);
+                }
+                Err(e) if is_account_error(&e) => {
+                    self.mark_unhealthy(&account.name, &e.to_string());
+                    let committed = *committed.lock().unwrap();
+                    if committed.chunks > 0 {
+                        warn!(
+                            "⚠️ 账号 {} 已转存 {}/{} 批后不可用，已写入的文件保留在该账号中: {}",
+                            account.name, committed.chunks, committed.total, e
+                        );
+                        return Err(BaiduError::PartialTransfer {
+                            account: account.name.clone(),
+                            chunks: committed.chunks,
+                            total: committed.total,
+                            files: committed.files,
+                            message: e.to_string(),
+                        }
+                        .into());
+                    }
+                    warn!("⚠️ 账号 {} 不可用，尝试下一个账号: {}", account.name, e);
+                    options.emit(TransferEvent::AccountFailed {
+                        account: account.name.clone(),
+                        error: e.to_string(),
+                    });
+                    last_err = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(match last_err {
+            // 只有一个账号时保留原始错误，便于调用方判断类型
+            Some(e) if accounts.len() == 1 => e,
+            Some(e) => anyhow!("所有账号均不可用，最后一个错误: {}", e),
+            None => anyhow!("没有可用的账号（均已被标记为不可用，稍后重试）"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::baidupcs::api::{BaiduApi, Endpoints};
+    use crate::config::{AccountConfig, BaiduConfig};
+
+    fn pool(names: &[&str], strategy: PoolStrategy) -> AccountPool {
+        let baidu = BaiduConfig::default();
+        let accounts = names
+            .iter()
+            .map(|name| {
+                let config = AccountConfig {
+                    name: name.to_string(),
+                    cookie_bduss: String::new(),
+                    cookie_stoken: String::new(),
+                    import_cookies: None,
+                    save_path: None,
+                    profile: None,
+                };
+                let api = BaiduApi::new(reqwest::Client::new(), Endpoints::default());
+                Arc::new(Account::with_api(&config, &baidu, api))
+            })
+            .collect();
+        AccountPool::new(
+            accounts,
+            &PoolConfig {
+                strategy,
+                unhealthy_cooldown_secs: 600,
+                max_transfers_per_account: 1,
+            },
+        )
+    }
+
+    fn names(accounts: &[Arc<Account>]) -> Vec<&str> {
+        accounts.iter().map(|a| a.name.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_priority_skips_unhealthy() {
+        let pool = pool(&["a", "b", "c"], PoolStrategy::Priority);
+        pool.mark_unhealthy("a", "Cookie 已失效");
+        assert_eq!(names(&pool.candidates().await), vec!["b", "c"]);
+
+        let status = pool.status();
+        assert!(!status[0].healthy);
+        assert_eq!(status[0].reason.as_deref(), Some("Cookie 已失效"));
+
+        pool.mark_healthy("a");
+        assert_eq!(names(&pool.candidates().await), vec!["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_rotates() {
+        let pool = pool(&["a", "b", "c"], PoolStrategy::RoundRobin);
+        assert_eq!(names(&pool.candidates().await), vec!["a", "b", "c"]);
+        assert_eq!(names(&pool.candidates().await), vec!["b", "c", "a"]);
+        assert_eq!(names(&pool.candidates().await), vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn test_is_account_error() {
+        assert!(is_account_error(
+            &BaiduError::CookieInvalid { errno: -6 }.into()
+        ));
+        assert!(is_account_error(
+            &BaiduError::QuotaExceeded {
+                required: 2,
+                free: 1
+            }
+            .into()
+        ));
+        assert!(!is_account_error(&anyhow!("提取码错误")));
+    }
+}
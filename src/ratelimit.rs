@@ -0,0 +1,358 @@
+//! 限流：按客户端 IP 和登录身份的请求预算，以及登录失败后的指数退避锁定
+//!
+//! [`limit`] 中间件挂在整个 Router 上，解析客户端 IP（只采信可信代理的 `X-Forwarded-For`）
+//! 并按 IP 检查请求预算；[`crate::auth::require`] 认证通过后再按用户、管理员或 API 令牌检查一次。
+//! 预算来自当前配置（`[web.rate_limit]`），热重载后立即生效，计数在重载时沿用。
+//! 超出预算或登录被锁定时返回 `429`，带 `Retry-After` 头。
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use ipnet::IpNet;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::config::{RateLimitConfig, RouteBudget};
+use crate::SharedState;
+
+/// 计数表超过该数量时清理已回满的令牌桶和过期的失败记录
+const PRUNE_THRESHOLD: usize = 4096;
+
+/// 解析出的客户端 IP，由 [`limit`] 放入请求扩展
+///
+/// 经 Unix socket 连接且代理未传 `X-Forwarded-For` 时为 `None`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+/// 超出请求预算或登录被锁定
+#[derive(Debug)]
+pub struct RateLimited {
+    pub retry_after: Duration,
+    pub message: String,
+}
+
+impl RateLimited {
+    fn new(retry_after: Duration, message: impl Into<String>) -> Self {
+        Self {
+            retry_after,
+            message: message.into(),
+        }
+    }
+
+    /// 连续登录失败被锁定
+    pub fn login_locked(retry_after: Duration) -> Self {
+        Self::new(
+            retry_after,
+            format!("登录失败次数过多，请 {} 秒后重试", retry_secs(retry_after)),
+        )
+    }
+
+    /// 向上取整的等待秒数（至少 1 秒）
+    pub fn retry_after_secs(&self) -> u64 {
+        retry_secs(self.retry_after)
+    }
+}
+
+impl IntoResponse for RateLimited {
+    fn into_response(self) -> Response {
+        let secs = self.retry_after_secs();
+        let mut resp = (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(serde_json::json!({ "success": false, "message": self.message })),
+        )
+            .into_response();
+        resp.headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        resp
+    }
+}
+
+fn retry_secs(wait: Duration) -> u64 {
+    wait.as_secs() + u64::from(wait.subsec_nanos() > 0)
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// 令牌桶回满的时间，之后可以清理
+    full_at: Instant,
+}
+
+struct LoginFailures {
+    count: u32,
+    last: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// 请求计数与登录失败记录（热重载时沿用）
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<(String, String), Bucket>>,
+    failures: Mutex<HashMap<String, LoginFailures>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 为 `key`（如 `ip:1.2.3.4`、`user:alice`）在 `path` 适用的预算中扣除一次请求
+    pub fn check(
+        &self,
+        config: &RateLimitConfig,
+        key: &str,
+        path: &str,
+    ) -> Result<(), RateLimited> {
+        if !config.enabled {
+            return Ok(());
+        }
+        let Some((prefix, budget)) = config.budget(path) else {
+            return Ok(());
+        };
+        self.take(key, prefix, budget, Instant::now())
+            .map_err(|wait| {
+                warn!("🚦 {} 超出 {} 的请求预算", key, prefix);
+                RateLimited::new(
+                    wait,
+                    format!("请求过于频繁，请 {} 秒后重试", retry_secs(wait)),
+                )
+            })
+    }
+
+    fn take(
+        &self,
+        key: &str,
+        prefix: &str,
+        budget: RouteBudget,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let capacity = f64::from(budget.requests);
+        let rate = capacity / budget.per_secs as f64;
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= PRUNE_THRESHOLD {
+            buckets.retain(|_, b| b.full_at > now);
+        }
+        let bucket = buckets
+            .entry((key.to_string(), prefix.to_string()))
+            .or_insert(Bucket {
+                tokens: capacity,
+                updated: now,
+                full_at: now,
+            });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.updated = now;
+        if bucket.tokens < 1.0 {
+            return Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate));
+        }
+        bucket.tokens -= 1.0;
+        bucket.full_at = now + Duration::from_secs_f64((capacity - bucket.tokens) / rate);
+        Ok(())
+    }
+
+    /// 登录是否被锁定，返回剩余时间（`keys` 中任一被锁定即视为锁定）
+    pub fn login_locked(&self, keys: &[String]) -> Option<Duration> {
+        let now = Instant::now();
+        let failures = self.failures.lock().unwrap();
+        keys.iter()
+            .filter_map(|key| failures.get(key)?.locked_until)
+            .filter_map(|until| until.checked_duration_since(now))
+            .filter(|wait| !wait.is_zero())
+            .max()
+    }
+
+    /// 记录一次登录失败；达到次数后锁定 `login_lockout_secs * 2^(超出次数)`，不超过上限
+    ///
+    /// 返回本次失败触发的锁定时长。距上次失败超过锁定上限时重新计数。
+    pub fn login_failed(&self, config: &RateLimitConfig, keys: &[String]) -> Option<Duration> {
+        self.login_failed_at(config, keys, Instant::now())
+    }
+
+    fn login_failed_at(
+        &self,
+        config: &RateLimitConfig,
+        keys: &[String],
+        now: Instant,
+    ) -> Option<Duration> {
+        if !config.enabled || config.login_max_failures == 0 {
+            return None;
+        }
+        let forget_after = Duration::from_secs(config.login_lockout_max_secs);
+        let mut failures = self.failures.lock().unwrap();
+        if failures.len() >= PRUNE_THRESHOLD {
+            failures.retain(|_, f| now.saturating_duration_since(f.last) < forget_after);
+        }
+        let mut lockout = None;
+        for key in keys {
+            let entry = failures.entry(key.clone()).or_insert(LoginFailures {
+                count: 0,
+                last: now,
+                locked_until: None,
+            });
+            if now.saturating_duration_since(entry.last) >= forget_after {
+                entry.count = 0;
+                entry.locked_until = None;
+            }
+            entry.count += 1;
+            entry.last = now;
+            if entry.count >= config.login_max_failures {
+                let exponent = (entry.count - config.login_max_failures).min(31);
+                let secs = config
+                    .login_lockout_secs
+                    .saturating_mul(1 << exponent)
+                    .min(config.login_lockout_max_secs);
+                let wait = Duration::from_secs(secs);
+                entry.locked_until = Some(now + wait);
+                lockout = lockout.max(Some(wait));
+            }
+        }
+        lockout
+    }
+
+    /// 登录成功后清除失败记录
+    pub fn login_succeeded(&self, keys: &[String]) {
+        let mut failures = self.failures.lock().unwrap();
+        for key in keys {
+            failures.remove(key);
+        }
+    }
+}
+
+/// 直连地址是否为可信代理（Unix socket 的 `None` 视为可信），只有可信代理传来的转发头才被采信
+pub fn peer_trusted(peer: Option<IpAddr>, trusted: &[IpNet]) -> bool {
+    peer.is_none_or(|ip| trusted.iter().any(|net| net.contains(&ip)))
+}
+
+/// 请求的直连地址，经 Unix socket 连接时为 `None`
+pub fn peer_ip(req: &Request) -> Option<IpAddr> {
+    req.extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip().to_canonical())
+}
+
+/// 解析客户端 IP
+///
+/// 直连地址（`peer`，Unix socket 为 `None` 并视为可信）是可信代理时，从右向左读取
+/// `X-Forwarded-For`，跳过可信代理，第一个不可信的地址即客户端；否则忽略该头，防止伪造。
+pub fn client_ip(peer: Option<IpAddr>, headers: &HeaderMap, trusted: &[IpNet]) -> Option<IpAddr> {
+    let is_trusted = |ip: &IpAddr| trusted.iter().any(|net| net.contains(ip));
+    if !peer_trusted(peer, trusted) {
+        return peer;
+    }
+    let forwarded: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .collect();
+    let mut client = peer;
+    for hop in forwarded.iter().rev() {
+        match hop.parse::<IpAddr>() {
+            Ok(ip) => {
+                let ip = ip.to_canonical();
+                client = Some(ip);
+                if !is_trusted(&ip) {
+                    break;
+                }
+            }
+            // 无法解析的地址之前的内容都不可信
+            Err(_) => break,
+        }
+    }
+    client
+}
+
+/// 中间件：解析客户端 IP 放入请求扩展，并按 IP 检查请求预算
+pub async fn limit(State(shared): State<SharedState>, mut req: Request, next: Next) -> Response {
+    let state = shared.load();
+    let rate_limit = &state.config.web.rate_limit;
+    let ip = client_ip(peer_ip(&req), req.headers(), &state.trusted_proxies);
+    req.extensions_mut().insert(ClientIp(ip));
+
+    if let Some(ip) = ip {
+        if let Err(limited) =
+            state
+                .limiter
+                .check(rate_limit, &format!("ip:{}", ip), req.uri().path())
+        {
+            return limited.into_response();
+        }
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_ip_trusted_proxies_only() {
+        let trusted: Vec<IpNet> = vec!["10.0.0.0/8".parse().unwrap()];
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("6.6.6.6, 1.2.3.4, 10.0.0.2"),
+        );
+
+        // 不可信的直连地址：忽略 X-Forwarded-For
+        let direct: IpAddr = "5.5.5.5".parse().unwrap();
+        assert_eq!(client_ip(Some(direct), &headers, &trusted), Some(direct));
+
+        // 经可信代理：跳过代理地址，伪造的最左侧地址不被采信
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        assert_eq!(
+            client_ip(Some(proxy), &headers, &trusted),
+            Some("1.2.3.4".parse().unwrap())
+        );
+
+        // Unix socket 没有代理头时无法得知客户端
+        assert_eq!(client_ip(None, &HeaderMap::new(), &trusted), None);
+    }
+
+    #[test]
+    fn test_bucket_and_login_lockout() {
+        let limiter = RateLimiter::new();
+        let budget = RouteBudget {
+            requests: 2,
+            per_secs: 10,
+        };
+        let now = Instant::now();
+        assert!(limiter.take("ip:a", "/api/", budget, now).is_ok());
+        assert!(limiter.take("ip:a", "/api/", budget, now).is_ok());
+        let wait = limiter.take("ip:a", "/api/", budget, now).unwrap_err();
+        assert_eq!(retry_secs(wait), 5);
+        assert!(limiter.take("ip:b", "/api/", budget, now).is_ok());
+        assert!(limiter
+            .take("ip:a", "/api/", budget, now + Duration::from_secs(5))
+            .is_ok());
+
+        let config = RateLimitConfig {
+            login_max_failures: 3,
+            login_lockout_secs: 10,
+            login_lockout_max_secs: 25,
+            ..Default::default()
+        };
+        let keys = vec!["ip:a".to_string()];
+        assert_eq!(limiter.login_failed_at(&config, &keys, now), None);
+        assert_eq!(limiter.login_failed_at(&config, &keys, now), None);
+        let lockouts: Vec<_> = (0..3)
+            .map(|_| {
+                limiter
+                    .login_failed_at(&config, &keys, now)
+                    .unwrap()
+                    .as_secs()
+            })
+            .collect();
+        assert_eq!(lockouts, vec![10, 20, 25]);
+
+        limiter.login_succeeded(&keys);
+        assert_eq!(limiter.login_locked(&keys), None);
+    }
+}
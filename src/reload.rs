@@ -0,0 +1,339 @@
+//! 配置热重载
+//!
+//! 收到 SIGHUP 或检测到配置文件（及加密凭据文件）变化时重新执行 [`Config::load`]，
+//! 重建账号和 HTTP client 后整体替换 [`SharedState`] 中的 [`AppState`]。
+//! 新配置无效时保留旧状态；进行中的请求继续使用取到的旧快照。
+
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::state::{AppState, SharedState};
+
+/// 一项配置变化
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigChange {
+    /// 形如 `baidu.save_path`、`accounts[1].cookie_bduss`
+    pub key: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl ConfigChange {
+    /// Cookie、密码等敏感项只显示是否变化
+    pub fn is_secret(&self) -> bool {
+        is_secret_key(&self.key)
+    }
+
+    /// 监听地址、端口、Unix socket、TLS 路径和任务并发数在启动时确定，修改后需重启服务
+    pub fn requires_restart(&self) -> bool {
+        [
+            "web.bind",
+            "web.port",
+            "web.unix_socket",
+            "web.tls",
+            "jobs.workers",
+        ]
+        .iter()
+        .any(|k| self.key == *k || self.key.starts_with(&format!("{}.", k)))
+    }
+}
+
+impl std::fmt::Display for ConfigChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let show = |v: &Option<String>| match v {
+            None => "(无)".to_string(),
+            Some(_) if self.is_secret() => "******".to_string(),
+            // 代理地址中可能带有认证信息
+            Some(v) if self.key.ends_with("proxy.url") => {
+                let url = crate::config::ProxyConfig {
+                    url: Some(v.trim_matches('"').to_string()),
+                    ..Default::default()
+                };
+                format!("{:?}", url.display_url().unwrap_or_default())
+            }
+            Some(v) => v.clone(),
+        };
+        write!(f, "{}: {} → {}", self.key, show(&self.old), show(&self.new))
+    }
+}
+
+fn is_secret_key(key: &str) -> bool {
+    // 请求配置的 `cookies`、`headers` 中的值（Cookie、Authorization 等）一律隐藏
+    let mut parents = key.split('.').rev().skip(1);
+    if parents.any(|s| s == "cookies" || s == "headers") {
+        return true;
+    }
+    let name = key.rsplit('.').next().unwrap_or(key).to_ascii_lowercase();
+    [
+        "bduss",
+        "stoken",
+        "password",
+        "token",
+        "secret",
+        "passphrase",
+    ]
+    .iter()
+    .any(|s| name.contains(s))
+}
+
+/// 比较两份配置，返回变化的键（按键名排序）
+pub fn diff(old: &Config, new: &Config) -> Vec<ConfigChange> {
+    let (old, new) = (flatten(old), flatten(new));
+    let keys: std::collections::BTreeSet<_> = old.keys().chain(new.keys()).collect();
+    keys.into_iter()
+        .filter(|k| old.get(*k) != new.get(*k))
+        .map(|k| ConfigChange {
+            key: k.clone(),
+            old: old.get(k).cloned(),
+            new: new.get(k).cloned(),
+        })
+        .collect()
+}
+
+/// 将配置展开为 `键路径 → 值`
+fn flatten(config: &Config) -> BTreeMap<String, String> {
+    fn walk(prefix: &str, value: &toml::Value, out: &mut BTreeMap<String, String>) {
+        match value {
+            toml::Value::Table(table) => {
+                for (k, v) in table {
+                    let key = if prefix.is_empty() {
+                        k.clone()
+                    } else {
+                        format!("{}.{}", prefix, k)
+                    };
+                    walk(&key, v, out);
+                }
+            }
+            toml::Value::Array(items) if items.iter().all(|v| v.is_table()) => {
+                for (i, v) in items.iter().enumerate() {
+                    walk(&format!("{}[{}]", prefix, i), v, out);
+                }
+            }
+            other => {
+                out.insert(prefix.to_string(), other.to_string());
+            }
+        }
+    }
+
+    let mut out = BTreeMap::new();
+    if let Ok(value) = toml::Value::try_from(config) {
+        walk("", &value, &mut out);
+    }
+    out
+}
+
+/// 重新加载配置并替换应用状态，返回变化的配置项
+///
+/// 配置没有变化时不重建状态；新配置无法加载或账号无法构建时返回错误，旧状态保持不变。
+/// 读写文件并可能派生加密凭据的密钥（argon2），异步代码中应在 `spawn_blocking` 中调用。
+pub fn reload(shared: &SharedState) -> Result<Vec<ConfigChange>> {
+    let old = shared.load();
+    let path = old
+        .config
+        .path
+        .clone()
+        .ok_or_else(|| anyhow!("当前配置不是从文件加载的，无法重载"))?;
+    let config = Config::load(&path.to_string_lossy())?;
+
+    let changes = diff(&old.config, &config);
+    if changes.is_empty() {
+        return Ok(changes);
+    }
+
+    // 先保存旧账号的 Cookie，新账号从 Cookie 文件恢复
+    old.save_cookies();
+    let mut state = AppState::new(config)?;
+    // 登录会话沿用；修改 Web 密码后全部失效
+    let (new_web, old_web) = (&state.config.web, &old.config.web);
+    if new_web.password == old_web.password && new_web.password_hash == old_web.password_hash {
+        state.sessions = old.sessions.clone();
+    } else if !old.sessions.list(&old.config.web).is_empty() {
+        info!("🔐 Web 密码已修改，已注销所有登录会话");
+    }
+    // 令牌使用记录、用户、限流计数与任务沿用（已删除的令牌保留历史记录，不影响认证）
+    state.tokens = old.tokens.clone();
+    state.users = old.users.clone();
+    state.limiter = old.limiter.clone();
+    state.jobs = old.jobs.clone();
+    state.pool.keep_limits_from(&old.pool);
+    shared.store(Arc::new(state));
+    Ok(changes)
+}
+
+/// 重载并记录结果（SIGHUP 与文件监视共用）
+///
+/// 重载在阻塞线程池中执行，不占用处理请求的运行时线程。
+pub async fn reload_and_log(shared: &SharedState, reason: &str) {
+    info!("🔄 {}，重新加载配置...", reason);
+    let result = {
+        let shared = shared.clone();
+        tokio::task::spawn_blocking(move || reload(&shared))
+            .await
+            .unwrap_or_else(|e| Err(anyhow!("重载任务异常退出: {}", e)))
+    };
+    match result {
+        Ok(changes) if changes.is_empty() => info!("✅ 配置没有变化"),
+        Ok(changes) => {
+            info!("✅ 配置已重载，{} 项变化:", changes.len());
+            for change in &changes {
+                info!("   {}", change);
+            }
+            if changes.iter().any(ConfigChange::requires_restart) {
+                warn!("⚠️ 监听地址或 TLS 配置的修改需要重启服务后生效");
+            }
+        }
+        Err(e) => warn!("❌ 新配置无效，继续使用旧配置: {:#}", e),
+    }
+}
+
+/// 被监视的文件：配置文件与加密凭据文件
+fn watched_files(config: &Config) -> Vec<PathBuf> {
+    config
+        .path
+        .iter()
+        .chain(config.secrets.file.iter())
+        .cloned()
+        .collect()
+}
+
+fn modified_times(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|f| std::fs::metadata(f).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+/// 定期检查配置文件的修改时间，变化时重载
+pub async fn watch_files(shared: SharedState, interval: Duration) {
+    let mut files = watched_files(&shared.load().config);
+    let mut last = modified_times(&files);
+    let mut ticker = tokio::time::interval(interval);
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let current = modified_times(&files);
+        if current == last {
+            continue;
+        }
+        reload_and_log(&shared, "检测到配置文件变化").await;
+        // 加密凭据文件路径可能随配置变化
+        files = watched_files(&shared.load().config);
+        last = modified_times(&files);
+    }
+}
+
+/// 收到 SIGHUP 时重载
+#[cfg(unix)]
+pub async fn watch_sighup(shared: SharedState) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(signal) => signal,
+        Err(e) => {
+            warn!("⚠️ 无法监听 SIGHUP: {}", e);
+            return;
+        }
+    };
+    while hangup.recv().await.is_some() {
+        reload_and_log(&shared, "收到 SIGHUP").await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_redacts_secrets() {
+        let old: Config = toml::from_str(
+            r#"
+            [baidu]
+            cookie_bduss = "old-bduss"
+            save_path = "/a"
+
+            [web]
+            password = "old"
+            "#,
+        )
+        .unwrap();
+        let mut new = old.clone();
+        new.baidu.cookie_bduss = "new-bduss".to_string();
+        new.baidu.save_path = "/b".to_string();
+
+        let changes = diff(&old, &new);
+        let keys: Vec<_> = changes.iter().map(|c| c.key.as_str()).collect();
+        assert_eq!(keys, vec!["baidu.cookie_bduss", "baidu.save_path"]);
+
+        let text: Vec<_> = changes.iter().map(|c| c.to_string()).collect();
+        assert_eq!(text[0], "baidu.cookie_bduss: ****** → ******");
+        assert_eq!(text[1], r#"baidu.save_path: "/a" → "/b""#);
+        assert!(diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_diff_redacts_profile_cookies_and_headers() {
+        let old: Config = toml::from_str(
+            r#"
+            [baidu.profiles.app]
+            user_agent = "old-ua"
+
+            [baidu.profiles.app.headers]
+            X-Auth = "old-header"
+
+            [baidu.profiles.app.cookies]
+            BAIDUID = "old-cookie"
+            "#,
+        )
+        .unwrap();
+        let mut new = old.clone();
+        let profile = new.baidu.profiles.get_mut("app").unwrap();
+        profile.user_agent = Some("new-ua".to_string());
+        profile
+            .headers
+            .insert("X-Auth".to_string(), "new-header".to_string());
+        profile
+            .cookies
+            .insert("BAIDUID".to_string(), "new-cookie".to_string());
+        profile
+            .cookies
+            .insert("a.b".to_string(), "dotted".to_string());
+
+        let text: Vec<_> = diff(&old, &new).iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            text,
+            vec![
+                "baidu.profiles.app.cookies.BAIDUID: ****** → ******",
+                "baidu.profiles.app.cookies.a.b: (无) → ******",
+                "baidu.profiles.app.headers.X-Auth: ****** → ******",
+                r#"baidu.profiles.app.user_agent: "old-ua" → "new-ua""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_accounts_by_index() {
+        let old = Config::default();
+        let mut new = old.clone();
+        new.accounts.push(crate::config::AccountConfig {
+            name: "team".to_string(),
+            cookie_bduss: "x".to_string(),
+            cookie_stoken: "y".to_string(),
+            import_cookies: None,
+            save_path: None,
+            profile: None,
+        });
+
+        let changes = diff(&old, &new);
+        assert!(changes
+            .iter()
+            .any(|c| c.key == "accounts[0].name" && c.new.as_deref() == Some("\"team\"")));
+        assert!(changes
+            .iter()
+            .any(|c| c.key == "accounts[0].cookie_stoken" && c.old.is_none()));
+    }
+}
@@ -0,0 +1,326 @@
+//! 加密凭据文件与 `*_FILE` 环境变量
+//!
+//! 加密文件第一行为 `BDL-SECRETS-V1`，第二行是 base64(salt | nonce | 密文)：
+//! 口令经 Argon2id 派生 256 位密钥，再用 ChaCha20-Poly1305 加密。明文为 TOML：
+//!
+//! ```toml
+//! cookie_bduss = "..."
+//! cookie_stoken = "..."
+//! web_password = "..."
+//!
+//! [accounts.family]
+//! cookie_bduss = "..."
+//! cookie_stoken = "..."
+//! ```
+
+use anyhow::{anyhow, Result};
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::config::{AccountConfig, Config};
+
+const HEADER: &str = "BDL-SECRETS-V1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// 加密文件中的凭据
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Secrets {
+    /// `[baidu]` 账号
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cookie_bduss: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cookie_stoken: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub web_password: Option<String>,
+    /// 其他账号，按名称
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub accounts: BTreeMap<String, AccountSecrets>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AccountSecrets {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cookie_bduss: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cookie_stoken: Option<String>,
+}
+
+impl Secrets {
+    /// 从明文 TOML 解析
+    pub fn parse(plaintext: &str) -> Result<Self> {
+        toml::from_str(plaintext).map_err(|e| anyhow!("凭据格式错误: {}", e))
+    }
+
+    /// 读取并解密
+    pub fn load(path: &Path, passphrase: &str) -> Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("读取加密凭据文件失败: {} ({})", path.display(), e))?;
+        let plaintext = decrypt(&data, passphrase)?;
+        Self::parse(&String::from_utf8(plaintext)?)
+    }
+
+    /// 加密并写入（权限 0600，见 [`write_private`]）
+    pub fn save(&self, path: &Path, passphrase: &str) -> Result<()> {
+        let data = encrypt(toml::to_string(self)?.as_bytes(), passphrase)?;
+        write_private(path, data.as_bytes())
+    }
+
+    /// 覆盖配置中对应的值；只在加密文件中出现的账号会被追加到 `[[accounts]]`
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(bduss) = &self.cookie_bduss {
+            config.baidu.cookie_bduss = bduss.clone();
+        }
+        if let Some(stoken) = &self.cookie_stoken {
+            config.baidu.cookie_stoken = stoken.clone();
+        }
+        if let Some(password) = &self.web_password {
+            config.web.password = password.clone();
+        }
+
+        for (name, secrets) in &self.accounts {
+            if *name == config.baidu.name {
+                if let Some(bduss) = &secrets.cookie_bduss {
+                    config.baidu.cookie_bduss = bduss.clone();
+                }
+                if let Some(stoken) = &secrets.cookie_stoken {
+                    config.baidu.cookie_stoken = stoken.clone();
+                }
+                continue;
+            }
+
+            let index = match config.accounts.iter().position(|a| a.name == *name) {
+                Some(index) => index,
+                None => {
+                    config.accounts.push(AccountConfig {
+                        name: name.clone(),
+                        cookie_bduss: String::new(),
+                        cookie_stoken: String::new(),
+                        import_cookies: None,
+                        save_path: None,
+                        profile: None,
+                    });
+                    config.accounts.len() - 1
+                }
+            };
+            let account = &mut config.accounts[index];
+            if let Some(bduss) = &secrets.cookie_bduss {
+                account.cookie_bduss = bduss.clone();
+            }
+            if let Some(stoken) = &secrets.cookie_stoken {
+                account.cookie_stoken = stoken.clone();
+            }
+        }
+    }
+
+    /// 设置账号凭据；`account` 为空时写入 `[baidu]` 账号
+    pub fn set_credentials(&mut self, account: Option<&str>, bduss: &str, stoken: &str) {
+        match account {
+            None => {
+                self.cookie_bduss = Some(bduss.to_string());
+                self.cookie_stoken = Some(stoken.to_string());
+            }
+            Some(name) => {
+                self.accounts.insert(
+                    name.to_string(),
+                    AccountSecrets {
+                        cookie_bduss: Some(bduss.to_string()),
+                        cookie_stoken: Some(stoken.to_string()),
+                    },
+                );
+            }
+        }
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("派生密钥失败: {}", e))?;
+    Ok(key)
+}
+
+/// 使用口令加密
+pub fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<String> {
+    if passphrase.is_empty() {
+        return Err(anyhow!("口令不能为空"));
+    }
+    let mut salt = [0u8; SALT_LEN];
+    chacha20poly1305::aead::rand_core::RngCore::fill_bytes(&mut OsRng, &mut salt);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| anyhow!("加密失败"))?;
+
+    let mut data = Vec::with_capacity(SALT_LEN + NONCE_LEN + ciphertext.len());
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&ciphertext);
+    Ok(format!("{}\n{}\n", HEADER, STANDARD.encode(data)))
+}
+
+/// 使用口令解密
+pub fn decrypt(data: &str, passphrase: &str) -> Result<Vec<u8>> {
+    let mut lines = data.lines();
+    if lines.next().map(str::trim) != Some(HEADER) {
+        return Err(anyhow!("不是加密凭据文件（缺少 {} 头）", HEADER));
+    }
+    let data = STANDARD
+        .decode(lines.collect::<String>().trim())
+        .map_err(|e| anyhow!("加密凭据文件已损坏: {}", e))?;
+    if data.len() < SALT_LEN + NONCE_LEN {
+        return Err(anyhow!("加密凭据文件已损坏: 长度不足"));
+    }
+
+    let (salt, rest) = data.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, salt)?);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("解密失败：口令错误或文件已被篡改"))
+}
+
+/// 读取环境变量，支持 `NAME_FILE` 指向的文件（Docker/Podman secrets）
+///
+/// 两者同时设置时报错；文件内容末尾的换行会被去掉。
+pub fn env_or_file(name: &str) -> Result<Option<String>> {
+    let file_var = format!("{}_FILE", name);
+    let value = std::env::var(name).ok();
+    let file = std::env::var(&file_var).ok().filter(|f| !f.is_empty());
+
+    match (value, file) {
+        (Some(v), Some(_)) if !v.is_empty() => {
+            Err(anyhow!("{} 和 {} 不能同时设置", name, file_var))
+        }
+        (_, Some(path)) => {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| anyhow!("读取 {} 指向的文件失败: {} ({})", file_var, path, e))?;
+            Ok(Some(content.trim_end_matches(['\r', '\n']).to_string()))
+        }
+        (value, None) => Ok(value),
+    }
+}
+
+/// 加密文件口令：`SECRETS_PASSPHRASE` 或 `SECRETS_PASSPHRASE_FILE`
+pub fn passphrase_from_env() -> Result<Option<String>> {
+    Ok(env_or_file("SECRETS_PASSPHRASE")?.filter(|p| !p.is_empty()))
+}
+
+/// 以 0600 权限原子地写入保存凭据的文件
+///
+/// 先以 0600 创建临时文件写入再改名，文件任何时候都不会被其他用户读到，写到一半退出也不会损坏原文件。
+pub fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    use std::io::Write;
+
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    let tmp = path.with_file_name(name);
+    // 残留的临时文件可能是其他权限创建的，删除后重新创建才能保证 0600
+    let _ = std::fs::remove_file(&tmp);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let data = encrypt(b"cookie_bduss = \"x\"", "correct horse").unwrap();
+        assert!(data.starts_with(HEADER));
+        assert!(!data.contains("cookie_bduss"));
+
+        assert_eq!(
+            decrypt(&data, "correct horse").unwrap(),
+            b"cookie_bduss = \"x\""
+        );
+        assert!(decrypt(&data, "wrong").is_err());
+    }
+
+    #[test]
+    fn test_save_private_and_atomic() {
+        let dir = std::env::temp_dir().join(format!("bdl-secrets-save-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("secrets.enc");
+        std::fs::write(&path, "old").unwrap();
+
+        let secrets = Secrets {
+            cookie_bduss: Some("x".to_string()),
+            ..Default::default()
+        };
+        secrets.save(&path, "pass").unwrap();
+        assert_eq!(
+            Secrets::load(&path, "pass")
+                .unwrap()
+                .cookie_bduss
+                .as_deref(),
+            Some("x")
+        );
+        assert!(!dir.join("secrets.enc.tmp").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_apply_overrides_config() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [baidu]
+            cookie_bduss = "plain"
+
+            [[accounts]]
+            name = "family"
+            cookie_bduss = "plain"
+            cookie_stoken = "plain"
+            save_path = "/家庭"
+            "#,
+        )
+        .unwrap();
+        let secrets = Secrets::parse(
+            r#"
+            cookie_bduss = "secret"
+            web_password = "pw"
+
+            [accounts.family]
+            cookie_stoken = "family-secret"
+
+            [accounts.work]
+            cookie_bduss = "work-secret"
+            "#,
+        )
+        .unwrap();
+        secrets.apply(&mut config);
+
+        assert_eq!(config.baidu.cookie_bduss, "secret");
+        assert_eq!(config.web.password, "pw");
+        assert_eq!(config.accounts[0].cookie_bduss, "plain");
+        assert_eq!(config.accounts[0].cookie_stoken, "family-secret");
+        assert_eq!(config.accounts[0].save_path.as_deref(), Some("/家庭"));
+        assert_eq!(config.accounts[1].name, "work");
+        assert_eq!(config.accounts[1].cookie_bduss, "work-secret");
+    }
+}
@@ -0,0 +1,315 @@
+//! Web 服务监听：TCP（IPv4/IPv6）、HTTPS（rustls）与 Unix domain socket
+//!
+//! `axum::serve` 只支持明文 TCP，这里用 hyper-util 自行接受连接，三种监听方式共用同一个 Router。
+//! TLS 证书在文件变化或收到 SIGHUP 时重新加载，已建立的连接不受影响。
+
+use anyhow::{anyhow, Context, Result};
+use arc_swap::ArcSwap;
+use axum::extract::ConnectInfo;
+use axum::Router;
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::server::graceful::GracefulShutdown;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tower::Service;
+use tracing::{debug, info, warn};
+
+use crate::config::{TlsConfig, WebConfig};
+
+/// 退出时等待进行中的请求完成的最长时间
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 可热更新的 TLS 证书
+pub struct TlsCerts {
+    config: TlsConfig,
+    current: ArcSwap<rustls::ServerConfig>,
+}
+
+impl TlsCerts {
+    /// 读取证书和私钥
+    pub fn load(config: &TlsConfig) -> Result<Self> {
+        Ok(Self {
+            config: config.clone(),
+            current: ArcSwap::from_pointee(server_config(config)?),
+        })
+    }
+
+    /// 重新读取证书；失败时继续使用旧证书
+    pub fn reload(&self) -> Result<()> {
+        self.current.store(Arc::new(server_config(&self.config)?));
+        Ok(())
+    }
+
+    fn acceptor(&self) -> tokio_rustls::TlsAcceptor {
+        tokio_rustls::TlsAcceptor::from(self.current.load_full())
+    }
+
+    fn files(&self) -> [&PathBuf; 2] {
+        [&self.config.cert, &self.config.key]
+    }
+}
+
+/// 由 PEM 文件构建 rustls 服务端配置（HTTP/2 与 HTTP/1.1 均可协商）
+pub fn server_config(tls: &TlsConfig) -> Result<rustls::ServerConfig> {
+    let certs = CertificateDer::pem_file_iter(&tls.cert)
+        .and_then(|iter| iter.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("读取证书 {} 失败", tls.cert.display()))?;
+    if certs.is_empty() {
+        return Err(anyhow!("证书文件 {} 中没有证书", tls.cert.display()));
+    }
+    let key = PrivateKeyDer::from_pem_file(&tls.key)
+        .with_context(|| format!("读取私钥 {} 失败", tls.key.display()))?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| anyhow!("证书与私钥不匹配: {}", e))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+/// 监听方式
+pub enum Listener {
+    Tcp(TcpListener),
+    Tls(TcpListener, Arc<TlsCerts>),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener, PathBuf),
+}
+
+impl Listener {
+    /// 按 `[web]` 配置监听：设置了 `unix_socket` 时监听 Unix socket（加载配置时已拒绝同时配置 TLS），否则监听 TCP（可选 TLS）
+    pub async fn bind(web: &WebConfig) -> Result<Self> {
+        if let Some(path) = &web.unix_socket {
+            return Self::bind_unix(path);
+        }
+        let addr = web.listen_addr();
+        let tcp = TcpListener::bind(&addr)
+            .await
+            .with_context(|| format!("无法监听 {}", addr))?;
+        match &web.tls {
+            Some(tls) => Ok(Self::Tls(tcp, Arc::new(TlsCerts::load(tls)?))),
+            None => Ok(Self::Tcp(tcp)),
+        }
+    }
+
+    #[cfg(unix)]
+    fn bind_unix(path: &std::path::Path) -> Result<Self> {
+        // 上次异常退出可能留下 socket 文件；仍有进程在监听时不删除
+        if path.exists() {
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                return Err(anyhow!("{} 已有其他进程在监听", path.display()));
+            }
+            std::fs::remove_file(path)
+                .with_context(|| format!("删除旧的 socket 文件 {} 失败", path.display()))?;
+        }
+        let listener = tokio::net::UnixListener::bind(path)
+            .with_context(|| format!("无法监听 {}", path.display()))?;
+        Ok(Self::Unix(listener, path.to_path_buf()))
+    }
+
+    #[cfg(not(unix))]
+    fn bind_unix(_path: &std::path::Path) -> Result<Self> {
+        Err(anyhow!("当前平台不支持 Unix domain socket"))
+    }
+
+    /// 访问地址，用于日志
+    pub fn url(&self) -> String {
+        match self {
+            Self::Tcp(tcp) => format!("http://{}", display_addr(tcp)),
+            Self::Tls(tcp, _) => format!("https://{}", display_addr(tcp)),
+            #[cfg(unix)]
+            Self::Unix(_, path) => format!("unix:{}", path.display()),
+        }
+    }
+
+    /// 使用 TLS 时返回证书，供热更新使用
+    pub fn tls(&self) -> Option<Arc<TlsCerts>> {
+        match self {
+            Self::Tls(_, certs) => Some(certs.clone()),
+            _ => None,
+        }
+    }
+}
+
+fn display_addr(tcp: &TcpListener) -> String {
+    tcp.local_addr()
+        .map(|a| a.to_string())
+        .unwrap_or_else(|_| "?".to_string())
+}
+
+/// 接受连接并处理请求，`shutdown` 完成后停止接受新连接，等待进行中的请求结束
+pub async fn serve(
+    listener: Listener,
+    app: Router,
+    shutdown: impl Future<Output = ()> + Send,
+) -> Result<()> {
+    let graceful = GracefulShutdown::new();
+    tokio::pin!(shutdown);
+
+    loop {
+        let accepted = tokio::select! {
+            accepted = accept(&listener) => accepted,
+            _ = &mut shutdown => break,
+        };
+        let conn = match accepted {
+            Ok(conn) => conn,
+            Err(e) => {
+                // 文件描述符耗尽等错误：稍后重试，不退出服务
+                warn!("⚠️ 接受连接失败: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+
+        let app = app.clone();
+        let watcher = graceful.watcher();
+        tokio::spawn(async move {
+            let result = match conn {
+                Accepted::Plain(stream, peer) => {
+                    serve_connection(stream, Some(peer), app, watcher).await
+                }
+                Accepted::Tls(stream, peer, acceptor) => match acceptor.accept(stream).await {
+                    Ok(stream) => serve_connection(stream, Some(peer), app, watcher).await,
+                    Err(e) => {
+                        debug!("TLS 握手失败: {}", e);
+                        return;
+                    }
+                },
+                #[cfg(unix)]
+                Accepted::Unix(stream) => serve_connection(stream, None, app, watcher).await,
+            };
+            if let Err(e) = result {
+                debug!("连接处理结束: {}", e);
+            }
+        });
+    }
+
+    #[cfg(unix)]
+    if let Listener::Unix(_, path) = &listener {
+        let _ = std::fs::remove_file(path);
+    }
+    drop(listener);
+    if tokio::time::timeout(SHUTDOWN_TIMEOUT, graceful.shutdown())
+        .await
+        .is_err()
+    {
+        warn!("⚠️ 等待进行中的请求超时，强制退出");
+    }
+    Ok(())
+}
+
+enum Accepted {
+    Plain(tokio::net::TcpStream, SocketAddr),
+    Tls(tokio::net::TcpStream, SocketAddr, tokio_rustls::TlsAcceptor),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixStream),
+}
+
+async fn accept(listener: &Listener) -> std::io::Result<Accepted> {
+    match listener {
+        Listener::Tcp(tcp) => {
+            let (stream, peer) = tcp.accept().await?;
+            Ok(Accepted::Plain(stream, peer))
+        }
+        Listener::Tls(tcp, certs) => {
+            let (stream, peer) = tcp.accept().await?;
+            Ok(Accepted::Tls(stream, peer, certs.acceptor()))
+        }
+        #[cfg(unix)]
+        Listener::Unix(unix, _) => Ok(Accepted::Unix(unix.accept().await?.0)),
+    }
+}
+
+/// 处理一个连接上的请求；TCP 连接的对端地址以 [`ConnectInfo`] 放入请求扩展（见 [`crate::ratelimit`]）
+async fn serve_connection<S>(
+    stream: S,
+    peer: Option<SocketAddr>,
+    app: Router,
+    watcher: hyper_util::server::graceful::Watcher,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = service_fn(move |mut req: hyper::Request<Incoming>| {
+        if let Some(peer) = peer {
+            req.extensions_mut().insert(ConnectInfo(peer));
+        }
+        app.clone().call(req)
+    });
+    let builder = auto::Builder::new(TokioExecutor::new());
+    let conn = builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+    watcher.watch(conn.into_owned()).await
+}
+
+fn modified_times(certs: &TlsCerts) -> Vec<Option<SystemTime>> {
+    certs
+        .files()
+        .iter()
+        .map(|f| std::fs::metadata(f).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+fn reload_and_log(certs: &TlsCerts, reason: &str) {
+    match certs.reload() {
+        Ok(()) => info!("🔐 {}，TLS 证书已重新加载", reason),
+        Err(e) => warn!("❌ TLS 证书重新加载失败，继续使用旧证书: {:#}", e),
+    }
+}
+
+/// 证书或私钥文件变化时（如 certbot 续期后）、或收到 SIGHUP 时重新加载证书
+///
+/// `interval` 为 `None` 时只响应 SIGHUP。
+pub async fn watch_tls(certs: Arc<TlsCerts>, interval: Option<Duration>) {
+    #[cfg(unix)]
+    let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()).ok();
+
+    let mut last = modified_times(&certs);
+    let mut ticker = interval.map(tokio::time::interval);
+    if let Some(ticker) = ticker.as_mut() {
+        ticker.tick().await;
+    }
+    loop {
+        let tick = async {
+            match ticker.as_mut() {
+                Some(ticker) => {
+                    ticker.tick().await;
+                }
+                None => std::future::pending::<()>().await,
+            }
+        };
+        #[cfg(unix)]
+        let sighup = async {
+            match hangup.as_mut() {
+                Some(signal) => {
+                    signal.recv().await;
+                }
+                None => std::future::pending::<()>().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let sighup = std::future::pending::<()>();
+
+        tokio::select! {
+            _ = tick => {
+                let current = modified_times(&certs);
+                if current != last {
+                    last = current;
+                    reload_and_log(&certs, "检测到证书文件变化");
+                }
+            }
+            _ = sighup => reload_and_log(&certs, "收到 SIGHUP"),
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Web 登录会话
+//!
+//! 登录成功后生成随机令牌写入 Cookie，服务端保存对应的会话。会话有绝对过期时间和空闲超时，
+//! 登出、管理员注销或修改 Web 密码（热重载）时失效。会话只保存在内存中，重启服务后需重新登录。
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::config::WebConfig;
+
+/// 一个登录会话
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    /// 公开标识，用于列出和注销（不是 Cookie 中的令牌）
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// 登录的用户（见 [`crate::users`]），为空表示使用 `[web]` 密码登录的管理员
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// 登录时的 User-Agent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// CSRF 令牌（见 [`crate::csrf`]），不在会话列表中返回
+    #[serde(skip)]
+    pub csrf_token: String,
+}
+
+impl Session {
+    fn is_valid(&self, web: &WebConfig, now: DateTime<Utc>) -> bool {
+        let idle_ok = web.session_idle_secs == 0
+            || now - self.last_seen < Duration::seconds(web.session_idle_secs as i64);
+        now < self.expires_at && idle_ok
+    }
+}
+
+/// 会话存储（令牌 → 会话），过期策略取自 `[web]` 配置
+#[derive(Debug, Default)]
+pub struct SessionStore {
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl SessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 创建会话（`[web]` 密码登录），返回写入 Cookie 的令牌
+    pub fn create(&self, web: &WebConfig, user_agent: Option<String>) -> (String, Session) {
+        self.create_for(web, None, user_agent)
+    }
+
+    /// 为 `user` 创建会话，返回写入 Cookie 的令牌
+    pub fn create_for(
+        &self,
+        web: &WebConfig,
+        user: Option<String>,
+        user_agent: Option<String>,
+    ) -> (String, Session) {
+        let now = Utc::now();
+        let session = Session {
+            id: random_string(9),
+            created_at: now,
+            last_seen: now,
+            expires_at: now + Duration::seconds(web.session_ttl_secs as i64),
+            user,
+            user_agent,
+            csrf_token: random_string(32),
+        };
+        let token = random_string(32);
+
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, s| s.is_valid(web, now));
+        sessions.insert(token.clone(), session.clone());
+        (token, session)
+    }
+
+    /// 校验令牌并刷新最后活动时间；过期或不存在时返回 `None`
+    pub fn touch(&self, token: &str, web: &WebConfig) -> Option<Session> {
+        let now = Utc::now();
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions.get_mut(token)?;
+        if !session.is_valid(web, now) {
+            sessions.remove(token);
+            return None;
+        }
+        session.last_seen = now;
+        Some(session.clone())
+    }
+
+    /// 校验令牌，不刷新最后活动时间
+    pub fn get(&self, token: &str, web: &WebConfig) -> Option<Session> {
+        let sessions = self.sessions.lock().unwrap();
+        let session = sessions.get(token)?;
+        session.is_valid(web, Utc::now()).then(|| session.clone())
+    }
+
+    /// 按令牌注销（登出）
+    pub fn revoke_token(&self, token: &str) -> bool {
+        self.sessions.lock().unwrap().remove(token).is_some()
+    }
+
+    /// 按公开标识注销，返回是否存在
+    pub fn revoke(&self, id: &str) -> bool {
+        let mut sessions = self.sessions.lock().unwrap();
+        let before = sessions.len();
+        sessions.retain(|_, s| s.id != id);
+        sessions.len() != before
+    }
+
+    /// 注销用户的所有会话（删除用户或修改其密码时），返回注销的数量
+    pub fn revoke_user(&self, user: &str) -> usize {
+        let mut sessions = self.sessions.lock().unwrap();
+        let before = sessions.len();
+        sessions.retain(|_, s| s.user.as_deref() != Some(user));
+        before - sessions.len()
+    }
+
+    /// 有效的会话，按登录时间排序
+    pub fn list(&self, web: &WebConfig) -> Vec<Session> {
+        let now = Utc::now();
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, s| s.is_valid(web, now));
+        let mut list: Vec<_> = sessions.values().cloned().collect();
+        list.sort_by_key(|s| s.created_at);
+        list
+    }
+}
+
+/// `bytes` 字节随机数的 URL 安全 base64
+pub(crate) fn random_string(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    OsRng.fill_bytes(&mut buf);
+    URL_SAFE_NO_PAD.encode(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_lifecycle() {
+        let web = WebConfig::default();
+        let store = SessionStore::new();
+        let (token, session) = store.create(&web, Some("curl/8".to_string()));
+        assert_eq!(token.len(), 43);
+        assert!(store.touch(&token, &web).is_some());
+        assert_eq!(
+            store.get(&token, &web).unwrap().csrf_token,
+            session.csrf_token
+        );
+        assert_ne!(session.csrf_token, token);
+        assert!(store.touch("baidupcs_auth_token", &web).is_none());
+        assert_eq!(store.list(&web).len(), 1);
+
+        assert!(store.revoke(&session.id));
+        assert!(store.touch(&token, &web).is_none());
+        assert!(!store.revoke(&session.id));
+    }
+
+    #[test]
+    fn test_session_expiry_and_idle_timeout() {
+        let web = WebConfig::default();
+        let store = SessionStore::new();
+        let (token, _) = store.create(&web, None);
+
+        // 空闲超时
+        store
+            .sessions
+            .lock()
+            .unwrap()
+            .get_mut(&token)
+            .unwrap()
+            .last_seen -= Duration::seconds(web.session_idle_secs as i64 + 1);
+        assert!(store.touch(&token, &web).is_none());
+
+        // 绝对过期：即使一直活跃
+        let (token, _) = store.create(&web, None);
+        store
+            .sessions
+            .lock()
+            .unwrap()
+            .get_mut(&token)
+            .unwrap()
+            .expires_at = Utc::now();
+        assert!(store.touch(&token, &web).is_none());
+        assert!(store.list(&web).is_empty());
+    }
+}
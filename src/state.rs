@@ -1,61 +1,402 @@
 //! 最小应用状态（仅用于百度网盘转存）
 
 use anyhow::{anyhow, Result};
-use reqwest::{cookie::Jar, Client, Url};
+use arc_swap::ArcSwap;
+use cookie_store::CookieStore;
+use reqwest::Url;
+use reqwest_cookie_store::CookieStoreMutex;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tracing::{debug, info, warn};
 
-use crate::config::Config;
+use crate::auth::TokenStore;
+use crate::baidupcs::api::{self, BaiduApi};
+use crate::config::{AccountConfig, BaiduConfig, Config};
+use crate::cookie_import::{self, ImportedCookies};
+use crate::jobs::JobQueue;
+use crate::pool::AccountPool;
+use crate::ratelimit::RateLimiter;
+use crate::secrets;
+use crate::session::SessionStore;
+use crate::users::UserStore;
 
-pub struct AppState {
-    pub config: Config,
-    pub client: Client,
+/// 一个百度账号：独立的 Cookie、保存路径和 HTTP client
+pub struct Account {
+    pub name: String,
+    pub save_path: String,
+    pub quota_warn_percent: u8,
+    pub api: BaiduApi,
+    /// 账号的 Cookie jar（通过 [`Account::with_api`] 注入自定义 client 时为 `None`）
+    cookies: Option<Arc<CookieStoreMutex>>,
+    /// Cookie 持久化文件
+    cookie_file: Option<PathBuf>,
 }
 
-impl AppState {
-    pub fn new(config: Config) -> Result<Self> {
-        if config.baidu.cookie_bduss.is_empty() || config.baidu.cookie_bduss.len() < 50 {
+impl Account {
+    /// 按账号配置构建（Cookie 校验 + 独立的 Cookie jar）
+    ///
+    /// `cookie_file` 不为空时从该文件恢复上次保存的 Cookie，并由 [`Account::save_cookies`] 写回。
+    pub fn new(
+        account: &AccountConfig,
+        baidu: &BaiduConfig,
+        cookie_file: Option<&Path>,
+    ) -> Result<Self> {
+        if account.cookie_bduss.is_empty() || account.cookie_bduss.len() < 50 {
             return Err(anyhow!(
-                "BDUSS 未配置或长度不足，请通过 config.toml 或环境变量 BDUSS 设置完整的 BDUSS"
+                "账号 {} 的 BDUSS 未配置或长度不足，请通过 config.toml 或环境变量 BDUSS 设置完整的 BDUSS",
+                account.name
             ));
         }
-        if config.baidu.cookie_stoken.is_empty() || config.baidu.cookie_stoken.len() < 30 {
+        if account.cookie_stoken.is_empty() || account.cookie_stoken.len() < 30 {
             return Err(anyhow!(
-                "STOKEN 未配置或长度不足，请通过 config.toml 或环境变量 STOKEN 设置完整的 STOKEN"
+                "账号 {} 的 STOKEN 未配置或长度不足，请通过 config.toml 或环境变量 STOKEN 设置完整的 STOKEN",
+                account.name
             ));
         }
 
-        let jar = Arc::new(Jar::default());
-        let domains = [
-            "https://baidu.com",
-            "https://pan.baidu.com",
-            "https://passport.baidu.com",
-        ];
-
-        for domain in domains {
-            let url = domain.parse::<Url>()?;
-            jar.add_cookie_str(
-                &format!(
-                    "BDUSS={}; Domain=.baidu.com; Path=/",
-                    config.baidu.cookie_bduss
-                ),
-                &url,
-            );
-            jar.add_cookie_str(
-                &format!(
-                    "STOKEN={}; Domain=.baidu.com; Path=/",
-                    config.baidu.cookie_stoken
-                ),
-                &url,
-            );
-        }
-
-        let client = Client::builder()
-            .cookie_provider(jar)
-            .timeout(std::time::Duration::from_secs(
-                config.baidu.http_timeout_secs,
-            ))
+        let profile = baidu
+            .request_profile(account.profile.as_deref())
+            .map_err(|e| anyhow!("账号 {}: {:#}", account.name, e))?;
+        let cookies: Vec<(&str, &str)> = [
+            ("BDUSS", account.cookie_bduss.as_str()),
+            ("STOKEN", account.cookie_stoken.as_str()),
+        ]
+        .into_iter()
+        .chain(
+            profile
+                .cookies
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str())),
+        )
+        .collect();
+
+        // 先加载上次保存的 Cookie，再用配置中的 BDUSS/STOKEN 及请求配置中的 Cookie 覆盖（配置优先）
+        let store = match cookie_file {
+            Some(path) if path.exists() => load_cookie_store(path).unwrap_or_else(|e| {
+                warn!("⚠️ 读取 Cookie 文件失败，忽略: {} ({})", path.display(), e);
+                CookieStore::default()
+            }),
+            _ => CookieStore::default(),
+        };
+        let jar = Arc::new(CookieStoreMutex::new(store));
+        {
+            let mut store = jar.lock().unwrap();
+            let domains = [
+                "https://baidu.com",
+                "https://pan.baidu.com",
+                "https://passport.baidu.com",
+            ];
+            for domain in domains {
+                let url = domain.parse::<Url>()?;
+                for (name, value) in &cookies {
+                    let _ = store.parse(
+                        &format!("{}={}; Domain=.baidu.com; Path=/", name, value),
+                        &url,
+                    );
+                }
+            }
+
+            // 自定义接口地址（非 baidu.com）时，Cookie 也要带给这些主机
+            let endpoints = &baidu.endpoints;
+            for base in [&endpoints.pan, &endpoints.passport, &endpoints.pcs] {
+                let url = base.parse::<Url>()?;
+                if url.host_str().is_some_and(|h| h.ends_with("baidu.com")) {
+                    continue;
+                }
+                for (name, value) in &cookies {
+                    let _ = store.parse(&format!("{}={}; Path=/", name, value), &url);
+                }
+            }
+        }
+
+        let client = api::client_builder(baidu)?
+            .cookie_provider(jar.clone())
             .build()?;
 
-        Ok(Self { config, client })
+        let api = BaiduApi::new(client, baidu.endpoints.clone())
+            .with_proxy(&baidu.proxy)
+            .with_profile(profile);
+        Ok(Self {
+            cookies: Some(jar),
+            cookie_file: cookie_file.map(Path::to_path_buf),
+            ..Self::with_api(account, baidu, api)
+        })
+    }
+
+    /// 使用调用方提供的接口客户端（自定义 HTTP client 或接口地址）
+    pub fn with_api(account: &AccountConfig, baidu: &BaiduConfig, api: BaiduApi) -> Self {
+        Self {
+            name: account.name.clone(),
+            save_path: account
+                .save_path
+                .clone()
+                .unwrap_or_else(|| baidu.save_path.clone()),
+            quota_warn_percent: baidu.quota_warn_percent,
+            api,
+            cookies: None,
+            cookie_file: None,
+        }
+    }
+
+    /// 按名称查找当前 jar 中未过期的 Cookie 值
+    pub fn cookie(&self, name: &str) -> Option<String> {
+        let store = self.cookies.as_ref()?.lock().unwrap();
+        let value = store
+            .iter_unexpired()
+            .find(|c| c.name() == name)
+            .map(|c| c.value().to_string());
+        value
+    }
+
+    /// 将导入的 Cookie 加入 jar，返回加入的数量
+    ///
+    /// BDUSS/STOKEN 由账号配置决定（构建账号前已从导入结果中补全），这里跳过。
+    pub fn import_cookies(&self, imported: &ImportedCookies) -> usize {
+        let Some(jar) = &self.cookies else {
+            return 0;
+        };
+        let now = chrono::Utc::now().timestamp();
+        let mut store = jar.lock().unwrap();
+        imported
+            .cookies
+            .iter()
+            .filter(|c| c.name != "BDUSS" && c.name != "STOKEN")
+            .filter_map(|c| c.to_set_cookie(now))
+            .filter(|(cookie, url)| {
+                url.parse::<Url>()
+                    .is_ok_and(|url| store.parse(cookie, &url).is_ok())
+            })
+            .count()
+    }
+
+    /// 将 Cookie jar 写入持久化文件（未启用持久化时什么也不做）
+    pub fn save_cookies(&self) -> Result<()> {
+        let (Some(jar), Some(path)) = (&self.cookies, &self.cookie_file) else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let mut buf = Vec::new();
+        {
+            let store = jar.lock().unwrap();
+            // 会话 Cookie（BDUSS/STOKEN 等没有过期时间）也需要保存
+            cookie_store::serde::json::save_incl_expired_and_nonpersistent(&store, &mut buf)
+                .map_err(|e| anyhow!("序列化 Cookie 失败: {}", e))?;
+        }
+
+        // 包含 BDUSS/STOKEN，以 0600 原子写入
+        secrets::write_private(path, &buf)?;
+        debug!("💾 已保存账号 {} 的 Cookie: {}", self.name, path.display());
+        Ok(())
+    }
+}
+
+/// 读取 Cookie 文件（已过期的 Cookie 会被丢弃）
+fn load_cookie_store(path: &Path) -> Result<CookieStore> {
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    cookie_store::serde::json::load(file).map_err(|e| anyhow!("{}", e))
+}
+
+pub struct AppState {
+    pub config: Config,
+    /// 所有账号，第一个为默认账号
+    pub accounts: Vec<Arc<Account>>,
+    /// 未指定账号时按策略选择并自动切换
+    pub pool: AccountPool,
+    /// Web 登录会话（热重载时沿用，修改 Web 密码时清空）
+    pub sessions: Arc<SessionStore>,
+    /// API 令牌使用记录（热重载时沿用）
+    pub tokens: Arc<TokenStore>,
+    /// Web 用户（热重载时沿用）
+    pub users: Arc<UserStore>,
+    /// 请求计数与登录失败记录（热重载时沿用）
+    pub limiter: Arc<RateLimiter>,
+    /// 后台转存任务（热重载时沿用）
+    pub jobs: Arc<JobQueue>,
+    /// 解析后的可信代理网段（`[web.rate_limit] trusted_proxies`），加载配置时解析一次
+    pub trusted_proxies: Vec<ipnet::IpNet>,
+}
+
+impl AppState {
+    pub fn new(config: Config) -> Result<Self> {
+        let accounts = config
+            .account_configs()?
+            .iter()
+            .map(|a| {
+                let cookie_file = config.storage.cookie_file(&a.name);
+                let Some(path) = &a.import_cookies else {
+                    return Account::new(a, &config.baidu, cookie_file.as_deref()).map(Arc::new);
+                };
+
+                // 配置中未填写的 BDUSS/STOKEN 从导入文件补全
+                let imported = cookie_import::import_file(path)?;
+                let mut a = a.clone();
+                if a.cookie_bduss.is_empty() {
+                    a.cookie_bduss = imported.bduss().unwrap_or_default().to_string();
+                }
+                if a.cookie_stoken.is_empty() {
+                    a.cookie_stoken = imported.stoken().unwrap_or_default().to_string();
+                }
+                let account = Account::new(&a, &config.baidu, cookie_file.as_deref())?;
+                let count = account.import_cookies(&imported);
+                info!(
+                    "🍪 账号 {} 从 {}（{}）导入 Cookie: {}，另有 {} 个加入 Cookie jar",
+                    a.name,
+                    path.display(),
+                    imported.format,
+                    imported.names().join(", "),
+                    count
+                );
+                Ok(Arc::new(account))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Self::with_accounts(config, accounts)
+    }
+
+    /// 使用调用方构建好的账号（不能为空，第一个为默认账号）
+    pub fn with_accounts(config: Config, accounts: Vec<Arc<Account>>) -> Result<Self> {
+        if accounts.is_empty() {
+            return Err(anyhow!("至少需要一个账号"));
+        }
+        let pool = AccountPool::new(accounts.clone(), &config.pool);
+        let tokens = Arc::new(TokenStore::load(config.storage.token_usage_file()));
+        let users = Arc::new(UserStore::load(config.storage.users_file())?);
+        let trusted_proxies = config.web.rate_limit.proxies()?;
+        Ok(Self {
+            accounts,
+            pool,
+            sessions: Arc::new(SessionStore::new()),
+            tokens,
+            users,
+            limiter: Arc::new(RateLimiter::new()),
+            jobs: Arc::new(JobQueue::new(&config.jobs)),
+            trusted_proxies,
+            config,
+        })
+    }
+
+    /// 是否需要登录：设置了 `[web]` 密码或创建了用户
+    pub fn login_required(&self) -> bool {
+        self.config.web.password_required() || !self.users.is_empty()
+    }
+
+    /// 默认账号
+    pub fn default_account(&self) -> Arc<Account> {
+        self.accounts
+            .first()
+            .cloned()
+            .expect("至少配置一个账号（AppState::new 已校验）")
+    }
+
+    /// 按名称选择账号，`None` 或空字符串时返回默认账号
+    pub fn account(&self, name: Option<&str>) -> Result<Arc<Account>> {
+        match name.filter(|n| !n.is_empty()) {
+            None => Ok(self.default_account()),
+            Some(name) => self
+                .accounts
+                .iter()
+                .find(|a| a.name == name)
+                .cloned()
+                .ok_or_else(|| anyhow!("未找到账号: {}", name)),
+        }
+    }
+
+    /// 所有账号名称
+    pub fn account_names(&self) -> Vec<String> {
+        self.accounts.iter().map(|a| a.name.clone()).collect()
+    }
+
+    /// 保存所有账号的 Cookie，失败只记录警告
+    pub fn save_cookies(&self) {
+        for account in &self.accounts {
+            if let Err(e) = account.save_cookies() {
+                warn!("⚠️ 保存账号 {} 的 Cookie 失败: {}", account.name, e);
+            }
+        }
+    }
+
+    /// 保存 API 令牌使用记录，失败只记录警告
+    pub fn save_token_usage(&self) {
+        if let Err(e) = self.tokens.save() {
+            warn!("⚠️ 保存 API 令牌使用记录失败: {}", e);
+        }
+    }
+}
+
+/// 可热重载的应用状态（见 [`crate::reload`]）
+///
+/// 每个请求开始时通过 [`SharedState::load`] 取一份快照；重载时整体替换，
+/// 进行中的请求继续使用旧快照直到结束。
+#[derive(Clone)]
+pub struct SharedState {
+    current: Arc<ArcSwap<AppState>>,
+}
+
+impl SharedState {
+    pub fn new(state: Arc<AppState>) -> Self {
+        Self {
+            current: Arc::new(ArcSwap::new(state)),
+        }
+    }
+
+    /// 当前状态的快照
+    pub fn load(&self) -> Arc<AppState> {
+        self.current.load_full()
+    }
+
+    /// 替换为新状态
+    pub fn store(&self, state: Arc<AppState>) {
+        self.current.store(state);
+    }
+}
+
+impl From<Arc<AppState>> for SharedState {
+    fn from(state: Arc<AppState>) -> Self {
+        Self::new(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> Config {
+        toml::from_str(&format!(
+            r#"
+            [baidu]
+            cookie_bduss = "{bduss}"
+            cookie_stoken = "{stoken}"
+            save_path = "/默认"
+
+            [[accounts]]
+            name = "team"
+            cookie_bduss = "{bduss}"
+            cookie_stoken = "{stoken}"
+            "#,
+            bduss = "b".repeat(60),
+            stoken = "s".repeat(32)
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_account_selection() {
+        let state = AppState::new(test_config()).unwrap();
+
+        assert_eq!(state.account_names(), vec!["default", "team"]);
+        assert_eq!(state.account(None).unwrap().name, "default");
+        assert_eq!(state.account(Some("")).unwrap().name, "default");
+        // 未设置 save_path 的账号沿用 [baidu].save_path
+        assert_eq!(state.account(Some("team")).unwrap().save_path, "/默认");
+        assert!(state.account(Some("nobody")).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_account_names_rejected() {
+        let mut config = test_config();
+        config.accounts[0].name = "default".to_string();
+        assert!(AppState::new(config).is_err());
     }
 }
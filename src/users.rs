@@ -0,0 +1,535 @@
+//! Web 用户：角色、允许的保存目录、绑定的百度账号与每日转存额度
+//!
+//! 用户保存在 `<data_dir>/users.json`（密码只保存 argon2 哈希），由管理员通过 `/api/users`
+//! 或 Web 界面管理。`[web]` 密码仍然可以登录，登录后拥有管理员权限。
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
+
+use crate::baidupcs::quota::format_size;
+use crate::baidupcs::{TransferPlan, TransferQuota};
+use crate::config::Scope;
+use crate::password;
+use crate::secrets;
+
+/// 用户不存在时用于校验的哈希（与 [`password::hash`] 参数相同），使耗时与用户存在时一致
+const DUMMY_HASH: &str =
+    "$argon2id$v=19$m=19456,t=2,p=1$wwVqnMhYfp4FRDN0lgxC1A$iaMRDNNc1476isVCISwysserVICskE4TGLAmm0CP3EY";
+
+/// 用户角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// 全部权限，包括用户管理
+    Admin,
+    /// 转存和文件管理（限于允许的保存目录）
+    User,
+    /// 只能预览（dry-run）和查询
+    ReadOnly,
+}
+
+impl Role {
+    /// 角色对应的接口权限
+    pub fn allows(self, scope: Scope) -> bool {
+        match self {
+            Role::Admin => true,
+            Role::User => scope != Scope::Admin,
+            Role::ReadOnly => scope == Scope::Preview,
+        }
+    }
+}
+
+/// 当日已转存的字节数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DailyUsage {
+    pub date: Option<NaiveDate>,
+    pub bytes: u64,
+}
+
+/// 一个 Web 用户
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct User {
+    pub name: String,
+    pub password_hash: String,
+    pub role: Role,
+    /// 允许转存和管理的目录（含子目录），为空表示不限制
+    #[serde(default)]
+    pub save_roots: Vec<String>,
+    /// 可使用的百度账号，为空表示全部账号
+    #[serde(default)]
+    pub accounts: Vec<String>,
+    /// 每日转存额度（字节），0 表示不限制
+    #[serde(default)]
+    pub daily_quota_bytes: u64,
+    #[serde(default)]
+    pub usage: DailyUsage,
+    /// 进行中的转存预留的字节数（不保存）
+    #[serde(skip)]
+    pub reserved_bytes: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl User {
+    /// `path` 是否位于允许的保存目录内
+    pub fn allows_path(&self, path: &str) -> bool {
+        self.save_roots.is_empty() || self.save_roots.iter().any(|root| within(path, root))
+    }
+
+    /// `path` 是否位于允许的保存目录之下（不含保存目录本身），删除、移动等操作使用
+    pub fn allows_path_below(&self, path: &str) -> bool {
+        self.save_roots.is_empty()
+            || self
+                .save_roots
+                .iter()
+                .any(|root| within(path, root) && !same_path(path, root))
+    }
+
+    /// 是否可以使用百度账号 `account`
+    pub fn allows_account(&self, account: &str) -> bool {
+        self.accounts.is_empty() || self.accounts.iter().any(|a| a == account)
+    }
+
+    /// 今日已转存的字节数
+    pub fn used_today(&self) -> u64 {
+        match self.usage.date {
+            Some(date) if date == today() => self.usage.bytes,
+            _ => 0,
+        }
+    }
+
+    /// 今日剩余额度（扣除进行中的转存预留的部分），不限制时为 `None`
+    pub fn remaining_quota(&self) -> Option<u64> {
+        (self.daily_quota_bytes > 0).then(|| {
+            self.daily_quota_bytes
+                .saturating_sub(self.used_today() + self.reserved_bytes)
+        })
+    }
+}
+
+/// `path` 是否为 `root` 或其子目录（拒绝包含 `.`、`..` 或空段的路径，见 [`is_plain_path`]）
+fn within(path: &str, root: &str) -> bool {
+    if !is_plain_path(path) {
+        return false;
+    }
+    let path = path.trim_end_matches('/');
+    let root = root.trim_end_matches('/');
+    root.is_empty() || path == root || path.starts_with(&format!("{}/", root))
+}
+
+/// 路径除开头和末尾的一个 `/` 外不含空段，也不含 `.` 或 `..`，
+/// 否则 `/root/.` 之类的写法会绕过与保存目录本身的比较
+fn is_plain_path(path: &str) -> bool {
+    let path = path.strip_suffix('/').unwrap_or(path);
+    path.split('/').enumerate().all(|(i, c)| match c {
+        "" => i == 0,
+        "." | ".." => false,
+        _ => true,
+    })
+}
+
+/// 忽略末尾 `/` 比较两个路径
+fn same_path(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/') == b.trim_end_matches('/')
+}
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// 创建或修改用户的请求，未提供的字段保持不变
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UserUpdate {
+    pub name: String,
+    /// 新用户必须提供；修改时提供则重设密码
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub role: Option<Role>,
+    #[serde(default)]
+    pub save_roots: Option<Vec<String>>,
+    #[serde(default)]
+    pub accounts: Option<Vec<String>>,
+    #[serde(default)]
+    pub daily_quota_bytes: Option<u64>,
+}
+
+/// 用户存储（用户名 → 用户），每次修改后立即写入文件
+#[derive(Debug, Default)]
+pub struct UserStore {
+    users: Mutex<BTreeMap<String, User>>,
+    file: Option<PathBuf>,
+}
+
+impl UserStore {
+    /// 从文件加载用户；文件不存在时为空，无法解析时返回错误（避免用户丢失后变为无需登录）
+    pub fn load(file: PathBuf) -> Result<Self> {
+        let users = match std::fs::read_to_string(&file) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| anyhow!("读取用户文件 {} 失败: {}", file.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(anyhow!("读取用户文件 {} 失败: {}", file.display(), e)),
+        };
+        Ok(Self {
+            users: Mutex::new(users),
+            file: Some(file),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.lock().unwrap().is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<User> {
+        self.users.lock().unwrap().get(name).cloned()
+    }
+
+    /// 所有用户，按用户名排序
+    pub fn list(&self) -> Vec<User> {
+        self.users.lock().unwrap().values().cloned().collect()
+    }
+
+    /// 校验用户名和密码（耗时数十毫秒，异步代码中应在 `spawn_blocking` 中调用）
+    ///
+    /// 用户不存在时也对固定的哈希做一次校验，使两种情况耗时相同，无法据此探测用户名。
+    pub fn authenticate(&self, name: &str, password: &str) -> Option<User> {
+        let Some(user) = self.get(name) else {
+            password::verify_hash(DUMMY_HASH, password);
+            return None;
+        };
+        password::verify_hash(&user.password_hash, password).then_some(user)
+    }
+
+    /// 创建或修改用户（设置密码时计算哈希，异步代码中应在 `spawn_blocking` 中调用）
+    ///
+    /// `accounts` 为当前配置的百度账号，`admin_fallback` 表示 `[web]` 密码可以登录管理员：
+    /// 没有时至少需要保留一个管理员用户，否则将无法再管理用户。
+    pub fn upsert(
+        &self,
+        update: UserUpdate,
+        accounts: &[String],
+        admin_fallback: bool,
+    ) -> Result<User> {
+        validate_name(&update.name)?;
+        // 哈希在加锁前计算，不阻塞其他用户的登录和额度记录
+        let new_hash = match &update.password {
+            Some(pw) if pw.chars().count() < 8 => return Err(anyhow!("密码至少需要 8 个字符")),
+            Some(pw) => Some(password::hash(pw)?),
+            None => None,
+        };
+        let mut users = self.users.lock().unwrap();
+        let existing = users.get(&update.name).cloned();
+
+        let password_hash = match (new_hash, &existing) {
+            (Some(hash), _) => hash,
+            (None, Some(user)) => user.password_hash.clone(),
+            (None, None) => return Err(anyhow!("新用户需要设置密码")),
+        };
+        let save_roots = update
+            .save_roots
+            .or_else(|| existing.as_ref().map(|u| u.save_roots.clone()))
+            .unwrap_or_default();
+        if let Some(root) = save_roots
+            .iter()
+            .find(|r| !r.starts_with('/') || r.split('/').any(|c| c == ".."))
+        {
+            return Err(anyhow!("保存目录必须是以 / 开头的绝对路径: {}", root));
+        }
+        let bound = update
+            .accounts
+            .or_else(|| existing.as_ref().map(|u| u.accounts.clone()))
+            .unwrap_or_default();
+        if let Some(name) = bound.iter().find(|a| !accounts.contains(a)) {
+            return Err(anyhow!("未找到账号: {}", name));
+        }
+
+        let user = User {
+            name: update.name.clone(),
+            password_hash,
+            role: update
+                .role
+                .or(existing.as_ref().map(|u| u.role))
+                .unwrap_or(Role::User),
+            save_roots,
+            accounts: bound,
+            daily_quota_bytes: update
+                .daily_quota_bytes
+                .or(existing.as_ref().map(|u| u.daily_quota_bytes))
+                .unwrap_or(0),
+            usage: existing
+                .as_ref()
+                .map(|u| u.usage.clone())
+                .unwrap_or_default(),
+            reserved_bytes: existing.as_ref().map_or(0, |u| u.reserved_bytes),
+            created_at: existing.map(|u| u.created_at).unwrap_or_else(Utc::now),
+        };
+
+        let mut updated = users.clone();
+        updated.insert(user.name.clone(), user.clone());
+        check_admin(&updated, admin_fallback)?;
+        *users = updated;
+        self.save(&users)?;
+        Ok(user)
+    }
+
+    /// 删除用户，返回是否存在
+    pub fn remove(&self, name: &str, admin_fallback: bool) -> Result<bool> {
+        let mut users = self.users.lock().unwrap();
+        if !users.contains_key(name) {
+            return Ok(false);
+        }
+        let mut updated = users.clone();
+        updated.remove(name);
+        check_admin(&updated, admin_fallback)?;
+        *users = updated;
+        self.save(&users)?;
+        Ok(true)
+    }
+
+    /// 为进行中的转存按计划预留额度，`previous` 为这次转存之前预留的字节数（换账号重试时被替换）
+    ///
+    /// 有额度限制时，今日已用、其他转存已预留与本次之和不能超过每日额度；
+    /// 分享中的目录过多、大小没有统计完时无法判断，拒绝转存。
+    pub fn reserve(&self, name: &str, previous: u64, plan: &TransferPlan) -> Result<()> {
+        let mut users = self.users.lock().unwrap();
+        let Some(user) = users.get_mut(name) else {
+            return Err(anyhow!("用户不存在: {}", name));
+        };
+        let others = user.reserved_bytes.saturating_sub(previous);
+        if user.daily_quota_bytes > 0 {
+            if !plan.size_known {
+                return Err(anyhow!(
+                    "分享中的文件夹过多，无法统计转存大小，有每日额度限制时不能转存"
+                ));
+            }
+            let remaining = user
+                .daily_quota_bytes
+                .saturating_sub(user.used_today() + others);
+            if plan.total_size > remaining {
+                return Err(anyhow!(
+                    "超出转存额度: 本次需要 {}，今日剩余 {}",
+                    format_size(plan.total_size),
+                    format_size(remaining)
+                ));
+            }
+        }
+        user.reserved_bytes = others + plan.total_size;
+        Ok(())
+    }
+
+    /// 结束一次转存：释放预留的 `reserved` 字节，并把实际转存的 `used` 字节计入今日已用额度
+    pub fn settle(&self, name: &str, reserved: u64, used: u64) -> Result<()> {
+        let mut users = self.users.lock().unwrap();
+        let Some(user) = users.get_mut(name) else {
+            return Ok(());
+        };
+        user.reserved_bytes = user.reserved_bytes.saturating_sub(reserved);
+        if used == 0 {
+            return Ok(());
+        }
+        user.usage = DailyUsage {
+            date: Some(today()),
+            bytes: user.used_today() + used,
+        };
+        self.save(&users)
+    }
+
+    fn save(&self, users: &BTreeMap<String, User>) -> Result<()> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // 文件包含密码哈希，以 0600 原子写入
+        secrets::write_private(path, serde_json::to_string_pretty(users)?.as_bytes())?;
+        debug!("💾 已保存用户: {}", path.display());
+        Ok(())
+    }
+}
+
+/// 一次转存的额度预留：转存前按计划预留（见 [`TransferQuota`]），每批转存成功后把这批的字节
+/// 从预留转为已用；结束后由 [`UserQuota::release`] 释放剩余预留，没有释放就被丢弃
+/// （任务异常终止）时同样释放
+#[derive(Debug)]
+pub struct UserQuota {
+    users: Arc<UserStore>,
+    name: String,
+    reserved: Mutex<u64>,
+}
+
+impl UserQuota {
+    pub fn new(users: Arc<UserStore>, name: &str) -> Self {
+        Self {
+            users,
+            name: name.to_string(),
+            reserved: Mutex::new(0),
+        }
+    }
+
+    /// 释放尚未使用的预留（已转存的批次在 [`TransferQuota::transferred`] 中计入）
+    pub fn release(&self) -> Result<()> {
+        let reserved = std::mem::take(&mut *self.reserved.lock().unwrap());
+        self.users.settle(&self.name, reserved, 0)
+    }
+}
+
+impl TransferQuota for UserQuota {
+    fn reserve(&self, plan: &TransferPlan) -> Result<()> {
+        let mut reserved = self.reserved.lock().unwrap();
+        self.users.reserve(&self.name, *reserved, plan)?;
+        *reserved = plan.total_size;
+        Ok(())
+    }
+
+    fn transferred(&self, bytes: u64) {
+        let mut reserved = self.reserved.lock().unwrap();
+        let released = bytes.min(*reserved);
+        *reserved -= released;
+        if let Err(e) = self.users.settle(&self.name, released, bytes) {
+            warn!("⚠️ 记录用户 {} 的转存额度失败: {}", self.name, e);
+        }
+    }
+}
+
+impl Drop for UserQuota {
+    fn drop(&mut self) {
+        let reserved = std::mem::take(self.reserved.get_mut().unwrap());
+        if reserved > 0 {
+            let _ = self.users.settle(&self.name, reserved, 0);
+        }
+    }
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 32
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "用户名只能包含字母、数字、_、-、.，长度 1-32: {}",
+            name
+        ))
+    }
+}
+
+fn check_admin(users: &BTreeMap<String, User>, admin_fallback: bool) -> Result<()> {
+    if admin_fallback || users.is_empty() || users.values().any(|u| u.role == Role::Admin) {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "至少需要保留一个管理员用户（或在 [web] 中设置密码），否则将无法再管理用户"
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(name: &str, role: Role) -> UserUpdate {
+        UserUpdate {
+            name: name.to_string(),
+            password: Some("password123".to_string()),
+            role: Some(role),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_roles_and_roots() {
+        assert!(Role::User.allows(Scope::Transfer));
+        assert!(Role::User.allows(Scope::Fs));
+        assert!(!Role::User.allows(Scope::Admin));
+        assert!(Role::ReadOnly.allows(Scope::Preview));
+        assert!(!Role::ReadOnly.allows(Scope::Transfer));
+
+        assert!(within("/团队/alice", "/团队/alice"));
+        assert!(within("/团队/alice/电影", "/团队/alice/"));
+        assert!(!within("/团队/alice2", "/团队/alice"));
+        assert!(!within("/团队/alice/../bob", "/团队/alice"));
+        assert!(within("/任意", "/"));
+
+        let store = UserStore::default();
+        let mut alice = update("alice", Role::User);
+        alice.save_roots = Some(vec!["/团队/alice/".to_string()]);
+        store.upsert(alice, &[], true).unwrap();
+        let user = store.get("alice").unwrap();
+        assert!(user.allows_path("/团队/alice"));
+        assert!(!user.allows_path_below("/团队/alice"));
+        assert!(!user.allows_path_below("/团队/alice/"));
+        assert!(user.allows_path_below("/团队/alice/电影"));
+    }
+
+    #[test]
+    fn test_dot_and_empty_segments_rejected() {
+        assert!(is_plain_path("/"));
+        assert!(is_plain_path("/root/"));
+        assert!(!is_plain_path("/root//x"));
+
+        let store = UserStore::default();
+        let mut alice = update("alice", Role::User);
+        alice.save_roots = Some(vec!["/root".to_string()]);
+        store.upsert(alice, &[], true).unwrap();
+        let user = store.get("alice").unwrap();
+        for path in ["/root/.", "/root/./x", "/root//", "/root/x/..", "//root/x"] {
+            assert!(!user.allows_path(path), "{}", path);
+            assert!(!user.allows_path_below(path), "{}", path);
+        }
+        assert!(user.allows_path_below("/root/x/"));
+    }
+
+    #[test]
+    fn test_upsert_keeps_an_admin() {
+        let store = UserStore::default();
+        let accounts = vec!["main".to_string()];
+
+        // 没有 [web] 密码时，第一个用户必须是管理员
+        assert!(store
+            .upsert(update("alice", Role::User), &accounts, false)
+            .is_err());
+        store
+            .upsert(update("admin", Role::Admin), &accounts, false)
+            .unwrap();
+        let mut alice = update("alice", Role::User);
+        alice.accounts = Some(vec!["other".to_string()]);
+        assert!(store.upsert(alice.clone(), &accounts, false).is_err());
+        alice.accounts = Some(accounts.clone());
+        alice.daily_quota_bytes = Some(100);
+        store.upsert(alice, &accounts, false).unwrap();
+
+        assert!(store.authenticate("alice", "password123").is_some());
+        assert!(store.authenticate("alice", "wrong").is_none());
+        assert!(store.authenticate("nobody", "password123").is_none());
+        // 固定哈希与新生成的哈希参数相同，校验耗时一致
+        let params = |h: &str| h.rsplitn(3, '$').nth(2).unwrap().to_string();
+        assert_eq!(params(DUMMY_HASH), params(&password::hash("x").unwrap()));
+        assert!(store.remove("admin", false).is_err());
+        assert!(store.remove("admin", true).unwrap());
+
+        // 只修改额度时保留密码和账号绑定
+        store.settle("alice", 0, 60).unwrap();
+        let alice = store
+            .upsert(
+                UserUpdate {
+                    name: "alice".to_string(),
+                    daily_quota_bytes: Some(80),
+                    ..Default::default()
+                },
+                &accounts,
+                true,
+            )
+            .unwrap();
+        assert_eq!(alice.accounts, accounts);
+        assert_eq!(alice.remaining_quota(), Some(20));
+        assert!(store.authenticate("alice", "password123").is_some());
+    }
+}
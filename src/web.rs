@@ -2,33 +2,51 @@
 
 use anyhow::{anyhow, Result};
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::{Html, Json, Redirect},
-    routing::{get, post},
-    Router,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Json, Redirect, Response,
+    },
+    routing::{delete, get, post},
+    Extension, Router,
 };
+use axum_extra::extract::cookie::SameSite;
 use axum_extra::extract::CookieJar;
+use futures_util::stream::{self, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
 use tracing::{error, info, warn};
 use url::Url;
 
-use crate::{baidupcs, AppState};
+use crate::auth::{self, AuthError, Principal, TokenUsage, AUTH_COOKIE_NAME};
+use crate::config::Scope;
+use crate::csrf;
+use crate::jobs::{Job, JobResult, JobUpdate};
+use crate::ratelimit::{self, ClientIp, RateLimited};
+use crate::users::{Role, User, UserQuota, UserUpdate};
+use crate::{baidupcs, password, session::Session, state::Account, AppState, SharedState};
 
 // 缓存 HTML 模板（避免每次都读取）
 static HTML_TEMPLATE: &str = include_str!("../templates/index.html");
 static LOGIN_TEMPLATE: &str = include_str!("../templates/login.html");
 
-// 认证 token（简单实现，生产环境应使用更安全的方式）
-const AUTH_TOKEN: &str = "baidupcs_auth_token";
-const AUTH_COOKIE_NAME: &str = "baidupcs_auth";
-
 #[derive(Debug, Deserialize)]
 pub struct TransferRequest {
     pub share_url: String,
     #[serde(default)]
     pub pwd: String,
+    /// 只返回转存计划，不写入网盘
+    #[serde(default)]
+    pub dry_run: bool,
+    /// 使用的账号名称，为空时使用默认账号
+    #[serde(default)]
+    pub account: Option<String>,
+    /// 保存目录，为空时使用用户的第一个允许目录或账号的 `save_path`
+    #[serde(default)]
+    pub save_path: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -39,10 +57,19 @@ pub struct TransferResponse {
     pub file_count: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub save_path: Option<String>,
+    /// 转存计划（dry-run 时返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<baidupcs::TransferPlan>,
+    /// 实际使用的账号
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct LoginRequest {
+    /// 用户名，为空时校验 `[web]` 密码（管理员）
+    #[serde(default)]
+    pub username: String,
     pub password: String,
 }
 
@@ -50,6 +77,214 @@ pub struct LoginRequest {
 pub struct LoginResponse {
     pub success: bool,
     pub message: String,
+    /// 登录成功时返回会话的 CSRF 令牌，修改类请求放在 `X-CSRF-Token` 头中（见 [`crate::csrf`]）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub csrf_token: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionView {
+    #[serde(flatten)]
+    pub session: Session,
+    /// 是否为发起请求的会话
+    pub current: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionListResponse {
+    pub success: bool,
+    pub message: String,
+    pub sessions: Vec<SessionView>,
+}
+
+/// Web 用户（不含密码哈希）
+#[derive(Debug, Serialize)]
+pub struct UserView {
+    pub name: String,
+    pub role: Role,
+    pub save_roots: Vec<String>,
+    pub accounts: Vec<String>,
+    pub daily_quota_bytes: u64,
+    pub used_today_bytes: u64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<&User> for UserView {
+    fn from(user: &User) -> Self {
+        Self {
+            name: user.name.clone(),
+            role: user.role,
+            save_roots: user.save_roots.clone(),
+            accounts: user.accounts.clone(),
+            daily_quota_bytes: user.daily_quota_bytes,
+            used_today_bytes: user.used_today(),
+            created_at: user.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserListResponse {
+    pub success: bool,
+    pub message: String,
+    pub users: Vec<UserView>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserResponse {
+    pub success: bool,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<UserView>,
+}
+
+/// 当前登录身份
+#[derive(Debug, Serialize)]
+pub struct MeResponse {
+    pub success: bool,
+    pub message: String,
+    /// 是否可以管理用户、会话和令牌
+    pub admin: bool,
+    /// 用户登录时的用户信息（目录、账号与额度限制）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<UserView>,
+}
+
+/// API 令牌（不含令牌本身）
+#[derive(Debug, Serialize)]
+pub struct TokenView {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    #[serde(flatten)]
+    pub usage: TokenUsage,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenListResponse {
+    pub success: bool,
+    pub message: String,
+    pub tokens: Vec<TokenView>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuotaResponse {
+    pub success: bool,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quota: Option<baidupcs::QuotaInfo>,
+}
+
+/// 通过 query 指定账号（`?account=name`）
+#[derive(Debug, Default, Deserialize)]
+pub struct AccountQuery {
+    #[serde(default)]
+    pub account: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountListResponse {
+    pub success: bool,
+    pub accounts: Vec<String>,
+    pub default: String,
+    /// 账号选择策略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<crate::config::PoolStrategy>,
+    /// 各账号的健康状态
+    pub pool: Vec<crate::pool::AccountStatus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountResponse {
+    pub success: bool,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account: Option<baidupcs::AccountInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QrLoginPollRequest {
+    pub sign: String,
+    /// 登录成功后写入的账号名称，为空时写入 `[baidu]`
+    #[serde(default)]
+    pub account: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct QrLoginResponse {
+    pub success: bool,
+    pub message: String,
+    /// waiting / scanned / confirmed / expired
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sign: Option<String>,
+    /// 二维码 SVG
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub svg: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FsResponse {
+    pub success: bool,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+    /// 递归列目录达到 `[web] fs_max_depth` / `fs_max_entries` 上限，结果不完整
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FsListQuery {
+    #[serde(default = "default_fs_dir")]
+    pub dir: String,
+    #[serde(default)]
+    pub recursive: bool,
+    #[serde(default)]
+    pub account: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FsTreeQuery {
+    #[serde(default = "default_fs_dir")]
+    pub dir: String,
+    pub depth: Option<usize>,
+    #[serde(default)]
+    pub account: Option<String>,
+}
+
+fn default_fs_dir() -> String {
+    "/".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FsMkdirRequest {
+    pub path: String,
+    #[serde(default)]
+    pub account: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FsRenameRequest {
+    pub path: String,
+    pub newname: String,
+    #[serde(default)]
+    pub account: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FsMoveRequest {
+    pub paths: Vec<String>,
+    pub dest: String,
+    #[serde(default)]
+    pub account: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FsDeleteRequest {
+    pub paths: Vec<String>,
+    #[serde(default)]
+    pub account: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -58,19 +293,9 @@ pub struct HealthResponse {
     pub version: &'static str,
 }
 
-/// 检查是否已认证
-fn is_authenticated(cookies: &CookieJar) -> bool {
-    cookies
-        .get(AUTH_COOKIE_NAME)
-        .map(|c| c.value() == AUTH_TOKEN)
-        .unwrap_or(false)
-}
-
-/// 验证密码
-/// 注意：这里使用简单的字符串比较，适用于单用户场景
-/// 如需多用户或更高安全性，建议使用密码哈希（如 bcrypt）
+/// 验证密码（argon2 哈希或常量时间比较明文，见 [`crate::password`]）
 fn verify_password(state: &AppState, password: &str) -> bool {
-    !state.config.web.password.is_empty() && state.config.web.password == password
+    password::verify(&state.config.web, password)
 }
 
 /// 登录页面
@@ -79,57 +304,132 @@ pub async fn login_page_handler() -> Html<&'static str> {
 }
 
 /// 登录 API
+///
+/// 连续失败达到次数后按客户端 IP 和用户名锁定（指数退避），锁定期间不校验密码，直接返回 `429`。
 pub async fn login_handler(
-    State(state): State<Arc<AppState>>,
+    State(shared): State<SharedState>,
+    Extension(ClientIp(ip)): Extension<ClientIp>,
+    headers: HeaderMap,
     mut cookies: CookieJar,
     Json(req): Json<LoginRequest>,
-) -> (CookieJar, Json<LoginResponse>) {
-    // 如果未设置密码，允许直接访问
-    if state.config.web.password.is_empty() {
-        return (
+) -> Result<(CookieJar, Json<LoginResponse>), RateLimited> {
+    let state = shared.load();
+    // 如果未设置密码也没有用户，允许直接访问
+    if !state.login_required() {
+        return Ok((
             cookies,
             Json(LoginResponse {
                 success: true,
                 message: "密码未设置，无需登录".to_string(),
+                csrf_token: None,
             }),
-        );
+        ));
+    }
+
+    let mut lock_keys = vec![if req.username.is_empty() {
+        "admin".to_string()
+    } else {
+        format!("user:{}", req.username)
+    }];
+    lock_keys.extend(ip.map(|ip| format!("ip:{}", ip)));
+    if let Some(wait) = state.limiter.login_locked(&lock_keys) {
+        warn!("🔒 登录已锁定（{}），拒绝尝试", lock_keys.join(", "));
+        return Err(RateLimited::login_locked(wait));
     }
 
-    if verify_password(&state, &req.password) {
-        info!("✅ 登录成功");
-        // 设置认证 cookie（30天过期，HttpOnly 防止 XSS）
-        let mut cookie = axum_extra::extract::cookie::Cookie::new(AUTH_COOKIE_NAME, AUTH_TOKEN);
+    // 填写了用户名时按用户登录，否则校验 [web] 密码
+    // argon2 校验要占用数十毫秒 CPU，放到阻塞线程池执行，不阻塞其他请求
+    let user = {
+        let state = state.clone();
+        let (username, password) = (req.username.clone(), req.password.clone());
+        tokio::task::spawn_blocking(move || {
+            if username.is_empty() {
+                verify_password(&state, &password).then_some(None)
+            } else {
+                state
+                    .users
+                    .authenticate(&username, &password)
+                    .map(|u| Some(u.name))
+            }
+        })
+        .await
+        .unwrap_or(None)
+    };
+    if let Some(user) = user {
+        state.limiter.login_succeeded(&lock_keys);
+        let user_agent = headers
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let (token, session) =
+            state
+                .sessions
+                .create_for(&state.config.web, user.clone(), user_agent);
+        info!(
+            "✅ 登录成功（{}），会话 {}",
+            user.as_deref().unwrap_or("管理员密码"),
+            session.id
+        );
+        // 会话令牌 cookie（HttpOnly 防止 XSS），有效期与服务端会话一致
+        let mut cookie = axum_extra::extract::cookie::Cookie::new(AUTH_COOKIE_NAME, token);
         cookie.set_path("/");
-        cookie.set_max_age(time::Duration::days(30));
+        cookie.set_max_age(time::Duration::seconds(
+            state.config.web.session_ttl_secs as i64,
+        ));
         cookie.set_http_only(true); // 防止 JavaScript 访问，提高安全性
-                                    // 注意：Secure 标志仅在 HTTPS 环境下启用，HTTP 环境下不设置
+        cookie.set_secure(state.config.web.tls_enabled()); // 启用 HTTPS 时只经加密连接发送
+        cookie.set_same_site(SameSite::Strict); // 其他站点发起的请求不携带（CSRF 防护）
         cookies = cookies.add(cookie);
-        (
+        Ok((
             cookies,
             Json(LoginResponse {
                 success: true,
                 message: "登录成功".to_string(),
+                csrf_token: Some(session.csrf_token),
             }),
-        )
+        ))
     } else {
-        warn!("❌ 登录失败：密码错误");
-        (
+        warn!(
+            "❌ 登录失败：用户名或密码错误（{}）",
+            ip.map(|ip| ip.to_string())
+                .unwrap_or_else(|| "-".to_string())
+        );
+        if let Some(wait) = state
+            .limiter
+            .login_failed(&state.config.web.rate_limit, &lock_keys)
+        {
+            warn!("🔒 连续登录失败，锁定 {} 秒", wait.as_secs());
+            return Err(RateLimited::login_locked(wait));
+        }
+        Ok((
             cookies,
             Json(LoginResponse {
                 success: false,
-                message: "密码错误".to_string(),
+                message: if req.username.is_empty() {
+                    "密码错误".to_string()
+                } else {
+                    "用户名或密码错误".to_string()
+                },
+                csrf_token: None,
             }),
-        )
+        ))
     }
 }
 
-/// 登出 API：清除认证 cookie
-pub async fn logout_handler(mut cookies: CookieJar) -> (CookieJar, Json<LoginResponse>) {
+/// 登出 API：注销服务端会话并清除认证 cookie
+pub async fn logout_handler(
+    State(shared): State<SharedState>,
+    mut cookies: CookieJar,
+) -> (CookieJar, Json<LoginResponse>) {
+    if let Some(token) = cookies.get(AUTH_COOKIE_NAME) {
+        shared.load().sessions.revoke_token(token.value());
+    }
     // 通过设置 Max-Age = 0 来删除 cookie（并保持 Path 与 HttpOnly 设置以确保正确移除）
     let mut cookie = axum_extra::extract::cookie::Cookie::new(AUTH_COOKIE_NAME, "");
     cookie.set_path("/");
     cookie.set_max_age(time::Duration::seconds(0));
     cookie.set_http_only(true);
+    cookie.set_same_site(SameSite::Strict);
     cookies = cookies.remove(cookie);
 
     (
@@ -137,26 +437,200 @@ pub async fn logout_handler(mut cookies: CookieJar) -> (CookieJar, Json<LoginRes
         Json(LoginResponse {
             success: true,
             message: "已退出登录".to_string(),
+            csrf_token: None,
         }),
     )
 }
 
-/// 首页 - 返回 HTML 页面（需要认证）
+/// 列出有效的登录会话
+pub async fn sessions_handler(
+    State(shared): State<SharedState>,
+    Extension(principal): Extension<Principal>,
+) -> Json<SessionListResponse> {
+    let state = shared.load();
+    let current = principal.session().map(|s| s.id.clone());
+    let sessions: Vec<_> = state
+        .sessions
+        .list(&state.config.web)
+        .into_iter()
+        .map(|session| SessionView {
+            current: current.as_deref() == Some(session.id.as_str()),
+            session,
+        })
+        .collect();
+    Json(SessionListResponse {
+        success: true,
+        message: format!("{} 个登录会话", sessions.len()),
+        sessions,
+    })
+}
+
+/// 注销指定的登录会话
+pub async fn session_revoke_handler(
+    State(shared): State<SharedState>,
+    Path(id): Path<String>,
+) -> Json<LoginResponse> {
+    let state = shared.load();
+    if state.sessions.revoke(&id) {
+        info!("🔐 已注销会话 {}", id);
+        Json(LoginResponse {
+            success: true,
+            message: "会话已注销".to_string(),
+            csrf_token: None,
+        })
+    } else {
+        Json(LoginResponse {
+            success: false,
+            message: "会话不存在或已过期".to_string(),
+            csrf_token: None,
+        })
+    }
+}
+
+/// 列出 API 令牌及最近使用情况
+pub async fn tokens_handler(State(shared): State<SharedState>) -> Json<TokenListResponse> {
+    let state = shared.load();
+    let tokens: Vec<_> = state
+        .config
+        .web
+        .tokens
+        .iter()
+        .map(|t| TokenView {
+            name: t.name.clone(),
+            scopes: t.scopes.clone(),
+            usage: state.tokens.usage(&t.name),
+        })
+        .collect();
+    Json(TokenListResponse {
+        success: true,
+        message: format!("{} 个 API 令牌", tokens.len()),
+        tokens,
+    })
+}
+
+/// 当前登录身份及其限制
+pub async fn me_handler(Extension(principal): Extension<Principal>) -> Json<MeResponse> {
+    let message = match &principal {
+        Principal::Anonymous => "未设置密码".to_string(),
+        Principal::Session(_) => "管理员".to_string(),
+        Principal::User { user, .. } => user.name.clone(),
+        Principal::Token { name, .. } => format!("API 令牌 {}", name),
+    };
+    Json(MeResponse {
+        success: true,
+        message,
+        admin: principal.allows(Scope::Admin),
+        user: principal.user().map(UserView::from),
+    })
+}
+
+/// 列出 Web 用户
+pub async fn users_handler(State(shared): State<SharedState>) -> Json<UserListResponse> {
+    let users: Vec<_> = shared
+        .load()
+        .users
+        .list()
+        .iter()
+        .map(UserView::from)
+        .collect();
+    Json(UserListResponse {
+        success: true,
+        message: format!("{} 个用户", users.len()),
+        users,
+    })
+}
+
+/// 创建或修改 Web 用户；重设密码后该用户需要重新登录
+pub async fn user_save_handler(
+    State(shared): State<SharedState>,
+    Json(update): Json<UserUpdate>,
+) -> Json<UserResponse> {
+    let state = shared.load();
+    let created = state.users.get(&update.name).is_none();
+    let password_changed = update.password.is_some();
+    // 设置密码时计算 argon2 哈希，放到阻塞线程池执行
+    let result = {
+        let state = state.clone();
+        tokio::task::spawn_blocking(move || {
+            state.users.upsert(
+                update,
+                &state.account_names(),
+                state.config.web.password_required(),
+            )
+        })
+        .await
+        .unwrap_or_else(|e| Err(anyhow!("保存用户失败: {}", e)))
+    };
+    match result {
+        Ok(user) => {
+            if password_changed && !created {
+                state.sessions.revoke_user(&user.name);
+            }
+            info!(
+                "👥 已{}用户 {}（{:?}）",
+                if created { "创建" } else { "修改" },
+                user.name,
+                user.role
+            );
+            Json(UserResponse {
+                success: true,
+                message: format!("用户 {} 已保存", user.name),
+                user: Some(UserView::from(&user)),
+            })
+        }
+        Err(e) => Json(UserResponse {
+            success: false,
+            message: e.to_string(),
+            user: None,
+        }),
+    }
+}
+
+/// 删除 Web 用户并注销其登录会话
+pub async fn user_delete_handler(
+    State(shared): State<SharedState>,
+    Path(name): Path<String>,
+) -> Json<UserResponse> {
+    let state = shared.load();
+    let (success, message) = match state
+        .users
+        .remove(&name, state.config.web.password_required())
+    {
+        Ok(true) => {
+            state.sessions.revoke_user(&name);
+            info!("👥 已删除用户 {}", name);
+            (true, format!("用户 {} 已删除", name))
+        }
+        Ok(false) => (false, format!("用户不存在: {}", name)),
+        Err(e) => (false, e.to_string()),
+    };
+    Json(UserResponse {
+        success,
+        message,
+        user: None,
+    })
+}
+
+/// 首页 - 返回 HTML 页面（需要认证），注入当前会话的 CSRF 令牌
 pub async fn index_handler(
-    State(state): State<Arc<AppState>>,
+    State(shared): State<SharedState>,
     cookies: CookieJar,
-) -> Result<Html<&'static str>, Redirect> {
-    // 如果未设置密码，直接返回页面
-    if state.config.web.password.is_empty() {
-        return Ok(Html(HTML_TEMPLATE));
+) -> Result<Html<String>, Redirect> {
+    let state = shared.load();
+    // 如果未设置密码也没有用户，直接返回页面
+    if !state.login_required() {
+        return Ok(Html(render_index("")));
     }
 
     // 检查认证
-    if !is_authenticated(&cookies) {
-        return Err(Redirect::to("/login"));
+    match auth::current_session(&state, &cookies) {
+        Some(session) => Ok(Html(render_index(&session.csrf_token))),
+        None => Err(Redirect::to("/login")),
     }
+}
 
-    Ok(Html(HTML_TEMPLATE))
+fn render_index(csrf_token: &str) -> String {
+    HTML_TEMPLATE.replace("{{CSRF_TOKEN}}", csrf_token)
 }
 
 /// 健康检查端点（不需要认证）
@@ -167,20 +641,198 @@ pub async fn health_handler() -> Json<HealthResponse> {
     })
 }
 
-/// 转存 API 端点（需要认证）
-pub async fn transfer_handler(
-    State(state): State<Arc<AppState>>,
-    cookies: CookieJar,
-    Json(req): Json<TransferRequest>,
-) -> Result<Json<TransferResponse>, StatusCode> {
-    // 检查认证（如果设置了密码）
-    if !state.config.web.password.is_empty() && !is_authenticated(&cookies) {
-        return Ok(Json(TransferResponse {
+/// 网盘容量查询端点（需要认证）
+pub async fn quota_handler(
+    State(shared): State<SharedState>,
+    Extension(principal): Extension<Principal>,
+    Query(query): Query<AccountQuery>,
+) -> Result<Json<QuotaResponse>, AuthError> {
+    let state = shared.load();
+    let account = match state.account(requested_account(&principal, query.account.as_deref())) {
+        Ok(account) => account,
+        Err(e) => {
+            return Ok(Json(QuotaResponse {
+                success: false,
+                message: e.to_string(),
+                quota: None,
+            }))
+        }
+    };
+    check_account(&principal, &account.name)?;
+
+    Ok(match baidupcs::get_quota(&account).await {
+        Ok(quota) => Json(QuotaResponse {
+            success: true,
+            message: format!(
+                "剩余 {} / 共 {}",
+                baidupcs::quota::format_size(quota.free),
+                baidupcs::quota::format_size(quota.total)
+            ),
+            quota: Some(quota),
+        }),
+        Err(e) => {
+            warn!("❌ 查询网盘容量失败: {}", e);
+            Json(QuotaResponse {
+                success: false,
+                message: format!("查询网盘容量失败: {}", e),
+                quota: None,
+            })
+        }
+    })
+}
+
+/// 账号信息端点（需要认证）
+pub async fn account_handler(
+    State(shared): State<SharedState>,
+    Extension(principal): Extension<Principal>,
+    Query(query): Query<AccountQuery>,
+) -> Result<Json<AccountResponse>, AuthError> {
+    let state = shared.load();
+    let account = match state.account(requested_account(&principal, query.account.as_deref())) {
+        Ok(account) => account,
+        Err(e) => {
+            return Ok(Json(AccountResponse {
+                success: false,
+                message: e.to_string(),
+                account: None,
+            }))
+        }
+    };
+    check_account(&principal, &account.name)?;
+
+    Ok(match baidupcs::whoami(&account).await {
+        Ok(account) => Json(AccountResponse {
+            success: true,
+            message: format!("{}（{}）", account.username, account.vip_level),
+            account: Some(account),
+        }),
+        Err(e) => {
+            warn!("❌ 查询账号信息失败: {}", e);
+            Json(AccountResponse {
+                success: false,
+                message: e.to_string(),
+                account: None,
+            })
+        }
+    })
+}
+
+/// 账号列表端点（需要认证），用户登录时只列出其可以使用的账号
+pub async fn accounts_handler(
+    State(shared): State<SharedState>,
+    Extension(principal): Extension<Principal>,
+) -> Json<AccountListResponse> {
+    let state = shared.load();
+    let accounts: Vec<_> = state
+        .account_names()
+        .into_iter()
+        .filter(|name| check_account(&principal, name).is_ok())
+        .collect();
+    let default = requested_account(&principal, None)
+        .map(str::to_string)
+        .unwrap_or_else(|| state.default_account().name.clone());
+
+    Json(AccountListResponse {
+        success: true,
+        default,
+        strategy: Some(state.pool.strategy()),
+        pool: state
+            .pool
+            .status()
+            .into_iter()
+            .filter(|s| accounts.contains(&s.name))
+            .collect(),
+        accounts,
+    })
+}
+
+/// 请求的账号名称：未指定时，绑定了账号的用户使用其第一个账号，其他情况为默认账号（`None`）
+fn requested_account<'a>(principal: &'a Principal, name: Option<&'a str>) -> Option<&'a str> {
+    name.filter(|n| !n.is_empty()).or_else(|| {
+        principal
+            .user()
+            .and_then(|u| u.accounts.first())
+            .map(String::as_str)
+    })
+}
+
+/// 用户登录时检查其是否可以使用百度账号 `account`
+fn check_account(principal: &Principal, account: &str) -> Result<(), AuthError> {
+    match principal.user() {
+        Some(user) if !user.allows_account(account) => Err(AuthError::forbidden(format!(
+            "用户 {} 无权使用账号 {}",
+            user.name, account
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// 用户登录时检查路径是否都位于其允许的目录内
+fn check_paths<'a>(
+    principal: &Principal,
+    paths: impl IntoIterator<Item = &'a str>,
+) -> Result<(), AuthError> {
+    check_paths_with(principal, paths, User::allows_path)
+}
+
+/// 同 [`check_paths`]，但保存目录本身不允许（删除、移动、重命名、复制的源路径）
+fn check_paths_below<'a>(
+    principal: &Principal,
+    paths: impl IntoIterator<Item = &'a str>,
+) -> Result<(), AuthError> {
+    check_paths_with(principal, paths, User::allows_path_below)
+}
+
+fn check_paths_with<'a>(
+    principal: &Principal,
+    paths: impl IntoIterator<Item = &'a str>,
+    allows: fn(&User, &str) -> bool,
+) -> Result<(), AuthError> {
+    let Some(user) = principal.user() else {
+        return Ok(());
+    };
+    match paths.into_iter().find(|p| !allows(user, p)) {
+        Some(path) => Err(AuthError::forbidden(format!(
+            "用户 {} 只能访问 {}，无权访问 {}",
+            user.name,
+            user.save_roots.join("、"),
+            path
+        ))),
+        None => Ok(()),
+    }
+}
+
+impl TransferResponse {
+    fn failure(message: impl Into<String>) -> Self {
+        Self {
             success: false,
-            message: "未登录，请先登录".to_string(),
+            message: message.into(),
             file_count: None,
             save_path: None,
-        }));
+            plan: None,
+            account: None,
+        }
+    }
+}
+
+/// 校验通过、可以执行的转存
+struct PreparedTransfer {
+    /// 依次尝试的账号
+    candidates: Vec<Arc<Account>>,
+    options: baidupcs::TransferOptions,
+    /// 用户的额度预留，转存结束后释放未使用的部分
+    quota: Option<Arc<UserQuota>>,
+}
+
+/// 校验转存请求：权限、链接与提取码、保存目录、用户额度，并选择候选账号
+async fn prepare_transfer(
+    state: &AppState,
+    principal: &Principal,
+    req: &TransferRequest,
+) -> Result<PreparedTransfer, Rejection> {
+    // 路由只要求 preview 权限，实际写入网盘还需要 transfer 权限
+    if !req.dry_run && !principal.allows(Scope::Transfer) {
+        return Err(AuthError::missing_scope(principal, Scope::Transfer).into());
     }
 
     info!("📥 收到转存请求: {}", req.share_url);
@@ -188,119 +840,671 @@ pub async fn transfer_handler(
     // 验证输入
     if let Err(e) = validate_share_url(&req.share_url) {
         warn!("❌ 分享链接验证失败: {}", e);
-        return Ok(Json(TransferResponse {
-            success: false,
-            message: format!("分享链接验证失败: {}", e),
-            file_count: None,
-            save_path: None,
-        }));
+        return Err(Rejection::Invalid(format!("分享链接验证失败: {}", e)));
     }
 
     // 验证提取码
     if let Err(e) = validate_password(&req.pwd) {
         warn!("❌ 提取码验证失败: {}", e);
-        return Ok(Json(TransferResponse {
-            success: false,
-            message: format!("提取码验证失败: {}", e),
-            file_count: None,
-            save_path: None,
-        }));
+        return Err(Rejection::Invalid(format!("提取码验证失败: {}", e)));
     }
 
-    // 提取 surl
-    let surl = match baidupcs::extract_surl(&req.share_url) {
-        Some(s) => s,
-        None => {
-            error!("❌ 无法从链接中提取 surl: {}", req.share_url);
-            return Ok(Json(TransferResponse {
-                success: false,
-                message: format!("无效的分享链接格式，无法提取分享码: {}", req.share_url),
-                file_count: None,
-                save_path: None,
-            }));
+    // 提取 surl（转存流程内部会再次提取，这里提前给出友好提示）
+    if baidupcs::extract_surl(&req.share_url).is_none() {
+        error!("❌ 无法从链接中提取 surl: {}", req.share_url);
+        return Err(Rejection::Invalid(format!(
+            "无效的分享链接格式，无法提取分享码: {}",
+            req.share_url
+        )));
+    }
+
+    // 用户只能转存到允许的目录，未指定时使用其第一个允许目录
+    let save_path = req
+        .save_path
+        .clone()
+        .filter(|p| !p.is_empty())
+        .or_else(|| principal.user().and_then(|u| u.save_roots.first().cloned()));
+    if let Some(path) = &save_path {
+        check_paths(principal, [path.as_str()])?;
+    }
+    if principal.user().and_then(User::remaining_quota) == Some(0) && !req.dry_run {
+        return Err(Rejection::Invalid("今日转存额度已用完".to_string()));
+    }
+
+    // 选择账号：指定了账号时只用该账号，否则按账号池策略在可用的账号中选择并自动切换
+    let candidates = match req.account.as_deref().filter(|a| !a.is_empty()) {
+        Some(name) => match state.account(Some(name)) {
+            Ok(account) => {
+                check_account(principal, &account.name)?;
+                vec![account]
+            }
+            Err(e) => {
+                warn!("❌ {}", e);
+                return Err(Rejection::Invalid(e.to_string()));
+            }
+        },
+        None => state
+            .pool
+            .candidates()
+            .await
+            .into_iter()
+            .filter(|a| check_account(principal, &a.name).is_ok())
+            .collect(),
+    };
+
+    // 额度在生成计划后按实际大小预留，并发的转存不会都按同一份剩余额度放行
+    let quota = principal
+        .user()
+        .map(|u| Arc::new(UserQuota::new(state.users.clone(), &u.name)));
+    Ok(PreparedTransfer {
+        candidates,
+        options: baidupcs::TransferOptions {
+            dry_run: req.dry_run,
+            save_path,
+            quota: quota.clone().map(|q| q as Arc<dyn baidupcs::TransferQuota>),
+            progress: None,
+        },
+        quota,
+    })
+}
+
+/// 执行转存，已写入网盘的批次计入用户的转存额度，结束后释放剩余预留；失败时返回给用户看的错误信息
+async fn execute_transfer(
+    state: &AppState,
+    req: &TransferRequest,
+    prepared: PreparedTransfer,
+) -> Result<baidupcs::TransferOutcome, String> {
+    let result = state
+        .pool
+        .run_transfer_with(
+            &prepared.candidates,
+            &req.share_url,
+            &req.pwd,
+            prepared.options,
+        )
+        .await;
+    if let Some(quota) = &prepared.quota {
+        if let Err(e) = quota.release() {
+            warn!("⚠️ 释放转存额度预留失败: {}", e);
         }
+    }
+    let outcome = result.map_err(|e| {
+        error!("❌ 转存失败: {}", e);
+        format!("转存失败: {}", user_friendly_error(&e))
+    })?;
+
+    if outcome.dry_run {
+        info!("🧪 dry-run: 返回转存计划，不执行转存");
+        return Ok(outcome);
+    }
+    info!("✅ 转存成功（账号: {}）", outcome.account);
+    Ok(outcome)
+}
+
+/// 转存结果的提示信息
+fn outcome_message(outcome: &baidupcs::TransferOutcome) -> String {
+    let plan = &outcome.plan;
+    if !outcome.dry_run {
+        return format!(
+            "转存成功！{} 个文件已通过账号 {} 保存至: {}",
+            plan.fs_ids.len(),
+            outcome.account,
+            plan.save_path
+        );
+    }
+    if plan.save_path_exists {
+        format!(
+            "预览：{} 个文件将通过账号 {} 转存至: {}",
+            plan.fs_ids.len(),
+            outcome.account,
+            plan.save_path
+        )
+    } else {
+        format!(
+            "预览：{} 个文件将通过账号 {} 转存至: {}（目录不存在，实际转存将失败）",
+            plan.fs_ids.len(),
+            outcome.account,
+            plan.save_path
+        )
+    }
+}
+
+/// 转存 API 端点（需要认证），转存完成后才返回；耗时较长的转存建议使用 `POST /api/jobs`
+pub async fn transfer_handler(
+    State(shared): State<SharedState>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<TransferRequest>,
+) -> Result<Json<TransferResponse>, Rejection> {
+    let state = shared.load();
+    let prepared = prepare_transfer(&state, &principal, &req).await?;
+    let outcome = match execute_transfer(&state, &req, prepared).await {
+        Ok(outcome) => outcome,
+        Err(message) => return Ok(Json(TransferResponse::failure(message))),
     };
 
-    // 获取分享信息
-    let info = match baidupcs::get_share_info(state.as_ref(), &req.share_url, &surl, &req.pwd).await
-    {
-        Ok(info) => info,
+    let message = outcome_message(&outcome);
+    let plan = outcome.plan;
+    Ok(Json(TransferResponse {
+        success: true,
+        message,
+        file_count: Some(plan.fs_ids.len()),
+        save_path: Some(plan.save_path.clone()),
+        plan: outcome.dry_run.then_some(plan),
+        account: Some(outcome.account),
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct JobResponse {
+    pub success: bool,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job: Option<Job>,
+}
+
+/// 提交后台转存任务，校验通过后立即返回任务 ID（`202`），通过 `GET /api/jobs/:id` 查询进度
+pub async fn job_submit_handler(
+    State(shared): State<SharedState>,
+    Extension(principal): Extension<Principal>,
+    Extension(ClientIp(ip)): Extension<ClientIp>,
+    Json(req): Json<TransferRequest>,
+) -> Result<(StatusCode, Json<JobResponse>), Rejection> {
+    let state = shared.load();
+    // 提交任务与 `/api/transfer` 共用转存的请求预算；查询和订阅进度只计入 `/api/` 的预算
+    let keys = ip.map(|ip| format!("ip:{}", ip)).into_iter();
+    for key in keys.chain(principal.identity()) {
+        state
+            .limiter
+            .check(&state.config.web.rate_limit, &key, "/api/transfer")?;
+    }
+    let prepared = prepare_transfer(&state, &principal, &req).await?;
+
+    let task_state = state.clone();
+    let share_url = req.share_url.clone();
+    let submitted = state.jobs.submit(
+        &state.config.jobs,
+        principal.identity(),
+        &share_url,
+        req.dry_run,
+        move |progress| async move {
+            let mut prepared = prepared;
+            prepared.options.progress = Some(progress);
+            let outcome = execute_transfer(&task_state, &req, prepared).await?;
+            Ok(JobResult {
+                message: outcome_message(&outcome),
+                account: outcome.account,
+                file_count: outcome.plan.fs_ids.len(),
+                save_path: outcome.plan.save_path.clone(),
+                plan: outcome.dry_run.then_some(outcome.plan),
+            })
+        },
+    );
+    Ok(match submitted {
+        Ok(job) => (
+            StatusCode::ACCEPTED,
+            Json(JobResponse {
+                success: true,
+                message: format!("任务已提交: {}", job.id),
+                job: Some(job),
+            }),
+        ),
         Err(e) => {
-            error!("❌ 获取分享信息失败: {}", e);
-            // 提供更友好的错误消息
-            let error_msg = e.to_string();
-            let user_friendly_msg = if error_msg.contains("提取码") || error_msg.contains("密码")
-            {
-                "提取码错误，请检查后重试".to_string()
-            } else if error_msg.contains("失效") || error_msg.contains("过期") {
-                "分享链接已失效或过期".to_string()
-            } else if error_msg.contains("Cookie") || error_msg.contains("登录") {
-                "Cookie 失效，请检查配置文件中的 BDUSS 和 STOKEN".to_string()
-            } else {
-                error_msg
-            };
-            return Ok(Json(TransferResponse {
-                success: false,
-                message: format!("获取分享信息失败: {}", user_friendly_msg),
-                file_count: None,
-                save_path: None,
-            }));
+            warn!("⚠️ {}", e);
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(JobResponse {
+                    success: false,
+                    message: e.to_string(),
+                    job: None,
+                }),
+            )
         }
-    };
+    })
+}
 
-    info!("📦 获取到 {} 个文件，开始转存...", info.fs_ids.len());
+/// 只能查看自己提交的任务（admin 权限可查看全部）
+fn can_view(principal: &Principal, job: &Job) -> bool {
+    principal.allows(Scope::Admin) || job.owner == principal.identity()
+}
 
-    // 执行转存
-    match baidupcs::transfer_files(
-        state.as_ref(),
-        &info.shareid,
-        &info.uk,
-        &info.fs_ids,
-        &info.bdstoken,
-        &surl,
+fn job_not_found(id: &str) -> (StatusCode, Json<JobResponse>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(JobResponse {
+            success: false,
+            message: format!("任务不存在或已过期: {}", id),
+            job: None,
+        }),
     )
-    .await
-    {
-        Ok(_) => {
-            info!("✅ 转存成功");
-            Ok(Json(TransferResponse {
+}
+
+/// 查询任务的阶段、进度、结果或错误
+pub async fn job_status_handler(
+    State(shared): State<SharedState>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<String>,
+) -> (StatusCode, Json<JobResponse>) {
+    let state = shared.load();
+    match state.jobs.get(&id).filter(|job| can_view(&principal, job)) {
+        Some(job) => (
+            StatusCode::OK,
+            Json(JobResponse {
                 success: true,
-                message: format!(
-                    "转存成功！{} 个文件已保存至: {}",
-                    info.fs_ids.len(),
-                    state.config.baidu.save_path
-                ),
-                file_count: Some(info.fs_ids.len()),
-                save_path: Some(state.config.baidu.save_path.clone()),
-            }))
+                message: job.status.label().to_string(),
+                job: Some(job),
+            }),
+        ),
+        None => job_not_found(&id),
+    }
+}
+
+/// 以 Server-Sent Events 推送任务进度
+///
+/// 先补发已有的进度事件（`progress`）和任务快照（`job`），之后实时推送；
+/// 任务开始执行和结束时各推送一次 `job`，结束后关闭连接。
+pub async fn job_events_handler(
+    State(shared): State<SharedState>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<JobResponse>)> {
+    let state = shared.load();
+    let (job, updates) = state
+        .jobs
+        .subscribe(&id)
+        .filter(|(job, _)| can_view(&principal, job))
+        .ok_or_else(|| job_not_found(&id))?;
+
+    let finished = job.status.is_finished();
+    let backlog: Vec<_> = job
+        .events
+        .iter()
+        .map(|event| Event::default().event("progress").json_data(event))
+        .chain([Event::default().event("job").json_data(&job)])
+        .collect();
+    let live = stream::unfold((updates, finished), |(mut updates, finished)| async move {
+        if finished {
+            return None;
+        }
+        loop {
+            match updates.recv().await {
+                Ok(JobUpdate::Event(event)) => {
+                    let sse = Event::default().event("progress").json_data(&event);
+                    return Some((sse, (updates, false)));
+                }
+                Ok(JobUpdate::Job(job)) => {
+                    let sse = Event::default().event("job").json_data(&job);
+                    return Some((sse, (updates, job.status.is_finished())));
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("⚠️ 任务进度推送过慢，跳过 {} 条更新", skipped)
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Ok((
+        // 关闭反向代理（nginx）的响应缓冲，事件才能及时送达
+        [("x-accel-buffering", "no")],
+        Sse::new(stream::iter(backlog).chain(live)).keep_alive(KeepAlive::default()),
+    ))
+}
+
+/// 将转存错误转换为更友好的提示
+fn user_friendly_error(e: &anyhow::Error) -> String {
+    if let Some(err) = e.downcast_ref::<baidupcs::BaiduError>() {
+        return match err {
+            baidupcs::BaiduError::CookieInvalid { .. } => {
+                "Cookie 失效，请检查配置文件中的 BDUSS 和 STOKEN".to_string()
+            }
+            other => other.to_string(),
+        };
+    }
+
+    let error_msg = e.to_string();
+    if error_msg.contains("提取码") || error_msg.contains("密码") {
+        "提取码错误，请检查后重试".to_string()
+    } else if error_msg.contains("路径不存在") || error_msg.contains("路径错误") {
+        format!("{}，请在百度网盘中先创建该文件夹", error_msg)
+    } else if error_msg.contains("失效") || error_msg.contains("过期") {
+        "分享链接已失效或过期".to_string()
+    } else if error_msg.contains("权限") {
+        "权限不足，可能是分享链接已失效或设置了权限限制".to_string()
+    } else {
+        error_msg
+    }
+}
+
+/// 扫码登录：获取二维码（需要认证）
+pub async fn qrlogin_start_handler(State(shared): State<SharedState>) -> Json<QrLoginResponse> {
+    let state = shared.load();
+
+    let qr = match baidupcs::QrLogin::new(&state.config.baidu) {
+        Ok(login) => login.start().await,
+        Err(e) => Err(e),
+    };
+    match qr.and_then(|qr| Ok((qr.to_svg()?, qr))) {
+        Ok((svg, qr)) => Json(QrLoginResponse {
+            success: true,
+            message: "请使用百度网盘 App 扫码".to_string(),
+            status: Some(baidupcs::QrStatus::Waiting.as_str()),
+            sign: Some(qr.sign),
+            svg: Some(svg),
+        }),
+        Err(e) => {
+            warn!("❌ 获取登录二维码失败: {}", e);
+            Json(QrLoginResponse {
+                message: format!("获取登录二维码失败: {}", e),
+                ..Default::default()
+            })
         }
+    }
+}
+
+/// 扫码登录：查询状态，确认后换取 Cookie 并写入配置文件（需要认证）
+///
+/// 会写入配置文件，因此用 `POST`，经过 [`csrf::protect`] 检查。
+pub async fn qrlogin_poll_handler(
+    State(shared): State<SharedState>,
+    Json(query): Json<QrLoginPollRequest>,
+) -> Json<QrLoginResponse> {
+    let state = shared.load();
+
+    let result = async {
+        let login = baidupcs::QrLogin::new(&state.config.baidu)?;
+        let status = login.poll(&query.sign).await?;
+        let baidupcs::QrStatus::Confirmed { token } = &status else {
+            return Ok((status, None));
+        };
+
+        let credentials = login.finish(token).await?;
+        let path = state
+            .config
+            .path
+            .clone()
+            .unwrap_or_else(|| "config.toml".into());
+        let saved = crate::config::Config::save_credentials(
+            &path,
+            query.account.as_deref(),
+            &credentials.bduss,
+            &credentials.stoken,
+        )?;
+        info!("✅ 扫码登录成功，Cookie 已写入 {}", saved.display());
+        Ok::<_, anyhow::Error>((status, Some(saved)))
+    }
+    .await;
+
+    match result {
+        Ok((status, saved)) => Json(QrLoginResponse {
+            success: true,
+            message: match (&status, saved) {
+                (_, Some(path)) => {
+                    format!("登录成功，Cookie 已写入 {}，配置重载后生效", path.display())
+                }
+                (baidupcs::QrStatus::Scanned, _) => "已扫码，请在手机上确认".to_string(),
+                (baidupcs::QrStatus::Expired, _) => "二维码已过期，请重新获取".to_string(),
+                _ => "等待扫码".to_string(),
+            },
+            status: Some(status.as_str()),
+            ..Default::default()
+        }),
         Err(e) => {
-            error!("❌ 转存失败: {}", e);
-            // 提供更友好的错误消息
-            let error_msg = e.to_string();
-            let user_friendly_msg =
-                if error_msg.contains("路径不存在") || error_msg.contains("路径错误") {
-                    format!(
-                        "保存路径不存在: {}，请在百度网盘中先创建该文件夹",
-                        state.config.baidu.save_path
-                    )
-                } else if error_msg.contains("Cookie") || error_msg.contains("登录") {
-                    "Cookie 失效，请检查配置文件中的 BDUSS 和 STOKEN".to_string()
-                } else if error_msg.contains("权限") {
-                    "权限不足，可能是分享链接已失效或设置了权限限制".to_string()
-                } else {
-                    error_msg
-                };
-            Ok(Json(TransferResponse {
+            warn!("❌ 扫码登录失败: {}", e);
+            Json(QrLoginResponse {
+                message: format!("扫码登录失败: {}", e),
+                ..Default::default()
+            })
+        }
+    }
+}
+
+/// 将文件管理操作的结果转换为响应
+///
+/// Cookie 失效等账号错误同时把账号标记为不可用，与转存时的账号池一致。
+fn fs_response<T: Serialize>(
+    state: &AppState,
+    account: &Account,
+    result: Result<T>,
+    ok_message: &str,
+) -> Json<FsResponse> {
+    if let Err(e) = &result {
+        if crate::pool::is_account_error(e) {
+            state.pool.mark_unhealthy(&account.name, &e.to_string());
+        }
+    }
+    match result {
+        Ok(data) => Json(FsResponse {
+            success: true,
+            message: ok_message.to_string(),
+            data: serde_json::to_value(data).ok().filter(|v| !v.is_null()),
+            truncated: false,
+        }),
+        Err(e) => {
+            warn!("❌ 文件操作失败: {}", e);
+            Json(FsResponse {
                 success: false,
-                message: format!("转存失败: {}", user_friendly_msg),
-                file_count: Some(info.fs_ids.len()),
-                save_path: Some(state.config.baidu.save_path.clone()),
-            }))
+                message: e.to_string(),
+                data: None,
+                truncated: false,
+            })
         }
     }
 }
 
+/// 递归列目录的响应，`data` 为条目列表，达到上限时带 `truncated: true`
+fn fs_listing<T: Serialize>(
+    state: &AppState,
+    account: &Account,
+    result: Result<baidupcs::fs::Listing<T>>,
+) -> Json<FsResponse> {
+    let truncated = result.as_ref().is_ok_and(|l| l.truncated);
+    let Json(mut response) = fs_response(state, account, result.map(|l| l.entries), "ok");
+    response.truncated = truncated;
+    Json(response)
+}
+
+/// 请求被拒绝：权限不足（`403`）或参数无效、账号不存在（`success: false`）
+pub enum Rejection {
+    Forbidden(AuthError),
+    Invalid(String),
+    /// 超出请求预算（`429`）
+    Limited(RateLimited),
+}
+
+impl From<RateLimited> for Rejection {
+    fn from(e: RateLimited) -> Self {
+        Self::Limited(e)
+    }
+}
+
+impl From<AuthError> for Rejection {
+    fn from(e: AuthError) -> Self {
+        Self::Forbidden(e)
+    }
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Forbidden(e) => e.into_response(),
+            Self::Invalid(message) => {
+                Json(serde_json::json!({ "success": false, "message": message })).into_response()
+            }
+            Self::Limited(e) => e.into_response(),
+        }
+    }
+}
+
+/// 按名称选择账号并检查用户的目录与账号限制
+///
+/// `sources` 是会被删除、移走或改名的路径，必须位于保存目录之下，不能是保存目录本身。
+fn fs_account<'a>(
+    state: &AppState,
+    principal: &Principal,
+    name: Option<&str>,
+    paths: impl IntoIterator<Item = &'a str>,
+    sources: impl IntoIterator<Item = &'a str>,
+) -> Result<Arc<Account>, Rejection> {
+    check_paths(principal, paths)?;
+    check_paths_below(principal, sources)?;
+    let account = state
+        .account(requested_account(principal, name))
+        .map_err(|e| Rejection::Invalid(e.to_string()))?;
+    check_account(principal, &account.name)?;
+    Ok(account)
+}
+
+/// 列目录（需要认证）
+pub async fn fs_list_handler(
+    State(shared): State<SharedState>,
+    Extension(principal): Extension<Principal>,
+    Query(query): Query<FsListQuery>,
+) -> Result<Json<FsResponse>, Rejection> {
+    let state = shared.load();
+    let account = fs_account(
+        &state,
+        &principal,
+        query.account.as_deref(),
+        [query.dir.as_str()],
+        [],
+    )?;
+    if query.recursive {
+        let limits = state.config.web.fs_limits(None);
+        let result = baidupcs::fs::list_recursive(&account, &query.dir, limits).await;
+        return Ok(fs_listing(&state, &account, result));
+    }
+    let result = baidupcs::fs::list_dir(&account, &query.dir).await;
+    Ok(fs_response(&state, &account, result, "ok"))
+}
+
+/// 目录树（需要认证）
+pub async fn fs_tree_handler(
+    State(shared): State<SharedState>,
+    Extension(principal): Extension<Principal>,
+    Query(query): Query<FsTreeQuery>,
+) -> Result<Json<FsResponse>, Rejection> {
+    let state = shared.load();
+    let account = fs_account(
+        &state,
+        &principal,
+        query.account.as_deref(),
+        [query.dir.as_str()],
+        [],
+    )?;
+    let limits = state.config.web.fs_limits(query.depth);
+    let result = baidupcs::fs::tree(&account, &query.dir, limits).await;
+    Ok(fs_listing(&state, &account, result))
+}
+
+/// 逐级创建目录（需要认证）
+pub async fn fs_mkdir_handler(
+    State(shared): State<SharedState>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<FsMkdirRequest>,
+) -> Result<Json<FsResponse>, Rejection> {
+    let state = shared.load();
+    let account = fs_account(
+        &state,
+        &principal,
+        req.account.as_deref(),
+        [req.path.as_str()],
+        [],
+    )?;
+    Ok(fs_response(
+        &state,
+        &account,
+        baidupcs::fs::mkdir_p(&account, &req.path).await,
+        "目录已创建",
+    ))
+}
+
+/// 重命名（需要认证）
+pub async fn fs_rename_handler(
+    State(shared): State<SharedState>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<FsRenameRequest>,
+) -> Result<Json<FsResponse>, Rejection> {
+    // 新文件名中的 `/`、`..` 会把文件移出允许的目录
+    baidupcs::fs::check_name(&req.newname).map_err(|e| Rejection::Invalid(e.to_string()))?;
+    let state = shared.load();
+    let account = fs_account(
+        &state,
+        &principal,
+        req.account.as_deref(),
+        [],
+        [req.path.as_str()],
+    )?;
+    Ok(fs_response(
+        &state,
+        &account,
+        baidupcs::fs::rename(&account, &req.path, &req.newname).await,
+        "已重命名",
+    ))
+}
+
+/// 移动到目录（需要认证）
+pub async fn fs_move_handler(
+    State(shared): State<SharedState>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<FsMoveRequest>,
+) -> Result<Json<FsResponse>, Rejection> {
+    let state = shared.load();
+    let sources = req.paths.iter().map(String::as_str);
+    let account = fs_account(
+        &state,
+        &principal,
+        req.account.as_deref(),
+        [req.dest.as_str()],
+        sources,
+    )?;
+    Ok(fs_response(
+        &state,
+        &account,
+        baidupcs::fs::move_to(&account, &req.paths, &req.dest).await,
+        "已移动",
+    ))
+}
+
+/// 复制到目录（需要认证）
+pub async fn fs_copy_handler(
+    State(shared): State<SharedState>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<FsMoveRequest>,
+) -> Result<Json<FsResponse>, Rejection> {
+    let state = shared.load();
+    let sources = req.paths.iter().map(String::as_str);
+    let account = fs_account(
+        &state,
+        &principal,
+        req.account.as_deref(),
+        [req.dest.as_str()],
+        sources,
+    )?;
+    Ok(fs_response(
+        &state,
+        &account,
+        baidupcs::fs::copy_to(&account, &req.paths, &req.dest).await,
+        "已复制",
+    ))
+}
+
+/// 删除（需要认证）
+pub async fn fs_delete_handler(
+    State(shared): State<SharedState>,
+    Extension(principal): Extension<Principal>,
+    Json(req): Json<FsDeleteRequest>,
+) -> Result<Json<FsResponse>, Rejection> {
+    let state = shared.load();
+    let sources = req.paths.iter().map(String::as_str);
+    let account = fs_account(&state, &principal, req.account.as_deref(), [], sources)?;
+    Ok(fs_response(
+        &state,
+        &account,
+        baidupcs::fs::delete(&account, &req.paths).await,
+        "已删除",
+    ))
+}
+
 /// 验证分享链接格式
 pub fn validate_share_url(url: &str) -> Result<()> {
     if url.is_empty() {
@@ -334,13 +1538,61 @@ pub fn validate_password(pwd: &str) -> Result<()> {
 
 /// 创建 Web 路由
 pub fn create_router(state: Arc<AppState>) -> Router {
+    create_reloadable_router(SharedState::new(state))
+}
+
+/// 创建 Web 路由，状态可通过 [`crate::reload`] 热重载
+///
+/// 受保护的接口按所需权限分组，由 [`auth::require`] 统一认证（会话 Cookie 或 Bearer 令牌）。
+/// 所有路由经过 [`ratelimit::limit`] 限流和 [`csrf::protect`] 跨站请求检查。
+pub fn create_reloadable_router(state: SharedState) -> Router {
+    let require =
+        |scope: Scope| middleware::from_fn_with_state((state.clone(), scope), auth::require);
+
+    // 只读查询；转存接口在非 dry-run 时另外检查 transfer 权限
+    let preview = Router::new()
+        .route("/api/transfer", post(transfer_handler))
+        .route("/api/jobs", post(job_submit_handler))
+        .route("/api/jobs/:id", get(job_status_handler))
+        .route("/api/jobs/:id/events", get(job_events_handler))
+        .route("/api/quota", get(quota_handler))
+        .route("/api/account", get(account_handler))
+        .route("/api/accounts", get(accounts_handler))
+        .route("/api/me", get(me_handler))
+        .route_layer(require(Scope::Preview));
+    let fs = Router::new()
+        .route("/api/fs/list", get(fs_list_handler))
+        .route("/api/fs/tree", get(fs_tree_handler))
+        .route("/api/fs/mkdir", post(fs_mkdir_handler))
+        .route("/api/fs/rename", post(fs_rename_handler))
+        .route("/api/fs/move", post(fs_move_handler))
+        .route("/api/fs/copy", post(fs_copy_handler))
+        .route("/api/fs/delete", post(fs_delete_handler))
+        .route_layer(require(Scope::Fs));
+    let admin = Router::new()
+        .route("/api/users", get(users_handler).post(user_save_handler))
+        .route("/api/users/:name", delete(user_delete_handler))
+        .route("/api/sessions", get(sessions_handler))
+        .route("/api/sessions/:id", delete(session_revoke_handler))
+        .route("/api/tokens", get(tokens_handler))
+        .route("/api/qrlogin/start", post(qrlogin_start_handler))
+        .route("/api/qrlogin/poll", post(qrlogin_poll_handler))
+        .route_layer(require(Scope::Admin));
+
     Router::new()
         .route("/login", get(login_page_handler))
         .route("/api/login", post(login_handler))
         .route("/api/logout", post(logout_handler))
         .route("/", get(index_handler))
         .route("/health", get(health_handler))
-        .route("/api/transfer", post(transfer_handler))
+        .merge(preview)
+        .merge(fs)
+        .merge(admin)
+        .layer(middleware::from_fn_with_state(state.clone(), csrf::protect))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            ratelimit::limit,
+        ))
         .with_state(state)
 }
 
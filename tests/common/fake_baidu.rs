@@ -0,0 +1,540 @@
+//! 基于 axum 的百度网盘假服务器
+//!
+//! 覆盖转存流程用到的接口：`share/init`、`share/verify`、`share/list`、`share/transfer`、
+//! `api/list`、`api/filemanager`、`xpan/file?method=create`，以及启动检查用到的 `xpan/nas?method=uinfo`、
+//! `api/quota`、`api/gettemplatevariable`，以及扫码登录用到的 passport 接口。通过 [`Scenario`] 预设错误码，
+//! 驱动 CLI 与 Web 的端到端测试。
+
+#![allow(dead_code)]
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Json, Redirect},
+    routing::{get, post},
+    Form, Router,
+};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+pub const SHAREID: &str = "48392011234";
+pub const UK: &str = "1102938475612";
+pub const BDSTOKEN: &str = "0123456789abcdef0123456789abcdef";
+pub const SAVE_PATH: &str = "/我的资源";
+/// 符合长度校验的假 Cookie
+pub const BDUSS: &str = "fake-bduss-0123456789-0123456789-0123456789-0123456789-0123456789";
+pub const STOKEN: &str = "fake-stoken-0123456789-0123456789";
+/// 分享页下发的额外 Cookie
+pub const PANPSC: &str = "fake-panpsc";
+/// 扫码登录的二维码标识与确认后的临时凭证
+pub const QR_SIGN: &str = "fake-qr-sign";
+pub const QR_TOKEN: &str = "fake-qr-token";
+
+/// 预设场景
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scenario {
+    /// 一切正常
+    Ok,
+    /// 提取码错误（verify 返回 errno=-12）
+    WrongCode,
+    /// 分享已过期（list 返回 errno=-7）
+    ExpiredLink,
+    /// 目标已存在且未创建副本（transfer 返回 errno=2 且 newno 为空），重试到子目录成功
+    DuplicateNoCopy,
+    /// 部分成功（transfer 返回 errno=12）
+    PartialSuccess,
+    /// 保存路径不存在（transfer 返回 errno=-20）
+    PathMissing,
+    /// Cookie 失效（uinfo/quota/list 返回 errno=-6）
+    CookieExpired,
+}
+
+/// 一次转存调用
+#[derive(Debug, Clone)]
+pub struct TransferCall {
+    pub fsidlist: Vec<u64>,
+    pub path: String,
+    pub ondup: String,
+}
+
+#[derive(Debug)]
+struct Inner {
+    scenario: Scenario,
+    pwd: String,
+    files: Vec<(u64, String, u64)>,
+    /// 分享中的目录（share/list 返回 isdir=1、size=0），其中有一个子目录，子目录中有一个指定大小的文件
+    shared_dirs: Vec<(u64, String, u64)>,
+    dirs: HashSet<String>,
+    /// 网盘中已存在的文件（`api/list` 中 isdir=0）
+    drive_files: HashSet<String>,
+    /// 文件管理调用（操作, filelist）
+    filemanager_calls: Vec<(String, String)>,
+    transfers: Vec<TransferCall>,
+    /// 第几次转存调用（从 1 开始）返回失败
+    fail_transfer: Option<usize>,
+    /// 分享根目录忽略 page 参数，每页都返回同样的内容（模拟不会结束的分页）
+    endless_root_pages: bool,
+    /// 从第几次转存调用（从 1 开始）起返回 Cookie 失效（errno=-6）
+    expire_cookie_from: Option<usize>,
+    /// 从第几次转存调用（从 1 开始）起，转存到保存路径时返回已存在且未创建副本
+    duplicate_from: Option<usize>,
+    /// 检查目录是否存在（api/list）的次数
+    list_calls: usize,
+    created_dirs: Vec<String>,
+    qr_polls: u32,
+    /// 最近一次查询账号信息（uinfo）的请求头
+    uinfo_headers: Option<HeaderMap>,
+}
+
+#[derive(Clone)]
+struct Shared(Arc<Mutex<Inner>>);
+
+/// 运行中的假服务器
+pub struct FakeBaidu {
+    pub addr: SocketAddr,
+    shared: Shared,
+}
+
+impl FakeBaidu {
+    /// 在随机端口启动
+    pub async fn start(scenario: Scenario) -> Self {
+        let shared = Shared(Arc::new(Mutex::new(Inner {
+            scenario,
+            pwd: "abcd".to_string(),
+            files: vec![
+                (1001, "电影.mkv".to_string(), 1024 * 1024),
+                (1002, "字幕.srt".to_string(), 2048),
+            ],
+            shared_dirs: Vec::new(),
+            dirs: ["/".to_string(), SAVE_PATH.to_string()].into(),
+            drive_files: HashSet::new(),
+            filemanager_calls: Vec::new(),
+            transfers: Vec::new(),
+            fail_transfer: None,
+            expire_cookie_from: None,
+            endless_root_pages: false,
+            duplicate_from: None,
+            list_calls: 0,
+            created_dirs: Vec::new(),
+            qr_polls: 0,
+            uinfo_headers: None,
+        })));
+
+        let app = Router::new()
+            .route("/share/init", get(share_init))
+            .route("/share/verify", post(share_verify))
+            .route("/share/list", get(share_list))
+            .route("/share/transfer", post(share_transfer))
+            .route("/api/list", get(api_list))
+            .route("/api/filemanager", post(api_filemanager))
+            .route("/api/quota", get(api_quota))
+            .route("/api/gettemplatevariable", get(gettemplatevariable))
+            .route("/rest/2.0/xpan/file", post(xpan_file))
+            .route("/rest/2.0/xpan/nas", get(xpan_nas))
+            .route("/v2/api/getqrcode", get(getqrcode))
+            .route("/channel/unicast", get(unicast))
+            .route("/v3/login/main/qrbdusslogin", get(qrbdusslogin))
+            .route("/v3/login/api/auth/", get(passport_auth))
+            .route("/disk/home", get(disk_home))
+            .with_state(shared.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        Self { addr, shared }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// 分享的提取码
+    pub fn pwd(&self) -> String {
+        self.shared.0.lock().unwrap().pwd.clone()
+    }
+
+    /// 添加一个已存在的目录
+    pub fn add_dir(&self, path: &str) {
+        self.shared.0.lock().unwrap().dirs.insert(path.to_string());
+    }
+
+    /// 在网盘中添加一个已存在的文件
+    pub fn add_file(&self, path: &str) {
+        self.shared
+            .0
+            .lock()
+            .unwrap()
+            .drive_files
+            .insert(path.to_string());
+    }
+
+    /// 文件管理调用（操作, filelist）
+    pub fn filemanager_calls(&self) -> Vec<(String, String)> {
+        self.shared.0.lock().unwrap().filemanager_calls.clone()
+    }
+
+    /// 在分享中加入一个目录，目录中的子目录里有一个大小为 `size` 的文件
+    pub fn share_dir(&self, fs_id: u64, name: &str, size: u64) {
+        self.shared
+            .0
+            .lock()
+            .unwrap()
+            .shared_dirs
+            .push((fs_id, name.to_string(), size));
+    }
+
+    /// 用 `count` 个大小为 `size` 的文件替换分享中的文件
+    pub fn set_files(&self, count: u64, size: u64) {
+        self.shared.0.lock().unwrap().files = (0..count)
+            .map(|i| (10_000 + i, format!("文件{}.bin", i), size))
+            .collect();
+    }
+
+    /// 在分享根目录末尾再加入一个文件（`fs_id` 可以与已有文件重复，模拟跨页重复的条目）
+    pub fn add_share_file(&self, fs_id: u64, name: &str, size: u64) {
+        self.shared
+            .0
+            .lock()
+            .unwrap()
+            .files
+            .push((fs_id, name.to_string(), size));
+    }
+
+    /// 第 `call` 次转存调用（从 1 开始）返回 errno=-1
+    pub fn fail_transfer(&self, call: usize) {
+        self.shared.0.lock().unwrap().fail_transfer = Some(call);
+    }
+
+    /// 分享根目录的每一页都返回第一页的内容，文件数不少于一页时分页永远不会结束
+    pub fn endless_root_pages(&self) {
+        self.shared.0.lock().unwrap().endless_root_pages = true;
+    }
+
+    /// 从第 `call` 次转存调用（从 1 开始）起返回 errno=-6（Cookie 失效）
+    pub fn expire_cookie_from(&self, call: usize) {
+        self.shared.0.lock().unwrap().expire_cookie_from = Some(call);
+    }
+
+    /// 从第 `call` 次转存调用（从 1 开始）起，转存到保存路径时返回 errno=2 且 newno 为空
+    pub fn duplicate_from(&self, call: usize) {
+        self.shared.0.lock().unwrap().duplicate_from = Some(call);
+    }
+
+    /// 检查目录是否存在（api/list）的次数
+    pub fn list_calls(&self) -> usize {
+        self.shared.0.lock().unwrap().list_calls
+    }
+
+    pub fn transfers(&self) -> Vec<TransferCall> {
+        self.shared.0.lock().unwrap().transfers.clone()
+    }
+
+    pub fn created_dirs(&self) -> Vec<String> {
+        self.shared.0.lock().unwrap().created_dirs.clone()
+    }
+
+    /// 最近一次 uinfo 请求的请求头
+    pub fn uinfo_headers(&self) -> Option<HeaderMap> {
+        self.shared.0.lock().unwrap().uinfo_headers.clone()
+    }
+}
+
+fn has_cookie(headers: &HeaderMap) -> bool {
+    headers
+        .get_all("cookie")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .any(|v| v.contains(&format!("BDUSS={}", BDUSS)))
+}
+
+/// 分享页，同时像百度一样下发一个额外的 Cookie（PANPSC）
+async fn share_init() -> impl IntoResponse {
+    (
+        [(header::SET_COOKIE, format!("PANPSC={}; Path=/", PANPSC))],
+        Html(format!(
+            r#"<html><script>locals.mset({{"shareid":{},"uk":"{}","bdstoken":"{}"}});</script></html>"#,
+            SHAREID, UK, BDSTOKEN
+        )),
+    )
+}
+
+async fn share_verify(
+    State(shared): State<Shared>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Json<Value> {
+    let inner = shared.0.lock().unwrap();
+    let pwd = form.get("pwd").cloned().unwrap_or_default();
+    if inner.scenario == Scenario::WrongCode || pwd != inner.pwd {
+        return Json(json!({ "errno": -12, "err_msg": "", "request_id": 1 }));
+    }
+    Json(json!({ "errno": 0, "err_msg": "", "request_id": 1 }))
+}
+
+async fn share_list(
+    State(shared): State<Shared>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Json<Value> {
+    let inner = shared.0.lock().unwrap();
+    if inner.scenario == Scenario::ExpiredLink {
+        return Json(json!({ "errno": -7 }));
+    }
+    let dir = query.get("dir").map(String::as_str).unwrap_or("/");
+    if dir != "/" {
+        // 目录 → 子目录 → 文件
+        for (fs_id, name, size) in &inner.shared_dirs {
+            let path = format!("/{}", name);
+            let sub = format!("{}/子目录", path);
+            if dir == path {
+                return Json(json!({ "errno": 0, "list": [{
+                    "fs_id": fs_id + 1, "server_filename": "子目录", "path": sub, "size": 0, "isdir": 1,
+                }] }));
+            }
+            if dir == sub {
+                return Json(json!({ "errno": 0, "list": [{
+                    "fs_id": fs_id + 2, "server_filename": "文件.bin",
+                    "path": format!("{}/文件.bin", sub), "size": size, "isdir": 0,
+                }] }));
+            }
+        }
+        return Json(json!({ "errno": 0, "list": [] }));
+    }
+    // 根目录按 page/num 分页
+    let page: usize = if inner.endless_root_pages {
+        1
+    } else {
+        query.get("page").and_then(|p| p.parse().ok()).unwrap_or(1)
+    };
+    let num: usize = query.get("num").and_then(|n| n.parse().ok()).unwrap_or(100);
+    let list: Vec<Value> = inner
+        .files
+        .iter()
+        .map(|(fs_id, name, size)| {
+            json!({ "fs_id": fs_id.to_string(), "server_filename": name, "size": size, "isdir": 0 })
+        })
+        .chain(inner.shared_dirs.iter().map(|(fs_id, name, _)| {
+            json!({
+                "fs_id": fs_id.to_string(), "server_filename": name,
+                "path": format!("/{}", name), "size": 0, "isdir": 1,
+            })
+        }))
+        .skip((page.max(1) - 1) * num)
+        .take(num)
+        .collect();
+    Json(json!({ "errno": 0, "list": list }))
+}
+
+async fn share_transfer(
+    State(shared): State<Shared>,
+    Query(query): Query<HashMap<String, String>>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Json<Value> {
+    let mut inner = shared.0.lock().unwrap();
+    let path = form.get("path").cloned().unwrap_or_default();
+    let fsidlist = form
+        .get("fsidlist")
+        .and_then(|s| serde_json::from_str(s).ok())
+        .unwrap_or_default();
+    inner.transfers.push(TransferCall {
+        fsidlist,
+        path: path.clone(),
+        ondup: query.get("ondup").cloned().unwrap_or_default(),
+    });
+    if inner
+        .expire_cookie_from
+        .is_some_and(|call| inner.transfers.len() >= call)
+    {
+        return Json(json!({ "errno": -6, "show_msg": "" }));
+    }
+    if inner.fail_transfer == Some(inner.transfers.len()) {
+        return Json(json!({ "errno": -1, "show_msg": "" }));
+    }
+    let duplicate = inner.scenario == Scenario::DuplicateNoCopy
+        || inner
+            .duplicate_from
+            .is_some_and(|call| inner.transfers.len() >= call);
+
+    match inner.scenario {
+        _ if duplicate && path == SAVE_PATH => Json(json!({
+            "errno": 2,
+            "show_msg": "文件已经保存过了",
+            "newno": "",
+            "request_id": 7
+        })),
+        Scenario::PartialSuccess => Json(json!({ "errno": 12, "show_msg": "部分文件已存在" })),
+        Scenario::PathMissing => Json(json!({ "errno": -20, "show_msg": "" })),
+        _ => Json(json!({ "errno": 0, "show_msg": "", "request_id": 1 })),
+    }
+}
+
+async fn api_list(
+    State(shared): State<Shared>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Json<Value> {
+    let mut inner = shared.0.lock().unwrap();
+    inner.list_calls += 1;
+    let dir = query.get("dir").cloned().unwrap_or_default();
+    if inner.scenario == Scenario::CookieExpired {
+        Json(json!({ "errno": -6, "list": [] }))
+    } else if inner.dirs.contains(&dir) {
+        // 列出已存在目录中的直接子目录和文件
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        let is_child = |p: &&String| {
+            p.strip_prefix(&prefix)
+                .is_some_and(|rest| !rest.is_empty() && !rest.contains('/'))
+        };
+        let mut children: Vec<(&String, bool)> = inner
+            .dirs
+            .iter()
+            .filter(is_child)
+            .map(|p| (p, true))
+            .chain(
+                inner
+                    .drive_files
+                    .iter()
+                    .filter(is_child)
+                    .map(|p| (p, false)),
+            )
+            .collect();
+        children.sort();
+        let list: Vec<Value> = children
+            .into_iter()
+            .enumerate()
+            .map(|(i, (path, isdir))| {
+                json!({
+                    "fs_id": 5000 + i, "path": path, "isdir": isdir as u8, "size": 0,
+                    "server_filename": path.rsplit('/').next().unwrap(),
+                })
+            })
+            .collect();
+        Json(json!({ "errno": 0, "list": list }))
+    } else if inner.drive_files.contains(&dir) {
+        // 对文件路径列目录同样成功，只是没有内容
+        Json(json!({ "errno": 0, "list": [] }))
+    } else {
+        Json(json!({ "errno": -9, "list": [] }))
+    }
+}
+
+async fn api_filemanager(
+    State(shared): State<Shared>,
+    Query(query): Query<HashMap<String, String>>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Json<Value> {
+    let mut inner = shared.0.lock().unwrap();
+    inner.filemanager_calls.push((
+        query.get("opera").cloned().unwrap_or_default(),
+        form.get("filelist").cloned().unwrap_or_default(),
+    ));
+    Json(json!({ "errno": 0, "info": [] }))
+}
+
+async fn api_quota(State(shared): State<Shared>, headers: HeaderMap) -> Json<Value> {
+    let inner = shared.0.lock().unwrap();
+    if inner.scenario == Scenario::CookieExpired || !has_cookie(&headers) {
+        return Json(json!({ "errno": -6 }));
+    }
+    Json(
+        json!({ "errno": 0, "total": 2u64 << 40, "used": 1u64 << 30, "free": (2u64 << 40) - (1u64 << 30) }),
+    )
+}
+
+async fn gettemplatevariable(State(shared): State<Shared>, headers: HeaderMap) -> Json<Value> {
+    let inner = shared.0.lock().unwrap();
+    if inner.scenario == Scenario::CookieExpired || !has_cookie(&headers) {
+        return Json(json!({ "errno": -6 }));
+    }
+    Json(json!({ "errno": 0, "result": { "bdstoken": BDSTOKEN } }))
+}
+
+async fn xpan_file(
+    State(shared): State<Shared>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Json<Value> {
+    let mut inner = shared.0.lock().unwrap();
+    if query.get("method").map(|m| m.as_str()) != Some("create") {
+        return Json(json!({ "errno": 2 }));
+    }
+    let path = query.get("path").cloned().unwrap_or_default();
+    inner.dirs.insert(path.clone());
+    inner.created_dirs.push(path);
+    Json(json!({ "errno": 0 }))
+}
+
+async fn xpan_nas(State(shared): State<Shared>, headers: HeaderMap) -> Json<Value> {
+    let mut inner = shared.0.lock().unwrap();
+    inner.uinfo_headers = Some(headers.clone());
+    if inner.scenario == Scenario::CookieExpired || !has_cookie(&headers) {
+        return Json(json!({ "errno": -6 }));
+    }
+    Json(json!({
+        "errno": 0,
+        "baidu_name": "fake_user",
+        "netdisk_name": "fake_user",
+        "uk": 1234567,
+        "vip_type": 2
+    }))
+}
+
+async fn getqrcode() -> Json<Value> {
+    Json(
+        json!({ "errno": 0, "sign": QR_SIGN, "imgurl": "passport.baidu.com/v2/api/qrcode?sign=fake" }),
+    )
+}
+
+/// 第一次轮询无变化，第二次已扫码，第三次已确认
+async fn unicast(
+    State(shared): State<Shared>,
+    Query(q): Query<HashMap<String, String>>,
+) -> Json<Value> {
+    if q.get("channel_id").map(String::as_str) != Some(QR_SIGN) {
+        return Json(json!({ "errno": 2 }));
+    }
+    let mut inner = shared.0.lock().unwrap();
+    inner.qr_polls += 1;
+    match inner.qr_polls {
+        1 => Json(json!({ "errno": 1 })),
+        2 => Json(json!({ "errno": 0, "channel_v": r#"{"status":1}"# })),
+        _ => Json(json!({
+            "errno": 0,
+            "channel_v": json!({ "status": 0, "v": QR_TOKEN }).to_string()
+        })),
+    }
+}
+
+async fn qrbdusslogin(Query(q): Query<HashMap<String, String>>) -> impl IntoResponse {
+    if q.get("bduss").map(String::as_str) != Some(QR_TOKEN) {
+        return Json(json!({ "errInfo": { "no": "1" } })).into_response();
+    }
+    (
+        [(header::SET_COOKIE, format!("BDUSS={}; Path=/", BDUSS))],
+        Json(json!({ "errInfo": { "no": "0" } })),
+    )
+        .into_response()
+}
+
+/// passport 授权后跳转到网盘
+async fn passport_auth(
+    headers: HeaderMap,
+    Query(q): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if !has_cookie(&headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    Redirect::to(q.get("u").map(String::as_str).unwrap_or("/disk/home")).into_response()
+}
+
+async fn disk_home(headers: HeaderMap) -> impl IntoResponse {
+    if !has_cookie(&headers) {
+        return Html("login").into_response();
+    }
+    (
+        [(header::SET_COOKIE, format!("STOKEN={}; Path=/", STOKEN))],
+        Html("home"),
+    )
+        .into_response()
+}
@@ -0,0 +1,3 @@
+//! 测试辅助
+
+pub mod fake_baidu;
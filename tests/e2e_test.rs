@@ -0,0 +1,2208 @@
+//! 基于假服务器的端到端测试：库、Web 和 CLI 的完整转存流程
+
+mod common;
+
+use baidu_direct_link::{baidupcs, server, web, AppState, Config};
+use common::fake_baidu::{FakeBaidu, Scenario, BDUSS, SAVE_PATH, STOKEN};
+use std::sync::Arc;
+
+const SHARE_URL: &str = "https://pan.baidu.com/s/1fakeShare";
+
+fn config_for(fake: &FakeBaidu) -> Config {
+    toml::from_str(&format!(
+        r#"
+        [baidu]
+        cookie_bduss = "{}"
+        cookie_stoken = "{}"
+        save_path = "{}"
+        http_timeout_secs = 5
+
+        [baidu.endpoints]
+        pan = "{url}"
+        passport = "{url}"
+        pcs = "{url}"
+
+        [storage]
+        persist_cookies = false
+        "#,
+        BDUSS,
+        STOKEN,
+        SAVE_PATH,
+        url = fake.url()
+    ))
+    .unwrap()
+}
+
+fn state_for(fake: &FakeBaidu) -> AppState {
+    AppState::new(config_for(fake)).unwrap()
+}
+
+async fn run(
+    fake: &FakeBaidu,
+    pwd: &str,
+    dry_run: bool,
+) -> anyhow::Result<baidupcs::TransferOutcome> {
+    baidupcs::run_transfer(
+        &state_for(fake).default_account(),
+        SHARE_URL,
+        pwd,
+        baidupcs::TransferOptions {
+            dry_run,
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_transfer_ok() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let outcome = run(&fake, &fake.pwd(), false).await.unwrap();
+
+    assert!(!outcome.dry_run);
+    let transfers = fake.transfers();
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(transfers[0].fsidlist, vec![1001, 1002]);
+    assert_eq!(transfers[0].path, SAVE_PATH);
+    assert_eq!(transfers[0].ondup, "newcopy");
+}
+
+#[tokio::test]
+async fn test_dry_run_does_not_write() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let outcome = run(&fake, &fake.pwd(), true).await.unwrap();
+
+    assert!(outcome.dry_run);
+    assert_eq!(outcome.plan.fs_ids, vec![1001, 1002]);
+    assert_eq!(outcome.plan.save_path, SAVE_PATH);
+    assert!(outcome.plan.save_path_exists);
+    assert!(fake.transfers().is_empty());
+    assert!(fake.created_dirs().is_empty());
+}
+
+#[tokio::test]
+async fn test_plan_total_size_counts_duplicates_once() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    fake.add_share_file(1001, "电影.mkv", 1024 * 1024);
+    let outcome = run(&fake, &fake.pwd(), true).await.unwrap();
+
+    assert_eq!(outcome.plan.fs_ids, vec![1001, 1002]);
+    assert_eq!(outcome.plan.total_size, 1024 * 1024 + 2048);
+}
+
+#[tokio::test]
+async fn test_wrong_code() {
+    let fake = FakeBaidu::start(Scenario::WrongCode).await;
+    let err = run(&fake, "zzzz", false).await.unwrap_err();
+
+    assert!(err.to_string().contains("errno=-12"), "{}", err);
+    assert!(fake.transfers().is_empty());
+}
+
+#[tokio::test]
+async fn test_expired_link() {
+    let fake = FakeBaidu::start(Scenario::ExpiredLink).await;
+    let err = run(&fake, &fake.pwd(), false).await.unwrap_err();
+
+    assert!(err.to_string().contains("errno=-7"), "{}", err);
+    assert!(fake.transfers().is_empty());
+}
+
+#[tokio::test]
+async fn test_duplicate_without_copy_retries_in_subdir() {
+    let fake = FakeBaidu::start(Scenario::DuplicateNoCopy).await;
+    run(&fake, &fake.pwd(), false).await.unwrap();
+
+    let created = fake.created_dirs();
+    assert_eq!(created.len(), 1);
+    assert!(created[0].starts_with(&format!("{}/copy-", SAVE_PATH)));
+
+    let transfers = fake.transfers();
+    assert_eq!(transfers.len(), 2);
+    assert_eq!(transfers[1].path, created[0]);
+}
+
+#[tokio::test]
+async fn test_duplicate_in_second_chunk_reuses_one_copy_dir() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    // 1200 个文件分三批，第二批起保存路径中已有相同文件
+    fake.set_files(1200, 1);
+    fake.duplicate_from(2);
+    run(&fake, &fake.pwd(), false).await.unwrap();
+
+    // 保存路径只在生成计划时检查一次，副本目录只创建一次，第三批直接转存到副本目录
+    assert_eq!(fake.list_calls(), 1);
+    let created = fake.created_dirs();
+    assert_eq!(created.len(), 1);
+    let paths: Vec<_> = fake.transfers().into_iter().map(|t| t.path).collect();
+    assert_eq!(
+        paths,
+        [SAVE_PATH, SAVE_PATH, &created[0], &created[0]].map(String::from)
+    );
+}
+
+#[tokio::test]
+async fn test_share_root_listing_is_paginated() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    // 根目录超过一页（1000 条），大小估算和转存都包含第二页的文件
+    fake.set_files(1500, 10);
+    let outcome = run(&fake, &fake.pwd(), true).await.unwrap();
+
+    assert_eq!(outcome.plan.fs_ids.len(), 1500);
+    assert_eq!(outcome.plan.total_size, 15_000);
+    assert!(outcome.plan.size_known);
+}
+
+#[tokio::test]
+async fn test_share_root_listing_pages_are_capped() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    // 每页都是满的 1000 条，翻页达到上限后报错而不是一直请求下去
+    fake.set_files(1000, 1);
+    fake.endless_root_pages();
+    let err = run(&fake, &fake.pwd(), false).await.unwrap_err();
+
+    assert!(err.to_string().contains("文件过多"), "{}", err);
+    assert!(fake.transfers().is_empty());
+}
+
+#[tokio::test]
+async fn test_partial_success_errno_12() {
+    let fake = FakeBaidu::start(Scenario::PartialSuccess).await;
+    run(&fake, &fake.pwd(), false).await.unwrap();
+    assert_eq!(fake.transfers().len(), 1);
+}
+
+#[tokio::test]
+async fn test_path_missing_errno_20() {
+    let fake = FakeBaidu::start(Scenario::PathMissing).await;
+    let err = run(&fake, &fake.pwd(), false).await.unwrap_err();
+    assert!(err.to_string().contains("路径不存在"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_whoami_cookie_expired() {
+    let fake = FakeBaidu::start(Scenario::CookieExpired).await;
+    let err = baidupcs::whoami(&state_for(&fake).default_account())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<baidupcs::BaiduError>(),
+        Some(baidupcs::BaiduError::CookieInvalid { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_fs_cookie_expired_marks_account_unhealthy() {
+    let fake = FakeBaidu::start(Scenario::CookieExpired).await;
+    let state = Arc::new(state_for(&fake));
+    let err = baidupcs::fs::list_dir(&state.default_account(), "/")
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<baidupcs::BaiduError>(),
+        Some(baidupcs::BaiduError::CookieInvalid { .. })
+    ));
+
+    // 文件管理遇到 Cookie 失效时与转存一样把账号标记为不可用
+    let base = start_web_with_shared(state.clone()).await;
+    let res: serde_json::Value = reqwest::get(format!("{}/api/fs/list?dir=/", base))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(res["success"], false, "{}", res);
+    assert!(!state.pool.status()[0].healthy);
+}
+
+#[tokio::test]
+async fn test_web_fs_recursive_listing_is_limited() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let mut deep = String::from("/深");
+    fake.add_dir(&deep);
+    for i in 1..=12 {
+        deep = format!("{}/{}", deep, i);
+        fake.add_dir(&deep);
+    }
+    fake.add_dir("/宽");
+    for i in 0..30 {
+        fake.add_dir(&format!("/宽/{:02}", i));
+    }
+    let mut config = config_for(&fake);
+    config.web.fs_max_depth = 3;
+    config.web.fs_max_entries = 10;
+    let base = start_web_with(AppState::new(config).unwrap()).await;
+    let get = |path: &str| {
+        let url = format!("{}{}", base, path);
+        async move {
+            let res: serde_json::Value = reqwest::get(url).await.unwrap().json().await.unwrap();
+            assert_eq!(res["success"], true, "{}", res);
+            res
+        }
+    };
+
+    // 深度超过上限时只展开 3 层（请求的更大深度也不例外）
+    let res = get("/api/fs/tree?dir=/深&depth=100").await;
+    assert_eq!(res["truncated"], true, "{}", res);
+    let level3 = &res["data"][0]["children"][0]["children"][0];
+    assert_eq!(level3["path"], "/深/1/2/3", "{}", res);
+    assert!(level3.get("children").is_none(), "{}", res);
+
+    // 条目数达到上限时停止列目录
+    let res = get("/api/fs/list?dir=/宽&recursive=true").await;
+    assert_eq!(res["truncated"], true, "{}", res);
+    assert_eq!(res["data"].as_array().unwrap().len(), 10);
+
+    // 没有达到上限时不带 truncated；非递归列目录不受条目数限制
+    let res = get("/api/fs/list?dir=/深/1/2/3/4/5/6/7/8/9/10&recursive=true").await;
+    assert!(res.get("truncated").is_none(), "{}", res);
+    assert_eq!(res["data"].as_array().unwrap().len(), 2);
+    let res = get("/api/fs/list?dir=/宽").await;
+    assert_eq!(res["data"].as_array().unwrap().len(), 30);
+}
+
+#[tokio::test]
+async fn test_pool_fails_over_to_next_account() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    fake.add_dir("/备用");
+
+    // 第一个账号 Cookie 已失效（假服务器不认），应自动切换到第二个账号
+    let mut config = config_for(&fake);
+    config.baidu.cookie_bduss = "x".repeat(BDUSS.len());
+    config.accounts = vec![baidu_direct_link::config::AccountConfig {
+        name: "backup".to_string(),
+        cookie_bduss: BDUSS.to_string(),
+        cookie_stoken: STOKEN.to_string(),
+        import_cookies: None,
+        save_path: Some("/备用".to_string()),
+        profile: None,
+    }];
+    let state = AppState::new(config).unwrap();
+
+    let outcome = state
+        .pool
+        .run_transfer(SHARE_URL, &fake.pwd(), baidupcs::TransferOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(outcome.account, "backup");
+    assert_eq!(fake.transfers().len(), 1);
+    assert_eq!(fake.transfers()[0].path, "/备用");
+
+    let status = state.pool.status();
+    assert!(!status[0].healthy);
+    assert!(status[1].healthy);
+
+    // 失效账号在冷却期内不再被选中
+    let candidates = state.pool.candidates().await;
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].name, "backup");
+}
+
+#[tokio::test]
+async fn test_pool_does_not_fail_over_after_chunks_were_written() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    fake.add_dir("/备用");
+    // 1200 个文件分三批，第二批时第一个账号的 Cookie 失效
+    fake.set_files(1200, 1);
+    fake.expire_cookie_from(2);
+
+    let mut config = config_for(&fake);
+    config.accounts = vec![baidu_direct_link::config::AccountConfig {
+        name: "backup".to_string(),
+        cookie_bduss: BDUSS.to_string(),
+        cookie_stoken: STOKEN.to_string(),
+        import_cookies: None,
+        save_path: Some("/备用".to_string()),
+        profile: None,
+    }];
+    let state = AppState::new(config).unwrap();
+
+    let err = state
+        .pool
+        .run_transfer(SHARE_URL, &fake.pwd(), baidupcs::TransferOptions::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<baidupcs::BaiduError>(),
+        Some(baidupcs::BaiduError::PartialTransfer {
+            chunks: 1,
+            total: 3,
+            files: 500,
+            ..
+        })
+    ));
+    assert!(err.to_string().contains("1/3"), "{}", err);
+
+    // 已写入第一批后不再换账号从头转存，账号仍被标记为不可用
+    let paths: Vec<_> = fake.transfers().into_iter().map(|t| t.path).collect();
+    assert_eq!(paths, [SAVE_PATH, SAVE_PATH]);
+    assert!(!state.pool.status()[0].healthy);
+}
+
+/// 启动 Web 服务并返回其地址
+async fn start_web(fake: &FakeBaidu) -> String {
+    start_web_with(state_for(fake)).await
+}
+
+async fn start_web_with(state: AppState) -> String {
+    start_web_with_shared(Arc::new(state)).await
+}
+
+async fn start_web_with_shared(state: Arc<AppState>) -> String {
+    let app = web::create_router(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(server::serve(
+        server::Listener::Tcp(listener),
+        app,
+        std::future::pending(),
+    ));
+    format!("http://{}", addr)
+}
+
+/// Web 登录请求，`username` 为空时使用 `[web]` 密码
+fn login_request(base: &str, username: &str, password: &str) -> reqwest::RequestBuilder {
+    reqwest::Client::new()
+        .post(format!("{}/api/login", base))
+        .json(&serde_json::json!({ "username": username, "password": password }))
+}
+
+/// 登录响应的响应体和会话（Cookie, CSRF 令牌），登录失败时会话为空
+async fn session_of(resp: reqwest::Response) -> (serde_json::Value, (String, String)) {
+    let cookie = resp
+        .headers()
+        .get("set-cookie")
+        .map(|c| c.to_str().unwrap().split(';').next().unwrap().to_string());
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let csrf = body["csrf_token"].as_str().unwrap_or_default().to_string();
+    (body, (cookie.unwrap_or_default(), csrf))
+}
+
+async fn try_login(
+    base: &str,
+    username: &str,
+    password: &str,
+) -> (serde_json::Value, (String, String)) {
+    session_of(
+        login_request(base, username, password)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await
+}
+
+/// 登录并返回会话（Cookie, CSRF 令牌）
+async fn login(base: &str, username: &str, password: &str) -> (String, String) {
+    let (body, session) = try_login(base, username, password).await;
+    assert_eq!(body["success"], true, "{}", body);
+    session
+}
+
+/// 带会话 Cookie 和 CSRF 令牌的请求
+fn session_request(
+    method: reqwest::Method,
+    base: &str,
+    path: &str,
+    (cookie, csrf): &(String, String),
+) -> reqwest::RequestBuilder {
+    reqwest::Client::new()
+        .request(method, format!("{}{}", base, path))
+        .header("Cookie", cookie)
+        .header("X-CSRF-Token", csrf)
+}
+
+/// 以会话身份调用接口（GET 不带请求体），返回状态码和响应体
+async fn session_call(
+    base: &str,
+    method: reqwest::Method,
+    path: &str,
+    session: &(String, String),
+    body: serde_json::Value,
+) -> (reqwest::StatusCode, serde_json::Value) {
+    let req = session_request(method.clone(), base, path, session);
+    let resp = if method == reqwest::Method::GET {
+        req.send().await.unwrap()
+    } else {
+        req.json(&body).send().await.unwrap()
+    };
+    let status = resp.status();
+    (status, resp.json().await.unwrap())
+}
+
+/// 以会话身份 POST JSON，返回响应体
+async fn post_json(
+    base: &str,
+    session: &(String, String),
+    path: &str,
+    body: serde_json::Value,
+) -> serde_json::Value {
+    session_call(base, reqwest::Method::POST, path, session, body)
+        .await
+        .1
+}
+
+/// 以会话身份 GET，返回响应体
+async fn get_json(base: &str, session: &(String, String), path: &str) -> serde_json::Value {
+    session_call(
+        base,
+        reqwest::Method::GET,
+        path,
+        session,
+        Default::default(),
+    )
+    .await
+    .1
+}
+
+async fn post_transfer(base: &str, body: serde_json::Value) -> serde_json::Value {
+    reqwest::Client::new()
+        .post(format!("{}/api/transfer", base))
+        .json(&body)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_web_transfer_ok() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let base = start_web(&fake).await;
+
+    let res = post_transfer(
+        &base,
+        serde_json::json!({ "share_url": SHARE_URL, "pwd": fake.pwd() }),
+    )
+    .await;
+
+    assert_eq!(res["success"], true, "{}", res);
+    assert_eq!(res["file_count"], 2);
+    assert_eq!(fake.transfers().len(), 1);
+}
+
+#[tokio::test]
+async fn test_web_dry_run_returns_plan() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let base = start_web(&fake).await;
+
+    let res = post_transfer(
+        &base,
+        serde_json::json!({ "share_url": SHARE_URL, "pwd": fake.pwd(), "dry_run": true }),
+    )
+    .await;
+
+    assert_eq!(res["success"], true, "{}", res);
+    assert_eq!(res["plan"]["fs_ids"], serde_json::json!([1001, 1002]));
+    assert!(fake.transfers().is_empty());
+}
+
+#[tokio::test]
+async fn test_web_wrong_code() {
+    let fake = FakeBaidu::start(Scenario::WrongCode).await;
+    let base = start_web(&fake).await;
+
+    let res = post_transfer(
+        &base,
+        serde_json::json!({ "share_url": SHARE_URL, "pwd": "zzzz" }),
+    )
+    .await;
+
+    assert_eq!(res["success"], false);
+    assert!(
+        res["message"].as_str().unwrap().contains("提取码"),
+        "{}",
+        res
+    );
+}
+
+#[tokio::test]
+async fn test_web_transfer_with_named_account() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    fake.add_dir("/团队");
+
+    let mut config = config_for(&fake);
+    config.accounts = toml::from_str::<toml::Value>(&format!(
+        r#"
+        [[accounts]]
+        name = "team"
+        cookie_bduss = "{}"
+        cookie_stoken = "{}"
+        save_path = "/团队"
+        "#,
+        BDUSS, STOKEN
+    ))
+    .unwrap()["accounts"]
+        .clone()
+        .try_into()
+        .unwrap();
+    let base = start_web_with(AppState::new(config).unwrap()).await;
+
+    let res = post_transfer(
+        &base,
+        serde_json::json!({ "share_url": SHARE_URL, "pwd": fake.pwd(), "account": "team" }),
+    )
+    .await;
+    assert_eq!(res["success"], true, "{}", res);
+    assert_eq!(fake.transfers()[0].path, "/团队");
+
+    let res = post_transfer(
+        &base,
+        serde_json::json!({ "share_url": SHARE_URL, "pwd": fake.pwd(), "account": "nobody" }),
+    )
+    .await;
+    assert_eq!(res["success"], false);
+    assert_eq!(fake.transfers().len(), 1);
+}
+
+/// 以假服务器为后端运行 CLI
+async fn run_cli(fake: &FakeBaidu, args: &[&str]) -> std::process::Output {
+    let url = fake.url();
+    let data_dir = std::env::temp_dir().join(format!("bdl-cli-{}", fake.addr.port()));
+    let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+    tokio::task::spawn_blocking(move || {
+        std::process::Command::new(env!("CARGO_BIN_EXE_baidu-direct-link"))
+            .args(&args)
+            .env_clear()
+            .env("CONFIG_PATH", "/nonexistent/config.toml")
+            .env("RUST_LOG", "warn")
+            .env("BDUSS", BDUSS)
+            .env("STOKEN", STOKEN)
+            .env("BAIDU_PAN_URL", &url)
+            .env("BAIDU_PASSPORT_URL", &url)
+            .env("BAIDU_PCS_URL", &url)
+            .env("DATA_DIR", &data_dir)
+            .output()
+            .unwrap()
+    })
+    .await
+    .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cli_transfer_ok() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let output = run_cli(&fake, &[SHARE_URL, &fake.pwd()]).await;
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(fake.transfers().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cli_dry_run() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let output = run_cli(&fake, &["--dry-run", SHARE_URL, &fake.pwd()]).await;
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("1001"), "{}", stdout);
+    assert!(fake.transfers().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cli_fails_fast_on_dead_cookie() {
+    let fake = FakeBaidu::start(Scenario::CookieExpired).await;
+    let output = run_cli(&fake, &[SHARE_URL, &fake.pwd()]).await;
+
+    assert!(!output.status.success());
+    assert!(fake.transfers().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cli_mv_cp_refuse_existing_file_as_destination() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    fake.add_dir("/我的资源/目录");
+    fake.add_file("/我的资源/a.txt");
+    fake.add_file("/我的资源/b.txt");
+
+    for command in ["mv", "cp"] {
+        let output = run_cli(&fake, &[command, "/我的资源/a.txt", "/我的资源/b.txt"]).await;
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("不是目录"), "{}", stderr);
+    }
+    assert!(fake.filemanager_calls().is_empty());
+
+    // 已存在的目录仍然作为目标目录
+    let output = run_cli(&fake, &["mv", "/我的资源/a.txt", "/我的资源/目录"]).await;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let calls = fake.filemanager_calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].0, "move");
+    assert!(
+        calls[0].1.contains("\"dest\":\"/我的资源/目录\""),
+        "{}",
+        calls[0].1
+    );
+}
+
+#[tokio::test]
+async fn test_qr_login_saves_credentials() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let config = config_for(&fake);
+    let login = baidupcs::QrLogin::new(&config.baidu).unwrap();
+
+    let qr = login.start().await.unwrap();
+    assert_eq!(qr.sign, common::fake_baidu::QR_SIGN);
+    assert!(qr.content.contains(&qr.sign));
+
+    let mut statuses = Vec::new();
+    let token = loop {
+        match login.poll(&qr.sign).await.unwrap() {
+            baidupcs::QrStatus::Confirmed { token } => break token,
+            status => statuses.push(status),
+        }
+    };
+    assert_eq!(
+        statuses,
+        vec![baidupcs::QrStatus::Waiting, baidupcs::QrStatus::Scanned]
+    );
+
+    let credentials = login.finish(&token).await.unwrap();
+    assert_eq!(credentials.bduss, BDUSS);
+    assert_eq!(credentials.stoken, STOKEN);
+
+    // 写入新账号后，配置文件可以直接加载并使用该账号
+    let path = std::env::temp_dir().join(format!("qr-login-{}.toml", std::process::id()));
+    std::fs::write(&path, "# 注释保留\n[baidu]\nsave_path = \"/我的资源\"\n").unwrap();
+    Config::save_credentials(
+        &path,
+        Some("phone"),
+        &credentials.bduss,
+        &credentials.stoken,
+    )
+    .unwrap();
+    let content = std::fs::read_to_string(&path).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    assert!(!path.with_extension("toml.tmp").exists());
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(content.starts_with("# 注释保留"));
+    let saved: Config = toml::from_str(&content).unwrap();
+    let accounts = saved.account_configs().unwrap();
+    assert_eq!(accounts.last().unwrap().name, "phone");
+    assert_eq!(accounts.last().unwrap().cookie_bduss, BDUSS);
+}
+
+#[tokio::test]
+async fn test_cookie_jar_persists_across_restarts() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let data_dir = std::env::temp_dir().join(format!("bdl-cookies-{}", fake.addr.port()));
+    let config_with = |bduss: &str| {
+        let mut config = config_for(&fake);
+        config.baidu.cookie_bduss = bduss.to_string();
+        config.storage.persist_cookies = true;
+        config.storage.data_dir = data_dir.clone();
+        config
+    };
+
+    let state = AppState::new(config_with(BDUSS)).unwrap();
+    baidupcs::run_transfer(
+        &state.default_account(),
+        SHARE_URL,
+        &fake.pwd(),
+        baidupcs::TransferOptions::default(),
+    )
+    .await
+    .unwrap();
+    state.save_cookies();
+    assert!(data_dir.join("cookies/default.json").exists());
+
+    // 重启：百度下发的 Cookie 被恢复，配置中的 BDUSS 优先于文件中保存的旧值
+    let new_bduss = "n".repeat(BDUSS.len());
+    let restarted = AppState::new(config_with(&new_bduss)).unwrap();
+    let account = restarted.default_account();
+    assert_eq!(
+        account.cookie("PANPSC").as_deref(),
+        Some(common::fake_baidu::PANPSC)
+    );
+    assert_eq!(account.cookie("BDUSS"), Some(new_bduss));
+
+    std::fs::remove_dir_all(&data_dir).unwrap();
+}
+
+#[test]
+fn test_cli_import_cookies() {
+    let dir = std::env::temp_dir().join(format!("bdl-import-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let cookies_txt = dir.join("cookies.txt");
+    std::fs::write(
+        &cookies_txt,
+        format!(
+            "# Netscape HTTP Cookie File\n\
+             .baidu.com\tTRUE\t/\tFALSE\t0\tBDUSS\t{}\n\
+             pan.baidu.com\tFALSE\t/\tFALSE\t0\tSTOKEN\t{}\n\
+             .baidu.com\tTRUE\t/\tFALSE\t0\tBAIDUID\tfake-baiduid\n",
+            BDUSS, STOKEN
+        ),
+    )
+    .unwrap();
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_baidu-direct-link"))
+        .args(["--account", "browser", "import-cookies"])
+        .arg(&cookies_txt)
+        .env_clear()
+        .env("CONFIG_PATH", dir.join("config.toml"))
+        .env("DATA_DIR", dir.join("data"))
+        .env("RUST_LOG", "warn")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}{}",
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(stdout.contains("BDUSS, STOKEN, BAIDUID"), "{}", stdout);
+
+    let config: Config =
+        toml::from_str(&std::fs::read_to_string(dir.join("config.toml")).unwrap()).unwrap();
+    let account = &config.account_configs().unwrap()[0];
+    assert_eq!(account.name, "browser");
+    assert_eq!(account.cookie_bduss, BDUSS);
+    assert_eq!(account.cookie_stoken, STOKEN);
+
+    let jar = std::fs::read_to_string(dir.join("data/cookies/browser.json")).unwrap();
+    assert!(jar.contains("fake-baiduid"), "{}", jar);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cli_encrypted_secrets_file() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let url = fake.url();
+    let dir = std::env::temp_dir().join(format!("bdl-secrets-{}", fake.addr.port()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let plain = dir.join("secrets.toml");
+    let encrypted = dir.join("secrets.enc");
+    std::fs::write(
+        &plain,
+        format!(
+            "cookie_bduss = \"{}\"\ncookie_stoken = \"{}\"\n",
+            BDUSS, STOKEN
+        ),
+    )
+    .unwrap();
+
+    let cli = move |args: Vec<std::ffi::OsString>, envs: Vec<(&'static str, String)>| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_baidu-direct-link"))
+            .args(args)
+            .env_clear()
+            .env("RUST_LOG", "warn")
+            .envs(envs)
+            .output()
+            .unwrap()
+    };
+
+    let output = cli(
+        vec![
+            "secrets".into(),
+            "encrypt".into(),
+            plain.clone().into(),
+            encrypted.clone().into(),
+        ],
+        vec![("SECRETS_PASSPHRASE", "hunter2".into())],
+    );
+    assert!(output.status.success(), "{:?}", output);
+    let data = std::fs::read_to_string(&encrypted).unwrap();
+    assert!(!data.contains(BDUSS));
+
+    // 只通过加密文件提供 Cookie
+    let passphrase_file = dir.join("passphrase");
+    std::fs::write(&passphrase_file, "hunter2\n").unwrap();
+    let envs = vec![
+        ("CONFIG_PATH", dir.join("config.toml").display().to_string()),
+        ("DATA_DIR", dir.join("data").display().to_string()),
+        ("SECRETS_FILE", encrypted.display().to_string()),
+        (
+            "SECRETS_PASSPHRASE_FILE",
+            passphrase_file.display().to_string(),
+        ),
+        ("BAIDU_PAN_URL", url.clone()),
+        ("BAIDU_PASSPORT_URL", url.clone()),
+        ("BAIDU_PCS_URL", url.clone()),
+    ];
+    let output = tokio::task::spawn_blocking({
+        let envs = envs.clone();
+        move || cli(vec!["whoami".into()], envs)
+    })
+    .await
+    .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    // 口令错误时拒绝启动
+    let mut wrong = envs.clone();
+    wrong.retain(|(k, _)| *k != "SECRETS_PASSPHRASE_FILE");
+    wrong.push(("SECRETS_PASSPHRASE", "wrong".into()));
+    let output = tokio::task::spawn_blocking(move || cli(vec!["whoami".into()], wrong))
+        .await
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("解密失败"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cli_credentials_from_env_files() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let url = fake.url();
+    let dir = std::env::temp_dir().join(format!("bdl-env-files-{}", fake.addr.port()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("bduss"), format!("{}\n", BDUSS)).unwrap();
+    std::fs::write(dir.join("stoken"), format!("{}\n", STOKEN)).unwrap();
+
+    let output = tokio::task::spawn_blocking({
+        let dir = dir.clone();
+        move || {
+            std::process::Command::new(env!("CARGO_BIN_EXE_baidu-direct-link"))
+                .arg("whoami")
+                .env_clear()
+                .env("RUST_LOG", "warn")
+                .env("CONFIG_PATH", dir.join("config.toml"))
+                .env("DATA_DIR", dir.join("data"))
+                .env("BDUSS_FILE", dir.join("bduss"))
+                .env("STOKEN_FILE", dir.join("stoken"))
+                .env("BAIDU_PAN_URL", &url)
+                .env("BAIDU_PASSPORT_URL", &url)
+                .env("BAIDU_PCS_URL", &url)
+                .output()
+                .unwrap()
+        }
+    })
+    .await
+    .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_reload_swaps_state_and_rejects_invalid_config() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let dir = std::env::temp_dir().join(format!("bdl-reload-{}", fake.addr.port()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+    let write_config = |save_path: &str, bduss: &str| {
+        let mut config = config_for(&fake);
+        config.baidu.save_path = save_path.to_string();
+        config.baidu.cookie_bduss = bduss.to_string();
+        std::fs::write(&path, toml::to_string(&config).unwrap()).unwrap();
+    };
+
+    write_config(SAVE_PATH, BDUSS);
+    let config = Config::load(path.to_str().unwrap()).unwrap();
+    let shared = baidu_direct_link::SharedState::new(Arc::new(AppState::new(config).unwrap()));
+    let before = shared.load();
+
+    // 未变化时不重建
+    assert!(baidu_direct_link::reload::reload(&shared)
+        .unwrap()
+        .is_empty());
+    assert!(Arc::ptr_eq(&before, &shared.load()));
+
+    write_config("/新目录", BDUSS);
+    let changes = baidu_direct_link::reload::reload(&shared).unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].key, "baidu.save_path");
+    assert_eq!(shared.load().default_account().save_path, "/新目录");
+    // 进行中的请求持有的旧快照不受影响
+    assert_eq!(before.default_account().save_path, SAVE_PATH);
+
+    // BDUSS 过短：拒绝新配置，保留旧状态
+    write_config("/另一个目录", "short");
+    assert!(baidu_direct_link::reload::reload(&shared).is_err());
+    assert_eq!(shared.load().default_account().save_path, "/新目录");
+
+    // 文件监视与 SIGHUP 在阻塞线程池中重载
+    write_config("/第三个目录", BDUSS);
+    baidu_direct_link::reload::reload_and_log(&shared, "测试").await;
+    assert_eq!(shared.load().default_account().save_path, "/第三个目录");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_doctor_reports_sources_and_checks() {
+    use baidu_direct_link::doctor::{self, Source, Status};
+
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let dir = std::env::temp_dir().join(format!("bdl-doctor-{}", fake.addr.port()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+    let mut config = config_for(&fake);
+    config.web.bind = "127.0.0.1".to_string();
+    config.web.port = 0;
+    let mut file = toml::Value::try_from(config).unwrap();
+    file.as_table_mut().unwrap().remove("pool");
+    std::fs::write(&path, toml::to_string(&file).unwrap()).unwrap();
+
+    let report = doctor::run(path.to_str().unwrap()).await;
+    assert!(report.load_error.is_none());
+    assert!(report.sources.contains(&("baidu.save_path", Source::File)));
+    assert!(report.sources.contains(&("pool.strategy", Source::Default)));
+
+    let status = |name: &str| {
+        report
+            .checks
+            .iter()
+            .find(|c| c.name == name)
+            .unwrap_or_else(|| panic!("缺少检查项 {}: {:?}", name, report.checks))
+            .status
+    };
+    assert_eq!(status("Web 密码"), Status::Warn);
+    assert_eq!(status("监听地址"), Status::Ok);
+    assert_eq!(status("网络连通"), Status::Ok);
+    assert_eq!(status("账号 default Cookie"), Status::Ok);
+    assert_eq!(status("账号 default 保存路径"), Status::Ok);
+    assert_eq!(report.exit_code(false), 0);
+    assert_eq!(report.exit_code(true), 1);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cli_doctor_exit_codes() {
+    let fake = FakeBaidu::start(Scenario::CookieExpired).await;
+    let output = run_cli(&fake, &["doctor"]).await;
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("❌ 账号 default Cookie"));
+
+    // 配置无法加载
+    let dir = std::env::temp_dir().join(format!("bdl-doctor-cli-{}", fake.addr.port()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), "[baidu\n").unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_baidu-direct-link"))
+        .arg("doctor")
+        .env_clear()
+        .env("CONFIG_PATH", dir.join("config.toml"))
+        .env("RUST_LOG", "warn")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2), "{:?}", output);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_requests_go_through_proxy() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+
+    // 接口地址无法直接解析，只有经由代理（假服务器同时充当 HTTP 代理）才能访问
+    let mut config = config_for(&fake);
+    config.baidu.endpoints = baidupcs::Endpoints::all("http://pan.baidu.invalid");
+    config.baidu.proxy.url = Some(fake.url());
+    let state = AppState::new(config).unwrap();
+    let info = baidupcs::whoami(&state.default_account()).await.unwrap();
+    assert_eq!(info.username, "fake_user");
+}
+
+#[tokio::test]
+async fn test_request_profile_headers_and_cookies() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let mut config = config_for(&fake);
+    config.baidu.profile = "app".to_string();
+    let custom: baidu_direct_link::config::RequestProfile = toml::from_str(
+        r#"
+        user_agent = "netdisk;99.0.0"
+        headers = { "X-Client" = "fake" }
+        cookies = { BAIDUID = "fake-baiduid" }
+        "#,
+    )
+    .unwrap();
+    config.baidu.profiles.insert("app".to_string(), custom);
+    let state = AppState::new(config.clone()).unwrap();
+    baidupcs::whoami(&state.default_account()).await.unwrap();
+
+    let headers = fake.uinfo_headers().unwrap();
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+    };
+    assert_eq!(header("user-agent"), "netdisk;99.0.0");
+    assert_eq!(header("x-client"), "fake");
+    assert!(header("cookie").contains("BAIDUID=fake-baiduid"));
+    assert!(header("cookie").contains("BDUSS="));
+
+    // 未定义的请求配置在构建账号时报错
+    config.baidu.profile = "missing".to_string();
+    let err = AppState::new(config).err().unwrap();
+    assert!(format!("{:#}", err).contains("missing"), "{:#}", err);
+}
+
+#[tokio::test]
+async fn test_proxy_failure_is_reported_as_proxy_error() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    // 绑定后立即释放，得到一个没有监听的端口
+    let closed = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let mut config = config_for(&fake);
+    config.baidu.proxy.url = Some(format!("http://user:secret@{}", closed));
+    let state = AppState::new(config.clone()).unwrap();
+    let err = baidupcs::whoami(&state.default_account())
+        .await
+        .unwrap_err();
+    match err.downcast_ref::<baidupcs::BaiduError>() {
+        Some(baidupcs::BaiduError::Proxy { proxy, .. }) => {
+            assert_eq!(proxy, &format!("http://{}", closed));
+        }
+        other => panic!("期望代理错误，实际: {:?} / {:#}", other, err),
+    }
+    assert!(!err.to_string().contains("secret"));
+
+    // 命中 no_proxy 的主机直连，不受代理影响
+    config.baidu.proxy.no_proxy = vec!["127.0.0.1".to_string()];
+    let state = AppState::new(config).unwrap();
+    assert!(baidupcs::whoami(&state.default_account()).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_web_over_tls_with_secure_cookie_and_cert_reload() {
+    use baidu_direct_link::{config::TlsConfig, server};
+
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tls");
+    let dir = std::env::temp_dir().join(format!("bdl-tls-{}", fake.addr.port()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy(fixtures.join("cert.pem"), dir.join("cert.pem")).unwrap();
+    std::fs::copy(fixtures.join("key.pem"), dir.join("key.pem")).unwrap();
+
+    let mut config = config_for(&fake);
+    config.web.password = "secret".to_string();
+    config.web.bind = "127.0.0.1".to_string();
+    config.web.port = 0;
+    config.web.tls = Some(TlsConfig {
+        cert: dir.join("cert.pem"),
+        key: dir.join("key.pem"),
+    });
+    let listener = server::Listener::bind(&config.web).await.unwrap();
+    let base = listener.url();
+    assert!(base.starts_with("https://127.0.0.1:"), "{}", base);
+    let certs = listener.tls().unwrap();
+    let app = web::create_router(Arc::new(AppState::new(config).unwrap()));
+    tokio::spawn(server::serve(listener, app, std::future::pending()));
+
+    let client_trusting = |name: &str| {
+        let pem = std::fs::read(fixtures.join(name)).unwrap();
+        reqwest::Client::builder()
+            .tls_built_in_root_certs(false)
+            .add_root_certificate(reqwest::Certificate::from_pem(&pem).unwrap())
+            .build()
+            .unwrap()
+    };
+    let resp = client_trusting("cert.pem")
+        .post(format!("{}/api/login", base))
+        .json(&serde_json::json!({ "password": "secret" }))
+        .send()
+        .await
+        .unwrap();
+    let cookie = resp.headers()["set-cookie"].to_str().unwrap().to_string();
+    assert!(cookie.contains("Secure"), "{}", cookie);
+
+    // 续期后的证书写入原路径，重新加载后新连接使用新证书
+    std::fs::copy(fixtures.join("renewed-cert.pem"), dir.join("cert.pem")).unwrap();
+    std::fs::copy(fixtures.join("renewed-key.pem"), dir.join("key.pem")).unwrap();
+    certs.reload().unwrap();
+    let health = client_trusting("renewed-cert.pem")
+        .get(format!("{}/health", base))
+        .send()
+        .await
+        .unwrap();
+    assert!(health.status().is_success());
+
+    // 无效证书不替换当前证书
+    std::fs::write(dir.join("cert.pem"), "not a certificate").unwrap();
+    assert!(certs.reload().is_err());
+    assert!(client_trusting("renewed-cert.pem")
+        .get(format!("{}/health", base))
+        .send()
+        .await
+        .is_ok());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_web_over_unix_socket() {
+    use baidu_direct_link::server;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let path = std::env::temp_dir().join(format!("bdl-{}.sock", fake.addr.port()));
+    // 残留的 socket 文件会被替换
+    std::fs::write(&path, "").unwrap();
+
+    let mut config = config_for(&fake);
+    config.web.unix_socket = Some(path.clone());
+    let listener = server::Listener::bind(&config.web).await.unwrap();
+    assert_eq!(listener.url(), format!("unix:{}", path.display()));
+    let app = web::create_router(Arc::new(AppState::new(config).unwrap()));
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let handle = tokio::spawn(server::serve(listener, app, async {
+        let _ = stopped.await;
+    }));
+
+    let mut stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+    stream
+        .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.contains("\"status\""), "{}", response);
+
+    // 退出时删除 socket 文件
+    stop.send(()).unwrap();
+    handle.await.unwrap().unwrap();
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn test_config_rejects_tls_with_unix_socket() {
+    use baidu_direct_link::config::TlsConfig;
+
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let dir = std::env::temp_dir().join(format!("bdl-unix-tls-{}", fake.addr.port()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+
+    let mut config = config_for(&fake);
+    config.web.unix_socket = Some(dir.join("web.sock"));
+    config.web.tls = Some(TlsConfig {
+        cert: dir.join("cert.pem"),
+        key: dir.join("key.pem"),
+    });
+    std::fs::write(&path, toml::to_string(&config).unwrap()).unwrap();
+    let err = Config::load(path.to_str().unwrap()).unwrap_err();
+    assert!(err.to_string().contains("unix_socket"), "{}", err);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_web_sessions_login_revoke_and_forged_cookie() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let mut config = config_for(&fake);
+    config.web.password = "secret".to_string();
+    let base = start_web_with(AppState::new(config).unwrap()).await;
+
+    let quota = |cookie: String| {
+        let base = base.clone();
+        async move {
+            reqwest::Client::new()
+                .get(format!("{}/api/quota", base))
+                .header("Cookie", cookie)
+                .send()
+                .await
+                .unwrap()
+                .json::<serde_json::Value>()
+                .await
+                .unwrap()
+        }
+    };
+    let login = |ua: &'static str| {
+        let request = login_request(&base, "", "secret").header("User-Agent", ua);
+        async move { session_of(request.send().await.unwrap()).await.1 }
+    };
+
+    // 旧版本的固定令牌不再被接受
+    let forged = quota("baidupcs_auth=baidupcs_auth_token".to_string()).await;
+    assert_eq!(forged["success"], false);
+
+    let (laptop, laptop_csrf) = login("laptop").await;
+    let (phone, _) = login("phone").await;
+    assert_ne!(laptop, phone);
+    assert_eq!(quota(laptop.clone()).await["success"], true);
+
+    let sessions: serde_json::Value = reqwest::Client::new()
+        .get(format!("{}/api/sessions", base))
+        .header("Cookie", &laptop)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let list = sessions["sessions"].as_array().unwrap();
+    assert_eq!(list.len(), 2);
+    let phone_session = list.iter().find(|s| s["user_agent"] == "phone").unwrap();
+    assert_eq!(phone_session["current"], false);
+
+    // 从笔记本注销手机的会话
+    let revoked: serde_json::Value = reqwest::Client::new()
+        .delete(format!(
+            "{}/api/sessions/{}",
+            base,
+            phone_session["id"].as_str().unwrap()
+        ))
+        .header("Cookie", &laptop)
+        .header("X-CSRF-Token", &laptop_csrf)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(revoked["success"], true);
+    assert_eq!(quota(phone).await["success"], false);
+
+    // 登出后令牌失效
+    reqwest::Client::new()
+        .post(format!("{}/api/logout", base))
+        .header("Cookie", &laptop)
+        .header("X-CSRF-Token", &laptop_csrf)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(quota(laptop).await["success"], false);
+}
+
+#[tokio::test]
+async fn test_password_change_on_reload_revokes_sessions() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let dir = std::env::temp_dir().join(format!("bdl-sessions-{}", fake.addr.port()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+    let write_config = |save_path: &str, password: &str| {
+        let mut config = config_for(&fake);
+        config.baidu.save_path = save_path.to_string();
+        config.web.password = password.to_string();
+        std::fs::write(&path, toml::to_string(&config).unwrap()).unwrap();
+    };
+
+    write_config(SAVE_PATH, "old");
+    let config = Config::load(path.to_str().unwrap()).unwrap();
+    let shared = baidu_direct_link::SharedState::new(Arc::new(AppState::new(config).unwrap()));
+    let (token, _) = {
+        let state = shared.load();
+        state.sessions.create(&state.config.web, None)
+    };
+    let valid = |shared: &baidu_direct_link::SharedState| {
+        let state = shared.load();
+        state.sessions.touch(&token, &state.config.web).is_some()
+    };
+
+    // 其他配置变化时会话保留
+    write_config("/新目录", "old");
+    baidu_direct_link::reload::reload(&shared).unwrap();
+    assert!(valid(&shared));
+
+    write_config("/新目录", "new");
+    baidu_direct_link::reload::reload(&shared).unwrap();
+    assert!(!valid(&shared));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cli_hash_password_and_hashed_login() {
+    use std::io::Write;
+
+    let output = tokio::task::spawn_blocking(|| {
+        let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_baidu-direct-link"))
+            .arg("hash-password")
+            .env_clear()
+            .env("RUST_LOG", "warn")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(b"correct horse\n")
+            .unwrap();
+        child.wait_with_output().unwrap()
+    })
+    .await
+    .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let hash = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    assert!(hash.starts_with("$argon2id$"), "{}", hash);
+
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let mut config = config_for(&fake);
+    config.web.password_hash = hash;
+    let base = start_web_with(AppState::new(config).unwrap()).await;
+    assert_eq!(try_login(&base, "", "wrong").await.0["success"], false);
+    login(&base, "", "correct horse").await;
+
+    // 未登录时受保护的接口返回 401
+    let resp = reqwest::get(format!("{}/api/quota", base)).await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let quota: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(quota["success"], false);
+}
+
+#[tokio::test]
+async fn test_api_tokens_scopes_status_codes_and_usage() {
+    use baidu_direct_link::config::{ApiTokenConfig, Scope};
+
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let dir = std::env::temp_dir().join(format!("bdl-tokens-{}", fake.addr.port()));
+    let mut config = config_for(&fake);
+    config.web.password = "secret".to_string();
+    config.storage.data_dir = dir.clone();
+    config.web.tokens = [("ci", Scope::Transfer), ("ops", Scope::Admin)]
+        .into_iter()
+        .map(|(name, scope)| ApiTokenConfig {
+            name: name.to_string(),
+            token: format!("{}-token-0123456789", name),
+            scopes: vec![scope],
+        })
+        .collect();
+    let state = Arc::new(AppState::new(config).unwrap());
+    let base = start_web_with_shared(state.clone()).await;
+
+    let request = |method: reqwest::Method, path: &str, token: &str| {
+        let req = reqwest::Client::new().request(method, format!("{}{}", base, path));
+        if token.is_empty() {
+            req
+        } else {
+            req.bearer_auth(token)
+        }
+    };
+
+    // 未认证：401；令牌无效：401
+    let resp = request(reqwest::Method::GET, "/api/accounts", "")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let resp = request(
+        reqwest::Method::GET,
+        "/api/accounts",
+        "wrong-token-0123456789",
+    )
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+    assert_eq!(resp.headers()["www-authenticate"], "Bearer");
+
+    // transfer 令牌可以转存，但不能管理文件或查看会话
+    let resp = request(
+        reqwest::Method::POST,
+        "/api/transfer",
+        "ci-token-0123456789",
+    )
+    .json(&serde_json::json!({ "share_url": SHARE_URL, "pwd": fake.pwd() }))
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+    let res: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(res["success"], true, "{}", res);
+    assert_eq!(fake.transfers().len(), 1);
+    for (method, path) in [
+        (reqwest::Method::GET, "/api/fs/list"),
+        (reqwest::Method::GET, "/api/sessions"),
+        (reqwest::Method::GET, "/api/tokens"),
+    ] {
+        let resp = request(method, path, "ci-token-0123456789")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::FORBIDDEN, "{}", path);
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(body["success"], false);
+    }
+
+    // admin 令牌可以查看令牌使用情况（不返回令牌本身）
+    let resp = request(reqwest::Method::GET, "/api/tokens", "ops-token-0123456789")
+        .send()
+        .await
+        .unwrap();
+    let body = resp.text().await.unwrap();
+    assert!(!body.contains("ci-token-0123456789"), "{}", body);
+    let tokens: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let ci = &tokens["tokens"][0];
+    assert_eq!(ci["name"], "ci");
+    assert_eq!(ci["scopes"], serde_json::json!(["transfer"]));
+    assert_eq!(ci["use_count"], 4);
+    assert_eq!(ci["last_route"], "GET /api/tokens");
+    assert!(ci["last_used_at"].is_string());
+
+    // 使用记录保存到数据目录，重启后保留
+    state.save_token_usage();
+    let restarted = baidu_direct_link::auth::TokenStore::load(dir.join("api_tokens.json"));
+    assert_eq!(restarted.usage("ci").use_count, 4);
+    assert_eq!(restarted.usage("ops").use_count, 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_web_users_roles_roots_and_quota() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    fake.add_dir("/团队/alice");
+    let dir = std::env::temp_dir().join(format!("bdl-users-{}", fake.addr.port()));
+    let mut config = config_for(&fake);
+    config.web.password = "admin-secret".to_string();
+    config.storage.data_dir = dir.clone();
+    let base = start_web_with(AppState::new(config.clone()).unwrap()).await;
+
+    let transfer = |save_path: Option<&str>, dry_run: bool| {
+        serde_json::json!({
+            "share_url": SHARE_URL, "pwd": fake.pwd(), "save_path": save_path, "dry_run": dry_run,
+        })
+    };
+
+    // 管理员（[web] 密码）创建用户
+    let admin = login(&base, "", "admin-secret").await;
+    let (_, res) = session_call(
+        &base,
+        reqwest::Method::POST,
+        "/api/users",
+        &admin,
+        serde_json::json!({
+            "name": "alice", "password": "alice-password", "role": "user",
+            "save_roots": ["/团队/alice"], "accounts": ["bogus"],
+        }),
+    )
+    .await;
+    assert_eq!(res["success"], false, "{}", res);
+    for user in [
+        serde_json::json!({
+            "name": "alice", "password": "alice-password", "role": "user",
+            "save_roots": ["/团队/alice"], "daily_quota_bytes": 2 * 1024 * 1024,
+        }),
+        serde_json::json!({ "name": "bob", "password": "bob-password", "role": "read_only" }),
+    ] {
+        let (_, res) = session_call(&base, reqwest::Method::POST, "/api/users", &admin, user).await;
+        assert_eq!(res["success"], true, "{}", res);
+    }
+    let (_, users) = session_call(
+        &base,
+        reqwest::Method::GET,
+        "/api/users",
+        &admin,
+        Default::default(),
+    )
+    .await;
+    assert_eq!(users["users"].as_array().unwrap().len(), 2);
+    assert!(!users.to_string().contains("argon2"));
+
+    let (res, _) = try_login(&base, "alice", "wrong-password").await;
+    assert_eq!(res["success"], false);
+    let (res, alice) = try_login(&base, "alice", "alice-password").await;
+    assert_eq!(res["success"], true, "{}", res);
+
+    // 普通用户：默认保存到第一个允许目录，其他目录和用户管理返回 403
+    let (status, me) = session_call(
+        &base,
+        reqwest::Method::GET,
+        "/api/me",
+        &alice,
+        Default::default(),
+    )
+    .await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+    assert_eq!(me["admin"], false);
+    assert_eq!(me["user"]["name"], "alice");
+    let (status, res) = session_call(
+        &base,
+        reqwest::Method::POST,
+        "/api/transfer",
+        &alice,
+        transfer(None, false),
+    )
+    .await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+    assert_eq!(res["success"], true, "{}", res);
+    assert_eq!(fake.transfers()[0].path, "/团队/alice");
+    for (method, path, body) in [
+        (
+            reqwest::Method::POST,
+            "/api/transfer",
+            transfer(Some(SAVE_PATH), false),
+        ),
+        (
+            reqwest::Method::POST,
+            "/api/transfer",
+            transfer(Some("/团队/alice/../bob"), false),
+        ),
+        (
+            reqwest::Method::GET,
+            "/api/fs/list?dir=/",
+            Default::default(),
+        ),
+        (reqwest::Method::GET, "/api/users", Default::default()),
+        // 保存目录本身不能被删除、移走或改名
+        (
+            reqwest::Method::POST,
+            "/api/fs/delete",
+            serde_json::json!({ "paths": ["/团队/alice"] }),
+        ),
+        (
+            reqwest::Method::POST,
+            "/api/fs/move",
+            serde_json::json!({ "paths": ["/团队/alice/"], "dest": "/团队/alice/备份" }),
+        ),
+        (
+            reqwest::Method::POST,
+            "/api/fs/rename",
+            serde_json::json!({ "path": "/团队/alice", "newname": "alice2" }),
+        ),
+    ] {
+        let (status, res) = session_call(&base, method, path, &alice, body).await;
+        assert_eq!(status, reqwest::StatusCode::FORBIDDEN, "{} {}", path, res);
+    }
+    let (status, _) = session_call(
+        &base,
+        reqwest::Method::GET,
+        "/api/fs/list?dir=/团队/alice",
+        &alice,
+        Default::default(),
+    )
+    .await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+
+    // 每日额度：剩余额度不足时不执行转存，预览不受影响
+    let (_, res) = session_call(
+        &base,
+        reqwest::Method::POST,
+        "/api/transfer",
+        &alice,
+        transfer(None, false),
+    )
+    .await;
+    assert_eq!(res["success"], false);
+    assert!(res["message"].as_str().unwrap().contains("额度"), "{}", res);
+    assert_eq!(fake.transfers().len(), 1);
+    let (_, res) = session_call(
+        &base,
+        reqwest::Method::POST,
+        "/api/transfer",
+        &alice,
+        transfer(None, true),
+    )
+    .await;
+    assert_eq!(res["success"], true, "{}", res);
+
+    // 只读用户只能预览
+    let bob = login(&base, "bob", "bob-password").await;
+    let (status, _) = session_call(
+        &base,
+        reqwest::Method::POST,
+        "/api/transfer",
+        &bob,
+        transfer(None, false),
+    )
+    .await;
+    assert_eq!(status, reqwest::StatusCode::FORBIDDEN);
+    let (status, res) = session_call(
+        &base,
+        reqwest::Method::POST,
+        "/api/transfer",
+        &bob,
+        transfer(None, true),
+    )
+    .await;
+    assert_eq!(status, reqwest::StatusCode::OK);
+    assert_eq!(res["plan"]["save_path"], SAVE_PATH);
+
+    // 用户保存在数据目录中，重启后仍可登录；删除用户后其会话立即失效
+    let restarted = AppState::new(config).unwrap();
+    assert!(restarted
+        .users
+        .authenticate("alice", "alice-password")
+        .is_some());
+    assert!(restarted.users.get("alice").unwrap().used_today() > 0);
+    let (_, res) = session_call(
+        &base,
+        reqwest::Method::DELETE,
+        "/api/users/alice",
+        &admin,
+        Default::default(),
+    )
+    .await;
+    assert_eq!(res["success"], true, "{}", res);
+    let (status, _) = session_call(
+        &base,
+        reqwest::Method::GET,
+        "/api/me",
+        &alice,
+        Default::default(),
+    )
+    .await;
+    assert_eq!(status, reqwest::StatusCode::UNAUTHORIZED);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_web_user_quota_reserved_for_concurrent_jobs_and_sized_folders() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let dir = std::env::temp_dir().join(format!("bdl-quota-{}", fake.addr.port()));
+    let mut config = config_for(&fake);
+    config.web.password = "admin-secret".to_string();
+    config.storage.data_dir = dir.clone();
+    let base = start_web_with(AppState::new(config).unwrap()).await;
+
+    let transfer = serde_json::json!({ "share_url": SHARE_URL, "pwd": fake.pwd() });
+
+    // 额度只够一次转存（分享共 1 MiB + 2 KiB）
+    let admin = login(&base, "", "admin-secret").await;
+    let carol = serde_json::json!({
+        "name": "carol", "password": "carol-password", "role": "user",
+        "daily_quota_bytes": 1536 * 1024,
+    });
+    let res = post_json(&base, &admin, "/api/users", carol).await;
+    assert_eq!(res["success"], true, "{}", res);
+    let carol = login(&base, "carol", "carol-password").await;
+
+    // 同时提交两个任务：提交时剩余额度都足够，只有先预留到额度的任务能转存
+    let (a, b) = tokio::join!(
+        session_request(reqwest::Method::POST, &base, "/api/jobs", &carol)
+            .json(&transfer)
+            .send(),
+        session_request(reqwest::Method::POST, &base, "/api/jobs", &carol)
+            .json(&transfer)
+            .send()
+    );
+    let mut statuses = Vec::new();
+    for resp in [a.unwrap(), b.unwrap()] {
+        assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+        let body: serde_json::Value = resp.json().await.unwrap();
+        let id = body["job"]["id"].as_str().unwrap().to_string();
+        let mut job = serde_json::Value::Null;
+        for _ in 0..100 {
+            let res = get_json(&base, &carol, &format!("/api/jobs/{}", id)).await;
+            job = res["job"].clone();
+            if job["status"] == "succeeded" || job["status"] == "failed" {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        statuses.push(job);
+    }
+    let succeeded = statuses
+        .iter()
+        .filter(|j| j["status"] == "succeeded")
+        .count();
+    assert_eq!(succeeded, 1, "{:?}", statuses);
+    let failed = statuses.iter().find(|j| j["status"] == "failed").unwrap();
+    assert!(
+        failed["error"].as_str().unwrap().contains("额度"),
+        "{}",
+        failed
+    );
+    assert_eq!(fake.transfers().len(), 1);
+    let me = get_json(&base, &carol, "/api/me").await;
+    assert_eq!(me["user"]["used_today_bytes"], 1024 * 1024 + 2048, "{}", me);
+
+    // 分享中的文件夹递归统计大小：额度差一个字节时拒绝，够用时按实际大小计入
+    fake.share_dir(2001, "合集", 4096);
+    let total = 1024 * 1024 + 2048 + 4096;
+    let dave = serde_json::json!({
+        "name": "dave", "password": "dave-password", "role": "user",
+        "daily_quota_bytes": total - 1,
+    });
+    let res = post_json(&base, &admin, "/api/users", dave).await;
+    assert_eq!(res["success"], true, "{}", res);
+    let dave_session = login(&base, "dave", "dave-password").await;
+    let res = post_json(&base, &dave_session, "/api/transfer", transfer.clone()).await;
+    assert_eq!(res["success"], false, "{}", res);
+    assert!(res["message"].as_str().unwrap().contains("额度"), "{}", res);
+    assert_eq!(fake.transfers().len(), 1);
+
+    let dave = serde_json::json!({ "name": "dave", "daily_quota_bytes": total });
+    let res = post_json(&base, &admin, "/api/users", dave).await;
+    assert_eq!(res["success"], true, "{}", res);
+    let res = post_json(&base, &dave_session, "/api/transfer", transfer).await;
+    assert_eq!(res["success"], true, "{}", res);
+    assert_eq!(fake.transfers().len(), 2);
+    let me = get_json(&base, &dave_session, "/api/me").await;
+    assert_eq!(me["user"]["used_today_bytes"], total, "{}", me);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_web_user_quota_charges_chunks_written_before_a_failure() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    // 600 个文件分两批转存，第二批失败
+    fake.set_files(600, 1000);
+    fake.fail_transfer(2);
+    let dir = std::env::temp_dir().join(format!("bdl-chunks-{}", fake.addr.port()));
+    let mut config = config_for(&fake);
+    config.web.password = "admin-secret".to_string();
+    config.storage.data_dir = dir.clone();
+    let base = start_web_with(AppState::new(config).unwrap()).await;
+
+    let admin = login(&base, "", "admin-secret").await;
+    let erin = serde_json::json!({
+        "name": "erin", "password": "erin-password", "role": "user",
+        "daily_quota_bytes": 1_100_000,
+    });
+    let res = post_json(&base, &admin, "/api/users", erin).await;
+    assert_eq!(res["success"], true, "{}", res);
+    let erin = login(&base, "erin", "erin-password").await;
+
+    let transfer = serde_json::json!({ "share_url": SHARE_URL, "pwd": fake.pwd() });
+    let res = post_json(&base, &erin, "/api/transfer", transfer.clone()).await;
+    assert_eq!(res["success"], false, "{}", res);
+    assert_eq!(fake.transfers().len(), 2);
+
+    // 第一批已写入网盘，计入额度；失败的第二批和剩余预留被释放，剩余额度正好够再转存一次
+    let me = get_json(&base, &erin, "/api/me").await;
+    assert_eq!(me["user"]["used_today_bytes"], 500 * 1000, "{}", me);
+    let res = post_json(&base, &erin, "/api/transfer", transfer.clone()).await;
+    assert_eq!(res["success"], true, "{}", res);
+    let res = post_json(&base, &erin, "/api/transfer", transfer).await;
+    assert_eq!(res["success"], false, "{}", res);
+    assert!(res["message"].as_str().unwrap().contains("额度"), "{}", res);
+    assert_eq!(fake.transfers().len(), 4);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_rate_limit_budgets_and_trusted_proxies() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let start = |trusted: &str| {
+        let mut config = config_for(&fake);
+        config.web.rate_limit = toml::from_str(&format!(
+            r#"
+            trusted_proxies = [{}]
+            [routes]
+            "/api/" = {{ requests = 100, per_secs = 60 }}
+            "/api/transfer" = {{ requests = 2, per_secs = 60 }}
+            "#,
+            trusted
+        ))
+        .unwrap();
+        start_web_with(AppState::new(config).unwrap())
+    };
+    let pwd = fake.pwd();
+    let preview = |base: &str, client: &str| {
+        let req = reqwest::Client::new()
+            .post(format!("{}/api/transfer", base))
+            .header("x-forwarded-for", client)
+            .json(&serde_json::json!({ "share_url": SHARE_URL, "pwd": pwd, "dry_run": true }));
+        async move { req.send().await.unwrap() }
+    };
+
+    // 经可信代理：按 X-Forwarded-For 中的客户端分别计数
+    let base = start("\"127.0.0.1\"").await;
+    for _ in 0..2 {
+        assert_eq!(preview(&base, "1.1.1.1").await.status(), 200);
+    }
+    let resp = preview(&base, "1.1.1.1").await;
+    assert_eq!(resp.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    let retry: u64 = resp.headers()["retry-after"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=30).contains(&retry), "{}", retry);
+    let res: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(res["success"], false);
+    assert_eq!(preview(&base, "2.2.2.2").await.status(), 200);
+    // 其他路由使用各自的预算
+    let quota = reqwest::get(format!("{}/api/quota", base)).await.unwrap();
+    assert_ne!(quota.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+
+    // 直连地址不是可信代理：伪造的 X-Forwarded-For 被忽略，按直连地址计数
+    let base = start("").await;
+    assert_eq!(preview(&base, "3.3.3.3").await.status(), 200);
+    assert_eq!(preview(&base, "4.4.4.4").await.status(), 200);
+    assert_eq!(
+        preview(&base, "5.5.5.5").await.status(),
+        reqwest::StatusCode::TOO_MANY_REQUESTS
+    );
+}
+
+#[tokio::test]
+async fn test_login_lockout_exponential_backoff() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let mut config = config_for(&fake);
+    config.web.password = "secret".to_string();
+    config.web.rate_limit.login_max_failures = 2;
+    config.web.rate_limit.login_lockout_secs = 1;
+    let state = Arc::new(AppState::new(config).unwrap());
+    let base = start_web_with_shared(state.clone()).await;
+    let login = |password: &'static str| {
+        let request = login_request(&base, "", password);
+        async move { request.send().await.unwrap() }
+    };
+
+    // 第一次失败只返回错误，第二次失败触发锁定
+    let res: serde_json::Value = login("wrong").await.json().await.unwrap();
+    assert_eq!(res["success"], false);
+    let resp = login("wrong").await;
+    assert_eq!(resp.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(resp.headers()["retry-after"], "1");
+
+    // 锁定期间即使密码正确也不校验
+    let resp = login("secret").await;
+    assert_eq!(resp.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+
+    // 锁定到期后再次失败，锁定时长翻倍
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let resp = login("wrong").await;
+    assert_eq!(resp.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(resp.headers()["retry-after"], "2");
+    assert!(state.limiter.login_locked(&["admin".to_string()]).is_some());
+
+    // 登录成功后清除失败记录
+    tokio::time::sleep(std::time::Duration::from_millis(2100)).await;
+    let resp = login("secret").await;
+    assert!(resp.headers().contains_key("set-cookie"));
+    let res: serde_json::Value = login("wrong").await.json().await.unwrap();
+    assert_eq!(res["success"], false);
+}
+
+#[tokio::test]
+async fn test_csrf_token_origin_check_and_bearer_exemption() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let mut config = config_for(&fake);
+    config.web.password = "secret".to_string();
+    config.web.tokens = vec![baidu_direct_link::config::ApiTokenConfig {
+        name: "ci".to_string(),
+        token: "ci-token-0123456789".to_string(),
+        scopes: vec![baidu_direct_link::config::Scope::Transfer],
+    }];
+    let base = start_web_with(AppState::new(config).unwrap()).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .post(format!("{}/api/login", base))
+        .json(&serde_json::json!({ "password": "secret" }))
+        .send()
+        .await
+        .unwrap();
+    let set_cookie = resp.headers()["set-cookie"].to_str().unwrap().to_string();
+    assert!(set_cookie.contains("SameSite=Strict"), "{}", set_cookie);
+    let cookie = set_cookie.split(';').next().unwrap().to_string();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let csrf = body["csrf_token"].as_str().unwrap().to_string();
+
+    // 首页注入当前会话的令牌
+    let html = client
+        .get(&base)
+        .header("Cookie", &cookie)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(
+        html.contains(&format!(r#"<meta name="csrf-token" content="{}">"#, csrf)),
+        "{}",
+        html
+    );
+
+    let preview = |headers: &[(&str, &str)]| {
+        let mut req = client.post(format!("{}/api/transfer", base)).json(
+            &serde_json::json!({ "share_url": SHARE_URL, "pwd": fake.pwd(), "dry_run": true }),
+        );
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        async move { req.send().await.unwrap().status() }
+    };
+    let forbidden = reqwest::StatusCode::FORBIDDEN;
+
+    // 携带 Cookie 的修改类请求必须带上正确的令牌
+    assert_eq!(preview(&[("Cookie", &cookie)]).await, forbidden);
+    assert_eq!(
+        preview(&[("Cookie", &cookie), ("X-CSRF-Token", "forged")]).await,
+        forbidden
+    );
+    assert_eq!(
+        preview(&[("Cookie", &cookie), ("X-CSRF-Token", &csrf)]).await,
+        200
+    );
+    assert_eq!(
+        preview(&[
+            ("Cookie", &cookie),
+            ("X-CSRF-Token", &csrf),
+            ("Origin", &base)
+        ])
+        .await,
+        200
+    );
+
+    // 来自其他站点的请求即使带了令牌也被拒绝，包括登录
+    assert_eq!(
+        preview(&[
+            ("Cookie", &cookie),
+            ("X-CSRF-Token", &csrf),
+            ("Origin", "https://evil.example"),
+        ])
+        .await,
+        forbidden
+    );
+    let login = client
+        .post(format!("{}/api/login", base))
+        .header("Origin", "https://evil.example")
+        .json(&serde_json::json!({ "password": "secret" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(login.status(), forbidden);
+
+    // 带着仍有效的旧会话 Cookie 在登录页重新登录：登录页拿不到令牌，只检查来源
+    let relogin = client
+        .post(format!("{}/api/login", base))
+        .header("Cookie", &cookie)
+        .header("Origin", &base)
+        .json(&serde_json::json!({ "password": "secret" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(relogin.status(), 200);
+    let body: serde_json::Value = relogin.json().await.unwrap();
+    assert_eq!(body["success"], true, "{}", body);
+
+    // Bearer 令牌不依赖 Cookie，不做 CSRF 检查；认证方案不区分大小写
+    for scheme in ["Bearer", "bearer", "BEARER"] {
+        let authorization = format!("{} ci-token-0123456789", scheme);
+        assert_eq!(
+            preview(&[
+                ("Authorization", authorization.as_str()),
+                ("Origin", "https://evil.example"),
+            ])
+            .await,
+            200,
+            "{}",
+            scheme
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_web_jobs_submit_and_poll() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let base = start_web(&fake).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .post(format!("{}/api/jobs", base))
+        .json(&serde_json::json!({ "share_url": SHARE_URL, "pwd": fake.pwd() }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+    let submitted: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(submitted["success"], true, "{}", submitted);
+    let id = submitted["job"]["id"].as_str().unwrap().to_string();
+
+    // 轮询直到任务结束
+    let mut job = serde_json::Value::Null;
+    for _ in 0..100 {
+        let res: serde_json::Value = client
+            .get(format!("{}/api/jobs/{}", base, id))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        job = res["job"].clone();
+        if job["status"] == "succeeded" || job["status"] == "failed" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(job["status"], "succeeded", "{}", job);
+    assert_eq!(job["phase"], "transferring");
+    assert_eq!(job["progress"]["percent"], 100);
+    assert_eq!(job["result"]["save_path"], SAVE_PATH);
+    assert_eq!(job["progress"]["files"], job["result"]["file_count"]);
+    assert_eq!(fake.transfers().len(), 1);
+
+    // 校验失败的请求不创建任务
+    let res: serde_json::Value = client
+        .post(format!("{}/api/jobs", base))
+        .json(&serde_json::json!({ "share_url": "https://example.com/s/1x" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(res["success"], false);
+    assert!(res.get("job").is_none());
+
+    let missing = client
+        .get(format!("{}/api/jobs/unknown", base))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_web_job_submit_shares_transfer_budget() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let base = start_web(&fake).await;
+    let client = reqwest::Client::new();
+    let submit = || {
+        client
+            .post(format!("{}/api/jobs", base))
+            .json(
+                &serde_json::json!({ "share_url": SHARE_URL, "pwd": fake.pwd(), "dry_run": true }),
+            )
+            .send()
+    };
+
+    // 默认 "/api/transfer" 预算为每分钟 10 次
+    let mut id = String::new();
+    for _ in 0..10 {
+        let resp = submit().await.unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+        let body: serde_json::Value = resp.json().await.unwrap();
+        id = body["job"]["id"].as_str().unwrap().to_string();
+    }
+    let resp = submit().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    assert!(resp.headers().contains_key("retry-after"));
+
+    // 同步转存接口共用同一份预算
+    let transfer = client
+        .post(format!("{}/api/transfer", base))
+        .json(&serde_json::json!({ "share_url": SHARE_URL, "pwd": fake.pwd(), "dry_run": true }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(transfer.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+
+    // 查询和订阅进度不受转存预算限制
+    for _ in 0..20 {
+        let status = client
+            .get(format!("{}/api/jobs/{}", base, id))
+            .send()
+            .await
+            .unwrap()
+            .status();
+        assert_eq!(status, reqwest::StatusCode::OK);
+    }
+    let events = client
+        .get(format!("{}/api/jobs/{}/events", base, id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(events.status(), reqwest::StatusCode::OK);
+}
+
+/// 解析 SSE 响应体为 (event, data) 列表
+fn parse_sse(body: &str) -> Vec<(String, serde_json::Value)> {
+    body.split("\n\n")
+        .filter_map(|block| {
+            let mut event = String::new();
+            let mut data = None;
+            for line in block.lines() {
+                if let Some(v) = line.strip_prefix("event: ") {
+                    event = v.to_string();
+                } else if let Some(v) = line.strip_prefix("data: ") {
+                    data = Some(serde_json::from_str(v).unwrap());
+                }
+            }
+            Some((event, data?))
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_web_job_events_stream() {
+    let fake = FakeBaidu::start(Scenario::Ok).await;
+    let base = start_web(&fake).await;
+    let client = reqwest::Client::new();
+
+    let submitted: serde_json::Value = client
+        .post(format!("{}/api/jobs", base))
+        .json(&serde_json::json!({ "share_url": SHARE_URL, "pwd": fake.pwd() }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let id = submitted["job"]["id"].as_str().unwrap().to_string();
+
+    // 任务结束后服务端关闭连接，无论订阅时任务进行到哪一步都能收到完整的事件
+    let resp = client
+        .get(format!("{}/api/jobs/{}/events", base, id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+    assert!(resp.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/event-stream"));
+    let body = tokio::time::timeout(std::time::Duration::from_secs(10), resp.text())
+        .await
+        .expect("任务结束后应关闭事件流")
+        .unwrap();
+    let events = parse_sse(&body);
+
+    let progress: Vec<&serde_json::Value> = events
+        .iter()
+        .filter(|(event, _)| event == "progress")
+        .map(|(_, data)| data)
+        .collect();
+    let types: Vec<&str> = progress
+        .iter()
+        .map(|e| e["type"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        types,
+        vec![
+            "phase",
+            "phase",
+            "files_discovered",
+            "phase",
+            "phase",
+            "chunk_transferred"
+        ],
+        "{}",
+        body
+    );
+    assert_eq!(progress[0]["phase"], "share_info");
+    assert_eq!(progress[5]["index"], 1);
+    assert_eq!(progress[5]["total"], 1);
+
+    let (event, job) = events.last().unwrap();
+    assert_eq!(event, "job");
+    assert_eq!(job["status"], "succeeded", "{}", job);
+    assert_eq!(job["progress"]["transferred"], job["result"]["file_count"]);
+    assert_eq!(job["events"].as_array().unwrap().len(), progress.len());
+
+    let missing = client
+        .get(format!("{}/api/jobs/unknown/events", base))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+}
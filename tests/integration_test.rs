@@ -40,8 +40,3 @@ fn test_extract_surl_invalid() {
         );
     }
 }
-
-#[test]
-fn test_basic() {
-    assert_eq!(1 + 1, 2);
-}